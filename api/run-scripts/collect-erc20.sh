@@ -11,6 +11,7 @@ call_api() {
   # Make POST request using curl
   curl -X POST "$API_URL" \
     -H "Content-Type: application/json" \
+    -H "X-Api-Key: ${API_KEY}" \
     -d "$payload"
   
   echo
@@ -36,4 +37,11 @@ payload3='{
   "values": [10, 30, 60, 10, 50],
   "values_type": "Amount"
 }'
-call_api "$payload3"
\ No newline at end of file
+call_api "$payload3"
+# Case 4: Invalid request with entries but no token
+payload4='{
+  "entries": [
+    { "sender": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8", "amount": 100 }
+  ]
+}'
+call_api "$payload4"
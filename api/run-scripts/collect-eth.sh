@@ -11,6 +11,7 @@ call_api() {
   # Make POST request using curl
   curl -X POST "$API_URL" \
     -H "Content-Type: application/json" \
+    -H "X-Api-Key: ${API_KEY}" \
     -d "$payload"
   
   echo
@@ -37,4 +38,12 @@ payload3='{
   "values": [10, 30, 60],
   "values_type": "Percentage"
 }'
-call_api "$payload3"
\ No newline at end of file
+call_api "$payload3"
+# Case 4: Invalid request sweeping an unknown vault to a receiver
+payload4='{
+  "receiver": "0x90F79bf6EB2c4f870365E785982E1f101E93b906",
+  "vaults": [
+    { "vault": "unknown-label", "amount": "all" }
+  ]
+}'
+call_api "$payload4"
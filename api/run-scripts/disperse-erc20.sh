@@ -10,6 +10,7 @@ call_api() {
   
   curl -X POST "$API_URL" \
     -H "Content-Type: application/json" \
+    -H "X-Api-Key: ${API_KEY}" \
     -d "$payload"
   
   echo
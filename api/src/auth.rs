@@ -0,0 +1,201 @@
+use crate::config::ApiKeyConfig;
+use crate::error::ApiError;
+use axum::extract::{Request, State};
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tracing::Span;
+
+/// Configured API keys
+///
+/// Authentication is only disabled when no keys are configured and `allow_unauthenticated` is
+/// set, otherwise requests without a valid key are rejected.
+#[derive(Clone, Default)]
+pub struct ApiKeys {
+    keys: Vec<ApiKeyConfig>,
+    allow_unauthenticated: bool,
+}
+
+/// Key that authenticated the request, inserted into request extensions
+#[derive(Clone, Debug)]
+pub struct ApiKeyIdentity {
+    pub name: String,
+}
+
+/// Middleware state: the key set and the scope required by the route
+#[derive(Clone)]
+pub struct RequiredScope {
+    keys: Arc<ApiKeys>,
+    scope: &'static str,
+    allow_signed: bool,
+}
+
+impl ApiKeys {
+    pub fn new(keys: Vec<ApiKeyConfig>, allow_unauthenticated: bool) -> Self {
+        Self {
+            keys,
+            allow_unauthenticated,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty() || !self.allow_unauthenticated
+    }
+
+    fn find(&self, key: &str) -> Option<&ApiKeyConfig> {
+        let key_hash = hash_api_key(key);
+        self.keys
+            .iter()
+            .find(|config| config.key_hash.eq_ignore_ascii_case(&key_hash))
+    }
+}
+
+impl RequiredScope {
+    pub fn new(keys: Arc<ApiKeys>, scope: &'static str) -> Self {
+        Self {
+            keys,
+            scope,
+            allow_signed: false,
+        }
+    }
+
+    /// Lets requests without an API key through, the handler must verify their signature
+    pub fn allow_signed(mut self) -> Self {
+        self.allow_signed = true;
+        self
+    }
+}
+
+/// Hex-encoded SHA-256 of the key, as stored in `api_keys[].key_hash`
+pub fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// `collect:*` grants every `collect:` scope and `*` grants all of them
+pub fn scope_matches(granted: &str, required: &str) -> bool {
+    if granted == "*" || granted == required {
+        return true;
+    }
+
+    match granted.strip_suffix('*') {
+        Some(prefix) => prefix.ends_with(':') && required.starts_with(prefix),
+        None => false,
+    }
+}
+
+fn request_api_key(headers: &HeaderMap) -> Option<&str> {
+    if let Some(key) = headers.get("x-api-key") {
+        return key.to_str().ok();
+    }
+
+    headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+pub async fn authorize(
+    State(required): State<RequiredScope>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !required.keys.is_enabled() {
+        return next.run(request).await;
+    }
+
+    let Some(key) = request_api_key(request.headers()) else {
+        if required.allow_signed {
+            return next.run(request).await;
+        }
+        return ApiError::unauthorized("Missing API key").into_response();
+    };
+    let Some(config) = required.keys.find(key) else {
+        return ApiError::unauthorized("Invalid API key").into_response();
+    };
+
+    Span::current().record("api_key", config.name.as_str());
+    if !config
+        .scopes
+        .iter()
+        .any(|granted| scope_matches(granted, required.scope))
+    {
+        return ApiError::forbidden(format!("API key lacks scope {}", required.scope))
+            .into_response();
+    }
+
+    request.extensions_mut().insert(ApiKeyIdentity {
+        name: config.name.clone(),
+    });
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::{body::Body, middleware, routing::post, Router};
+    use tower::ServiceExt;
+
+    fn app(keys: ApiKeys) -> Router {
+        Router::new().route(
+            "/collect/eth",
+            post(|| async { "ok" }).route_layer(middleware::from_fn_with_state(
+                RequiredScope::new(Arc::new(keys), "collect:eth"),
+                authorize,
+            )),
+        )
+    }
+
+    async fn call(keys: ApiKeys, api_key: Option<&str>) -> StatusCode {
+        let mut request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/collect/eth");
+        if let Some(api_key) = api_key {
+            request = request.header("X-Api-Key", api_key);
+        }
+
+        app(keys)
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[test]
+    fn test_scope_matches() {
+        assert!(scope_matches("*", "collect:eth"));
+        assert!(scope_matches("collect:*", "collect:erc20"));
+        assert!(scope_matches("disperse:eth", "disperse:eth"));
+        assert!(!scope_matches("disperse:*", "collect:eth"));
+        assert!(!scope_matches("collect*", "collector:eth"));
+    }
+
+    #[tokio::test]
+    async fn test_authorize_statuses() {
+        let keys = ApiKeys::new(
+            vec![ApiKeyConfig {
+                name: "payroll".to_string(),
+                key_hash: hash_api_key("secret"),
+                scopes: vec!["disperse:*".to_string()],
+            }],
+            false,
+        );
+        assert_eq!(call(keys.clone(), None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            call(keys.clone(), Some("wrong")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(call(keys, Some("secret")).await, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_authorize_without_keys() {
+        let closed = ApiKeys::new(Vec::new(), false);
+        assert_eq!(call(closed, None).await, StatusCode::UNAUTHORIZED);
+
+        let open = ApiKeys::new(Vec::new(), true);
+        assert_eq!(call(open, None).await, StatusCode::OK);
+    }
+}
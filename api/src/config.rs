@@ -1,17 +1,246 @@
-use config::{Config, ConfigError, Environment};
-use serde::Deserialize;
+use config::{Config, ConfigError, Environment, File};
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use url::Url;
 
 #[derive(Deserialize)]
 pub struct AppConfig {
-    pub rpc_url: String,
     pub port: u16,
+    /// Single-chain shorthand, used when no `chains` are configured
+    pub rpc_url: Option<String>,
+    /// Default signer for chains that don't define their own `private_key`
+    pub private_key: Option<String>,
+    /// Chain used for requests without `chain_id`, defaults to the first chain
+    pub default_chain_id: Option<u64>,
+    #[serde(default)]
+    pub chains: Vec<ChainConfig>,
+    #[serde(default)]
+    pub log_format: LogFormat,
+    /// Log filter used when `RUST_LOG` is not set, defaults to `info`
+    pub log_level: Option<String>,
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyConfig>,
+    /// Serve payout routes without authentication when no `api_keys` are configured
+    #[serde(default)]
+    pub allow_unauthenticated: bool,
+    /// Wallets allowed to authorize disperse requests with EIP-712 signatures
+    #[serde(default)]
+    pub approvers: Vec<Address>,
+    /// File of blocked addresses, reloaded when it changes
+    pub blocklist_path: Option<PathBuf>,
+    /// Directory of the state that has to survive restarts, defaults to `data`
+    #[serde(default = "default_data_dir")]
+    pub data_dir: PathBuf,
+    /// Receives a POST with each deposit when it is detected and when it is confirmed
+    pub deposit_webhook_url: Option<Url>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ApiKeyConfig {
+    pub name: String,
+    /// Hex-encoded SHA-256 of the key
+    pub key_hash: String,
+    /// E.g. `disperse:eth`, `disperse:erc20`, `collect:*`, `history:read`
+    pub scopes: Vec<String>,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Pretty,
+    Json,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ChainConfig {
+    /// Expected chain id, checked against the RPC on startup
+    pub chain_id: Option<u64>,
+    pub rpc_url: String,
+    pub private_key: Option<String>,
+    /// Contracts are deployed on startup when addresses are not provided
+    pub disperse_address: Option<Address>,
+    pub collect_address: Option<Address>,
+    /// Uniswap Permit2 deployment, defaults to the canonical address
+    pub permit2_address: Option<Address>,
+    #[serde(default)]
+    pub limits: ChainLimits,
+    #[serde(default)]
+    pub fee_policy: FeePolicy,
+    #[serde(default)]
+    pub readiness: ReadinessConfig,
+    #[serde(default)]
+    pub spend_policy: SpendPolicyConfig,
+    /// Approve exactly the batch total before each ERC20 disperse and reset it afterwards
+    #[serde(default)]
+    pub just_in_time_approval: bool,
+    #[serde(default)]
+    pub deposits: DepositWatchConfig,
+    #[serde(default)]
+    pub auto_sweep: AutoSweepConfig,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ChainLimits {
+    pub max_recipients: usize,
+}
+
+impl Default for ChainLimits {
+    fn default() -> Self {
+        Self {
+            max_recipients: 100,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct DepositWatchConfig {
+    /// Watch the vaults for incoming ETH and ERC20 deposits
+    pub enabled: bool,
+    /// Blocks, counting the deposit's own, after which a deposit is confirmed
+    pub confirmations: u64,
+    pub poll_interval_secs: u64,
+    /// First block to scan before a cursor was saved, defaults to the latest block
+    pub start_block: Option<u64>,
+}
+
+impl Default for DepositWatchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            confirmations: 12,
+            poll_interval_secs: 12,
+            start_block: None,
+        }
+    }
+}
+
+#[derive(Clone, Default, Deserialize)]
+pub struct AutoSweepConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Seconds between balance checks, defaults to a minute
+    pub interval_secs: Option<u64>,
+    /// Vault balance that triggers a sweep, keyed by `eth` or token address; other assets are not swept
+    #[serde(default)]
+    pub thresholds: HashMap<String, Amount>,
+    /// Sweep every non-empty vault while the gas price is at or below this, in gwei
+    pub max_gas_price_gwei: Option<u64>,
+    /// Defaults to the operator
+    pub receiver: Option<Address>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+pub struct FeePolicy {
+    /// Send legacy (type 0) transactions instead of EIP-1559
+    #[serde(default)]
+    pub legacy: bool,
+    /// Max fee per gas in wei (gas price for legacy transactions)
+    pub max_fee_per_gas: Option<u64>,
+    pub max_priority_fee_per_gas: Option<u64>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+pub struct ReadinessConfig {
+    /// Latest block older than this marks the chain as not ready, unchecked when unset
+    pub max_block_age_secs: Option<u64>,
+    /// Minimal operator balance in gwei
+    #[serde(default)]
+    pub min_operator_balance_gwei: u64,
+}
+
+#[derive(Clone, Default, Deserialize)]
+pub struct SpendPolicyConfig {
+    /// Tokens that can be dispersed, any token when unset
+    pub allowed_tokens: Option<Vec<Address>>,
+    /// Window of the rolling spend caps, defaults to a day; spend is kept in `data_dir/ledger-{chain_id}.json`
+    pub cap_window_secs: Option<u64>,
+    /// Limits keyed by `eth` or token address
+    #[serde(default)]
+    pub assets: HashMap<String, AssetPolicy>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+pub struct AssetPolicy {
+    pub max_per_transaction: Option<Amount>,
+    pub max_per_recipient: Option<Amount>,
+    /// Total spend within the cap window
+    pub cap: Option<Amount>,
+    /// Spend of a single API key (or approver) within the cap window
+    pub cap_per_api_key: Option<Amount>,
+    /// Transactions above this total need a second approval
+    pub second_approval_above: Option<Amount>,
+}
+
+fn default_data_dir() -> PathBuf {
+    PathBuf::from("data")
+}
+
+/// Amount in base units, written as a decimal string or an integer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Amount(pub U256);
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Integer(u64),
+            Decimal(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Integer(value) => Ok(Amount(value.into())),
+            Raw::Decimal(value) => U256::from_dec_str(&value)
+                .map(Amount)
+                .map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
 }
 
 impl AppConfig {
     pub fn load() -> Result<Self, ConfigError> {
+        let config_path = std::env::var("CONFIG_PATH").unwrap_or_else(|_| "config".to_string());
+
         Config::builder()
+            .add_source(File::with_name(&config_path).required(false))
             .add_source(Environment::default())
             .build()?
             .try_deserialize()
     }
+
+    /// Returns configured chains, falling back to a single chain built from `rpc_url`
+    pub fn chain_configs(&self) -> Vec<ChainConfig> {
+        if !self.chains.is_empty() {
+            return self.chains.clone();
+        }
+
+        self.rpc_url
+            .iter()
+            .map(|rpc_url| ChainConfig {
+                chain_id: None,
+                rpc_url: rpc_url.clone(),
+                private_key: None,
+                disperse_address: None,
+                collect_address: None,
+                permit2_address: None,
+                limits: ChainLimits::default(),
+                fee_policy: FeePolicy::default(),
+                readiness: ReadinessConfig::default(),
+                spend_policy: SpendPolicyConfig::default(),
+                just_in_time_approval: false,
+                deposits: DepositWatchConfig::default(),
+                auto_sweep: AutoSweepConfig::default(),
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,42 @@
+use ethers::types::{Address, H256};
+use ethers::utils::{get_create2_address_from_hash, keccak256};
+
+/// CREATE2 salt of a customer's deposit vault
+pub fn customer_salt(customer: &str) -> H256 {
+    H256(keccak256(customer.as_bytes()))
+}
+
+/// Hash of the withdrawal contract creation code, which takes no constructor arguments
+pub fn init_code_hash(creation_code: &[u8]) -> H256 {
+    H256(keccak256(creation_code))
+}
+
+/// Counterfactual address of a vault deployed by `factory` (the Collect contract) with `salt`
+pub fn vault_address(factory: Address, salt: H256, init_code_hash: H256) -> Address {
+    get_create2_address_from_hash(factory, salt, init_code_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vault_address() {
+        // EIP-1014 example 5
+        let factory: Address = "0x00000000000000000000000000000000deadbeef"
+            .parse()
+            .unwrap();
+        let salt: H256 = "0x00000000000000000000000000000000000000000000000000000000cafebabe"
+            .parse()
+            .unwrap();
+        let hash = init_code_hash(&hex::decode("deadbeef").unwrap());
+
+        assert_eq!(
+            vault_address(factory, salt, hash),
+            "0x60f3f640a8508fC6a86d45DF051962668E1e8AC7"
+                .parse::<Address>()
+                .unwrap()
+        );
+        assert_ne!(customer_salt("alice"), customer_salt("bob"));
+    }
+}
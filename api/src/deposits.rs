@@ -0,0 +1,286 @@
+use crate::persist::JsonFile;
+use crate::types::{deserialize_decimal, serialize_decimal};
+use ethers::types::{Address, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DepositStatus {
+    Pending,
+    Confirmed,
+    /// The transaction or log left the canonical chain before it was confirmed
+    Reorged,
+}
+
+/// ETH or ERC20 transfer into a vault
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Deposit {
+    pub chain_id: u64,
+    pub vault: Address,
+    /// `None` for ETH
+    pub token: Option<Address>,
+    pub from: Address,
+    #[serde(
+        serialize_with = "serialize_decimal",
+        deserialize_with = "deserialize_decimal"
+    )]
+    pub amount: U256,
+    pub tx_hash: H256,
+    /// Index of the `Transfer` log, `None` for ETH
+    pub log_index: Option<u64>,
+    pub block_number: u64,
+    /// Hash of the block the deposit was seen in, checked again before confirming
+    pub block_hash: H256,
+    pub confirmations: u64,
+    pub status: DepositStatus,
+    pub detected_at: u64,
+}
+
+/// Identifies a deposit across polls: chain, transaction and `Transfer` log index
+pub type DepositKey = (u64, H256, Option<u64>);
+
+impl Deposit {
+    pub fn key(&self) -> DepositKey {
+        (self.chain_id, self.tx_hash, self.log_index)
+    }
+}
+
+/// Where the watcher of a chain resumes scanning
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ScanCursor {
+    pub next_block: u64,
+    /// Hash of block `next_block - 1`, a different hash now means it was reorged out
+    pub last_block_hash: H256,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct StoredDeposits {
+    deposits: Vec<Deposit>,
+    cursors: HashMap<u64, ScanCursor>,
+}
+
+/// Record of the deposits found by the watcher
+///
+/// Deposits and the scan cursor of every chain are written to `file` so that a restart resumes
+/// where the watcher stopped instead of skipping the blocks mined while it was down.
+#[derive(Default)]
+pub struct DepositStore {
+    stored: Mutex<StoredDeposits>,
+    file: Option<JsonFile>,
+}
+
+impl DepositStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restores the deposits and scan cursors from `file` and records changes there
+    pub fn load(file: JsonFile) -> anyhow::Result<Self> {
+        Ok(Self {
+            stored: Mutex::new(file.load()?),
+            file: Some(file),
+        })
+    }
+
+    fn save(&self, stored: &StoredDeposits) {
+        if let Some(file) = &self.file {
+            if let Err(e) = file.save(stored) {
+                error!(error = %e, "Failed to record deposits");
+            }
+        }
+    }
+
+    /// Scan cursor of `chain_id`, `None` before the first scan
+    pub fn cursor(&self, chain_id: u64) -> Option<ScanCursor> {
+        self.stored.lock().unwrap().cursors.get(&chain_id).copied()
+    }
+
+    /// Records the deposits found before `cursor`, returning the new ones
+    pub fn record(&self, chain_id: u64, found: Vec<Deposit>, cursor: ScanCursor) -> Vec<Deposit> {
+        let mut stored = self.stored.lock().unwrap();
+        let mut recorded = Vec::new();
+
+        for deposit in found {
+            if stored
+                .deposits
+                .iter()
+                .any(|known| known.key() == deposit.key())
+            {
+                continue;
+            }
+
+            stored.deposits.push(deposit.clone());
+            recorded.push(deposit);
+        }
+        stored.cursors.insert(chain_id, cursor);
+
+        self.save(&stored);
+        recorded
+    }
+
+    /// Updates the confirmations of pending deposits at `head`, returning the ones deep enough to
+    /// confirm once they are checked to still be canonical
+    pub fn due(&self, chain_id: u64, head: u64, required: u64) -> Vec<Deposit> {
+        let mut stored = self.stored.lock().unwrap();
+
+        stored
+            .deposits
+            .iter_mut()
+            .filter(|deposit| deposit.chain_id == chain_id)
+            .filter(|deposit| deposit.status == DepositStatus::Pending)
+            .filter_map(|deposit| {
+                deposit.confirmations = confirmations(deposit.block_number, head);
+                (deposit.confirmations >= required).then(|| deposit.clone())
+            })
+            .collect()
+    }
+
+    /// Replaces the pending deposit recorded under `key`, returning `false` when there is none
+    pub fn update(&self, key: DepositKey, deposit: Deposit) -> bool {
+        let mut stored = self.stored.lock().unwrap();
+        let Some(known) = stored
+            .deposits
+            .iter_mut()
+            .find(|known| known.key() == key && known.status == DepositStatus::Pending)
+        else {
+            return false;
+        };
+
+        *known = deposit;
+        self.save(&stored);
+        true
+    }
+
+    /// Deposits matching the filters, newest first
+    pub fn list(
+        &self,
+        chain_id: Option<u64>,
+        vault: Option<Address>,
+        status: Option<DepositStatus>,
+    ) -> Vec<Deposit> {
+        let mut deposits: Vec<Deposit> = self
+            .stored
+            .lock()
+            .unwrap()
+            .deposits
+            .iter()
+            .filter(|deposit| chain_id.is_none_or(|chain_id| deposit.chain_id == chain_id))
+            .filter(|deposit| vault.is_none_or(|vault| deposit.vault == vault))
+            .filter(|deposit| status.is_none_or(|status| deposit.status == status))
+            .cloned()
+            .collect();
+        deposits.sort_by_key(|deposit| Reverse((deposit.block_number, deposit.log_index)));
+        deposits
+    }
+}
+
+/// Blocks on top of and including `block_number`
+pub fn confirmations(block_number: u64, head: u64) -> u64 {
+    (head + 1).saturating_sub(block_number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deposit(block_number: u64, log_index: Option<u64>) -> Deposit {
+        Deposit {
+            chain_id: 31337,
+            vault: Address::repeat_byte(1),
+            token: log_index.map(|_| Address::repeat_byte(0x70)),
+            from: Address::repeat_byte(2),
+            amount: 100.into(),
+            tx_hash: H256::from_low_u64_be(block_number),
+            log_index,
+            block_number,
+            block_hash: H256::repeat_byte(block_number as u8),
+            confirmations: 1,
+            status: DepositStatus::Pending,
+            detected_at: 0,
+        }
+    }
+
+    fn cursor(next_block: u64) -> ScanCursor {
+        ScanCursor {
+            next_block,
+            last_block_hash: H256::repeat_byte(next_block as u8 - 1),
+        }
+    }
+
+    fn confirm(store: &DepositStore, head: u64, required: u64) -> Vec<Deposit> {
+        store
+            .due(31337, head, required)
+            .into_iter()
+            .filter(|deposit| {
+                let mut confirmed = deposit.clone();
+                confirmed.status = DepositStatus::Confirmed;
+                store.update(deposit.key(), confirmed)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_confirm_deposits() {
+        let store = DepositStore::new();
+        assert_eq!(
+            store
+                .record(
+                    31337,
+                    vec![deposit(10, None), deposit(12, Some(0))],
+                    cursor(13)
+                )
+                .len(),
+            2
+        );
+        assert!(store
+            .record(31337, vec![deposit(10, None)], cursor(13))
+            .is_empty());
+
+        let confirmed = confirm(&store, 12, 3);
+        assert_eq!(confirmed.len(), 1);
+        assert_eq!(confirmed[0].block_number, 10);
+        assert!(confirm(&store, 12, 3).is_empty());
+
+        let pending = store.list(None, None, Some(DepositStatus::Pending));
+        assert_eq!(
+            (pending.len(), pending[0].confirmations),
+            (1, confirmations(12, 12))
+        );
+        assert_eq!(store.list(Some(31337), None, None)[0].block_number, 12);
+        assert!(store.list(Some(1), None, None).is_empty());
+    }
+
+    #[test]
+    fn test_deposits_survive_restart() {
+        let file = JsonFile::new(&crate::persist::temp_dir(), "deposits.json").unwrap();
+        let store = DepositStore::load(file.clone()).unwrap();
+        assert_eq!(store.cursor(31337), None);
+        store.record(
+            31337,
+            vec![deposit(10, None), deposit(11, Some(2))],
+            cursor(12),
+        );
+
+        let mut reorged = deposit(11, Some(2));
+        reorged.status = DepositStatus::Reorged;
+        assert!(store.update(reorged.key(), reorged.clone()));
+        assert!(!store.update(reorged.key(), reorged));
+
+        let restarted = DepositStore::load(file).unwrap();
+        assert_eq!(restarted.cursor(31337), Some(cursor(12)));
+        assert_eq!(restarted.cursor(1), None);
+        let deposits = restarted.list(None, None, None);
+        assert_eq!(deposits.len(), 2);
+        assert_eq!(
+            (deposits[0].status, deposits[0].amount),
+            (DepositStatus::Reorged, 100.into())
+        );
+        assert!(restarted
+            .record(31337, vec![deposit(10, None)], cursor(13))
+            .is_empty());
+    }
+}
@@ -0,0 +1,290 @@
+use crate::persist::JsonFile;
+use crate::types::PayoutAuthorization;
+use ethers::types::transaction::eip712::{Eip712, TypedData};
+use ethers::types::{Address, Signature, H256, U256};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use tracing::error;
+
+/// Allowlist of wallets that can authorize payouts by signing them as EIP-712 typed data
+///
+/// Used nonces are written to `file` so that a signature can't be replayed after a restart.
+#[derive(Default)]
+pub struct Approvers {
+    allowlist: Vec<Address>,
+    // (signer, nonce) -> deadline, kept until the deadline passes
+    used_nonces: Mutex<HashMap<(Address, u64), u64>>,
+    file: Option<JsonFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct UsedNonce {
+    signer: Address,
+    nonce: u64,
+    deadline: u64,
+}
+
+/// Canonical payout a signature is checked against, `token` is zero for ETH
+pub struct Payout<'a> {
+    pub chain_id: u64,
+    pub verifying_contract: Address,
+    pub recipients: &'a [Address],
+    pub amounts: &'a [U256],
+    pub token: Address,
+}
+
+impl Payout<'_> {
+    pub fn typed_data(&self, nonce: u64, deadline: u64) -> TypedData {
+        let amounts: Vec<String> = self
+            .amounts
+            .iter()
+            .map(|amount| amount.to_string())
+            .collect();
+
+        serde_json::from_value(json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "Payout": [
+                    { "name": "recipients", "type": "address[]" },
+                    { "name": "amounts", "type": "uint256[]" },
+                    { "name": "token", "type": "address" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "nonce", "type": "uint256" },
+                    { "name": "deadline", "type": "uint256" }
+                ]
+            },
+            "primaryType": "Payout",
+            "domain": {
+                "name": "Disperse",
+                "version": "1",
+                "chainId": self.chain_id,
+                "verifyingContract": self.verifying_contract
+            },
+            "message": {
+                "recipients": self.recipients,
+                "amounts": amounts,
+                "token": self.token,
+                "chainId": self.chain_id,
+                "nonce": nonce,
+                "deadline": deadline
+            }
+        }))
+        .expect("Payout typed data is well-formed")
+    }
+}
+
+impl Approvers {
+    pub fn new(allowlist: Vec<Address>) -> Self {
+        Self {
+            allowlist,
+            used_nonces: Mutex::new(HashMap::new()),
+            file: None,
+        }
+    }
+
+    /// Restores the nonces used before a restart from `file` and records new ones there
+    pub fn load(allowlist: Vec<Address>, file: JsonFile) -> anyhow::Result<Self> {
+        let used_nonces: Vec<UsedNonce> = file.load()?;
+
+        Ok(Self {
+            allowlist,
+            used_nonces: Mutex::new(
+                used_nonces
+                    .into_iter()
+                    .map(|used| ((used.signer, used.nonce), used.deadline))
+                    .collect(),
+            ),
+            file: Some(file),
+        })
+    }
+
+    /// Recovers the signer of `payout` and checks its nonce is unused, `now` is a unix timestamp
+    ///
+    /// The nonce is only spent by [`Approvers::consume`], once the payout passed its checks.
+    pub fn verify(
+        &self,
+        payout: &Payout,
+        authorization: &PayoutAuthorization,
+        now: u64,
+    ) -> Result<Address, String> {
+        if authorization.deadline < now {
+            return Err("Signature expired".to_string());
+        }
+
+        let signature = Signature::from_str(&authorization.signature)
+            .map_err(|e| format!("Invalid signature: {}", e))?;
+        let hash = payout
+            .typed_data(authorization.nonce, authorization.deadline)
+            .encode_eip712()
+            .map_err(|e| format!("Failed to encode typed data: {}", e))?;
+        let signer = signature
+            .recover(H256::from(hash))
+            .map_err(|e| format!("Failed to recover signer: {}", e))?;
+
+        if !self.allowlist.contains(&signer) {
+            return Err(format!("Signer {:?} is not an approver", signer));
+        }
+
+        let mut used_nonces = self.used_nonces.lock().unwrap();
+        used_nonces.retain(|_, deadline| *deadline >= now);
+        if used_nonces.contains_key(&(signer, authorization.nonce)) {
+            return Err("Signature already used".to_string());
+        }
+
+        Ok(signer)
+    }
+
+    /// Spends the nonce of an authorization verified for `signer`, unless a concurrent request
+    /// spent it first
+    pub fn consume(
+        &self,
+        signer: Address,
+        authorization: &PayoutAuthorization,
+        now: u64,
+    ) -> Result<(), String> {
+        let mut used_nonces = self.used_nonces.lock().unwrap();
+        used_nonces.retain(|_, deadline| *deadline >= now);
+        if used_nonces.contains_key(&(signer, authorization.nonce)) {
+            return Err("Signature already used".to_string());
+        }
+        used_nonces.insert((signer, authorization.nonce), authorization.deadline);
+
+        if let Some(file) = &self.file {
+            let stored: Vec<UsedNonce> = used_nonces
+                .iter()
+                .map(|(&(signer, nonce), &deadline)| UsedNonce {
+                    signer,
+                    nonce,
+                    deadline,
+                })
+                .collect();
+            if let Err(e) = file.save(&stored) {
+                error!(error = %e, "Failed to record used nonces");
+                return Err("Failed to record the signature nonce".to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::core::rand::thread_rng;
+    use ethers::signers::{LocalWallet, Signer};
+
+    fn payout<'a>(recipients: &'a [Address], amounts: &'a [U256]) -> Payout<'a> {
+        Payout {
+            chain_id: 31337,
+            verifying_contract: Address::repeat_byte(0xd1),
+            recipients,
+            amounts,
+            token: Address::zero(),
+        }
+    }
+
+    async fn sign(wallet: &LocalWallet, payout: &Payout<'_>, nonce: u64, deadline: u64) -> String {
+        let typed_data = payout.typed_data(nonce, deadline);
+        let signature = wallet.sign_typed_data(&typed_data).await.unwrap();
+
+        format!("0x{}", signature)
+    }
+
+    #[tokio::test]
+    async fn test_verify_signed_payout() {
+        let wallet = LocalWallet::new(&mut thread_rng());
+        let outsider = LocalWallet::new(&mut thread_rng());
+        let approvers = Approvers::new(vec![wallet.address()]);
+
+        let recipients = [Address::repeat_byte(1), Address::repeat_byte(2)];
+        let amounts = [U256::from(100), U256::from(300)];
+        let signed = payout(&recipients, &amounts);
+
+        let authorization = PayoutAuthorization {
+            nonce: 1,
+            deadline: 1_000,
+            signature: sign(&wallet, &signed, 1, 1_000).await,
+        };
+        assert_eq!(
+            approvers.verify(&signed, &authorization, 500),
+            Ok(wallet.address())
+        );
+        // verifying alone leaves the nonce unused
+        assert_eq!(
+            approvers.verify(&signed, &authorization, 500),
+            Ok(wallet.address())
+        );
+        assert_eq!(
+            approvers.consume(wallet.address(), &authorization, 500),
+            Ok(())
+        );
+        assert_eq!(
+            approvers.verify(&signed, &authorization, 500),
+            Err("Signature already used".to_string())
+        );
+        assert_eq!(
+            approvers.consume(wallet.address(), &authorization, 500),
+            Err("Signature already used".to_string())
+        );
+
+        let expired = PayoutAuthorization {
+            nonce: 2,
+            deadline: 400,
+            signature: sign(&wallet, &signed, 2, 400).await,
+        };
+        assert!(approvers.verify(&signed, &expired, 500).is_err());
+
+        let unknown = PayoutAuthorization {
+            nonce: 3,
+            deadline: 1_000,
+            signature: sign(&outsider, &signed, 3, 1_000).await,
+        };
+        assert!(approvers.verify(&signed, &unknown, 500).is_err());
+
+        let tampered_amounts = [U256::from(100), U256::from(3000)];
+        let tampered = PayoutAuthorization {
+            nonce: 4,
+            deadline: 1_000,
+            signature: sign(&wallet, &signed, 4, 1_000).await,
+        };
+        let tampered_payout = payout(&recipients, &tampered_amounts);
+        assert!(approvers.verify(&tampered_payout, &tampered, 500).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_used_nonces_survive_restart() {
+        let wallet = LocalWallet::new(&mut thread_rng());
+        let dir = crate::persist::temp_dir();
+        let file = JsonFile::new(&dir, "nonces.json").unwrap();
+
+        let recipients = [Address::repeat_byte(1)];
+        let amounts = [U256::from(100)];
+        let signed = payout(&recipients, &amounts);
+        let authorization = PayoutAuthorization {
+            nonce: 1,
+            deadline: 1_000,
+            signature: sign(&wallet, &signed, 1, 1_000).await,
+        };
+
+        let approvers = Approvers::load(vec![wallet.address()], file.clone()).unwrap();
+        let signer = approvers.verify(&signed, &authorization, 500).unwrap();
+        approvers.consume(signer, &authorization, 500).unwrap();
+
+        let restarted = Approvers::load(vec![wallet.address()], file).unwrap();
+        assert_eq!(
+            restarted.verify(&signed, &authorization, 500),
+            Err("Signature already used".to_string())
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+use crate::error::ApiError;
+use crate::handlers::services::{approve_disperse, disperse_allowance};
+use crate::state::{AppState, ChainContext};
+use crate::types::{AllowanceResponse, ChainQuery, SetAllowanceRequest};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::Json;
+use ethers::prelude::*;
+use std::sync::Arc;
+use tracing::info;
+
+// Handler for GET /allowances/:token
+pub async fn get_allowance_handler(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<Address>,
+    Query(query): Query<ChainQuery>,
+) -> Result<Json<AllowanceResponse>, ApiError> {
+    let chain = chain(&state, query.chain_id)?;
+
+    allowance(chain, token).await.map(Json)
+}
+
+// Handler for PUT /allowances/:token
+pub async fn set_allowance_handler(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<Address>,
+    Json(payload): Json<SetAllowanceRequest>,
+) -> Result<Json<AllowanceResponse>, ApiError> {
+    let chain = chain(&state, payload.chain_id)?;
+    let amount = match (payload.amount, payload.unlimited) {
+        (Some(amount), false) => amount.0,
+        (None, true) => U256::MAX,
+        _ => {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "Provide either amount or unlimited",
+            ))
+        }
+    };
+
+    approve(chain, token, amount).await.map(Json)
+}
+
+// Handler for DELETE /allowances/:token
+pub async fn revoke_allowance_handler(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<Address>,
+    Query(query): Query<ChainQuery>,
+) -> Result<Json<AllowanceResponse>, ApiError> {
+    let chain = chain(&state, query.chain_id)?;
+
+    approve(chain, token, U256::zero()).await.map(Json)
+}
+
+fn chain(state: &AppState, chain_id: Option<u64>) -> Result<&ChainContext, ApiError> {
+    state
+        .chain(chain_id)
+        .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "Unknown chain id"))
+}
+
+async fn approve(
+    chain: &ChainContext,
+    token: Address,
+    amount: U256,
+) -> Result<AllowanceResponse, ApiError> {
+    let (status, message) = approve_disperse(chain, token, amount).await;
+    if status != StatusCode::OK {
+        return Err(ApiError::new(status, message));
+    }
+    info!(chain_id = *chain.chain_id(), token = ?token, amount = %amount, "Allowance updated");
+
+    allowance(chain, token).await
+}
+
+async fn allowance(chain: &ChainContext, token: Address) -> Result<AllowanceResponse, ApiError> {
+    let allowance = disperse_allowance(chain, token).await.map_err(|e| {
+        ApiError::new(
+            StatusCode::BAD_GATEWAY,
+            format!("Failed to read allowance: {:?}", e),
+        )
+    })?;
+
+    Ok(AllowanceResponse {
+        chain_id: *chain.chain_id(),
+        token,
+        owner: chain.wallet().address(),
+        spender: chain.disperse_contract().address(),
+        allowance,
+        unlimited: allowance == U256::MAX,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::test_state;
+    use axum::{body::Body, routing::put, Router};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_set_allowance_requires_one_amount() {
+        let (_anvil, state) = test_state().await;
+        let app = Router::new()
+            .route("/allowances/:token", put(set_allowance_handler))
+            .with_state(state);
+
+        let payload = json!({
+            "amount": "1000",
+            "unlimited": true
+        });
+
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("PUT")
+                    .uri(format!("/allowances/{:?}", Address::repeat_byte(1)))
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(payload.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,34 +1,115 @@
 use crate::handlers::services;
-use crate::types::{CollectRequest, ValuesType};
+use crate::handlers::services::{
+    dispatch, preflight_erc20, screen_addresses, settle_transaction, shortfall_report,
+    unix_timestamp, verify_permit2, Shortfall, Signing,
+};
+use crate::handlers::vaults::deploy_funded_vaults;
+use crate::jobs::{Job, JobKind};
+use crate::permits::{ecrecover_v, verify_signer, Eip2612Permit, TransferWithAuthorization};
+use crate::policy::Asset;
+use crate::state::{AppState, ChainContext};
+use crate::types::{
+    CollectEntry, CollectRequest, Permit2CollectRequest, PermitCollectRequest, SignedPermit,
+    SignedTransferAuthorization, SweepAmount, ValuesType, VaultSweep,
+};
+use crate::vaults::VaultStatus;
+use axum::extract::State;
 use axum::http::StatusCode;
-use axum::response::Json;
+use axum::response::{IntoResponse, Json, Response};
 use ethers::prelude::*;
-use ethers::utils::Anvil;
-use std::{convert::TryFrom, sync::Arc, time::Duration};
+use ethers::signers::coins_bip39::English;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
 
 abigen!(Collect, "../contracts/out/Collect.sol/Collect.json");
 abigen!(TestToken, "../contracts/out/TestToken.sol/TestToken.json");
+abigen!(
+    WithdrawalContract,
+    "../contracts/out/Collect.sol/WithdrawalContract.json"
+);
+abigen!(
+    Eip3009Token,
+    r#"[
+        function DOMAIN_SEPARATOR() external view returns (bytes32)
+        function authorizationState(address authorizer, bytes32 nonce) external view returns (bool)
+    ]"#
+);
+
+// default Anvil mnemonic, used to derive the test token senders and receiver
+const ANVIL_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+fn anvil_wallet(index: u32, chain_id: u64) -> LocalWallet {
+    MnemonicBuilder::<English>::default()
+        .phrase(ANVIL_MNEMONIC)
+        .index(index)
+        .unwrap()
+        .build()
+        .unwrap()
+        .with_chain_id(chain_id)
+}
+
+pub async fn collect_eth_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CollectRequest>,
+) -> Response {
+    let Some(chain) = state.chain(payload.chain_id) else {
+        return (StatusCode::BAD_REQUEST, "Unknown chain id".to_string()).into_response();
+    };
+    let signing = match Signing::new(chain, payload.unsigned, payload.from) {
+        Ok(signing) => signing,
+        Err(e) => return e.into_response(),
+    };
+
+    if let Some(sweeps) = payload.vaults {
+        if !payload.values.is_empty() {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Provide either values or vaults".to_string(),
+            )
+                .into_response();
+        }
+        return collect_eth_vaults(&state, chain, payload.receiver, sweeps, signing).await;
+    }
+
+    if payload.unsigned {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Unsigned requests require vaults".to_string(),
+        )
+            .into_response();
+    }
+
+    if payload.receiver.is_some() {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Receiver requires vaults".to_string(),
+        )
+            .into_response();
+    }
 
-pub async fn collect_eth_handler(Json(payload): Json<CollectRequest>) -> (StatusCode, String) {
     if payload.values.len() > 5 {
-        return (StatusCode::BAD_REQUEST, "Too many values".to_string());
+        return (StatusCode::BAD_REQUEST, "Too many values".to_string()).into_response();
     }
 
     if payload.values.is_empty() {
-        return (StatusCode::OK, "No values provided".to_string());
+        return (StatusCode::OK, "No values provided".to_string()).into_response();
     }
 
     let total_amount = payload.total_amount;
     match payload.values_type {
         ValuesType::Amount => {
-            return collect_eth(payload.values).await;
+            return collect_eth(&state, chain, payload.values)
+                .await
+                .into_response();
         }
         ValuesType::Percentage => {
             if total_amount.is_none() {
                 return (
                     StatusCode::BAD_REQUEST,
                     "Total amount not provided".to_string(),
-                );
+                )
+                    .into_response();
             }
 
             let total_amount = total_amount.unwrap();
@@ -36,48 +117,57 @@ pub async fn collect_eth_handler(Json(payload): Json<CollectRequest>) -> (Status
                 services::calculate_amounts_from_percentages(&payload.values, total_amount);
 
             match result {
-                Ok(amounts) => return collect_eth(amounts).await,
-                Err(message) => return (status, message),
+                Ok(amounts) => return collect_eth(&state, chain, amounts).await.into_response(),
+                Err(message) => return (status, message).into_response(),
             }
         }
     }
 }
 
-async fn collect_eth(amounts: Vec<u128>) -> (StatusCode, String) {
-    let anvil = Anvil::new().spawn();
-    let wallet: LocalWallet = anvil.keys()[0].clone().into(); // client wallet and token receiver
-
-    let provider = Provider::<Http>::try_from(anvil.endpoint())
-        .unwrap()
-        .interval(Duration::from_millis(10u64));
+async fn collect_eth(
+    state: &AppState,
+    chain: &ChainContext,
+    amounts: Vec<u128>,
+) -> (StatusCode, String) {
+    let collect_contract = chain.collect_contract();
 
-    let client = Arc::new(SignerMiddleware::new(
-        provider.clone(),
-        wallet.clone().with_chain_id(anvil.chain_id()),
-    ));
-
-    let collect_contract = Collect::deploy(client, ()).unwrap().send().await.unwrap();
+    if let Err(e) = deploy_funded_vaults(state, chain).await {
+        return e;
+    }
 
-    collect_contract
-        .create_withrawal_contracts()
-        .send()
-        .await
-        .unwrap();
     let withdrawal_contracts = collect_contract
         .get_withdrawal_contracts()
         .call()
         .await
         .unwrap();
+    if amounts.len() > withdrawal_contracts.len() {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Not enough withdrawal contracts".to_string(),
+        );
+    }
+
+    let vaults = state.vaults();
+    vaults.sync(*chain.chain_id(), &withdrawal_contracts, unix_timestamp());
+    let retired = withdrawal_contracts[..amounts.len()]
+        .iter()
+        .find(|&&address| {
+            vaults
+                .get(*chain.chain_id(), address)
+                .is_some_and(|vault| vault.status == VaultStatus::Retired)
+        });
+    if let Some(address) = retired {
+        return (
+            StatusCode::CONFLICT,
+            format!("Vault {:?} is retired", address),
+        );
+    }
 
     for i in 0..amounts.len() {
-        let tmp_client = Arc::new(SignerMiddleware::new(
-            provider.clone(),
-            wallet.clone().with_chain_id(anvil.chain_id()),
-        ));
         let tx = TransactionRequest::new()
             .to(withdrawal_contracts[i])
             .value(1000);
-        let pending_tx = tmp_client.send_transaction(tx, None).await.unwrap();
+        let pending_tx = chain.client().send_transaction(tx, None).await.unwrap();
         pending_tx
             .await
             .unwrap()
@@ -87,46 +177,256 @@ async fn collect_eth(amounts: Vec<u128>) -> (StatusCode, String) {
 
     let amounts_u256: Vec<U256> = amounts.iter().map(|&x| U256::from(x)).collect();
 
-    let collect_eth_call = collect_contract.collect_eth(amounts_u256);
+    let collect_eth_call = chain.prepare(collect_contract.collect_eth(amounts_u256));
     let collect_eth_send = collect_eth_call.send().await;
 
-    match collect_eth_send {
-        Ok(tx_receipt) => {
-            return (
-                StatusCode::OK,
-                format!("Transaction successful: {:?}", tx_receipt.tx_hash()),
-            );
+    settle_transaction(chain, "collect", "eth", collect_eth_send).await
+}
+
+/// Sweeps the requested vaults into `receiver` after checking their live balances
+async fn collect_eth_vaults(
+    state: &AppState,
+    chain: &ChainContext,
+    receiver: Option<Address>,
+    sweeps: Vec<VaultSweep>,
+    signing: Signing,
+) -> Response {
+    let chain_id = *chain.chain_id();
+
+    if sweeps.len() > chain.limits().max_recipients {
+        return (StatusCode::BAD_REQUEST, "Too many vaults".to_string()).into_response();
+    }
+
+    if sweeps.is_empty() {
+        return (StatusCode::OK, "No vaults provided".to_string()).into_response();
+    }
+
+    let receiver = receiver.unwrap_or(signing.address(chain));
+    if let Err(e) = screen_addresses(state, chain, &[receiver]) {
+        return e.into_response();
+    }
+
+    // deployments are sent by the operator, offline sweeps only cover deployed vaults
+    if let Signing::Operator = signing {
+        if let Err(e) = deploy_funded_vaults(state, chain).await {
+            return e.into_response();
         }
+    }
+    let withdrawal_contracts = match chain
+        .collect_contract()
+        .get_withdrawal_contracts()
+        .call()
+        .await
+    {
+        Ok(withdrawal_contracts) => withdrawal_contracts,
         Err(e) => {
+            return (
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to read withdrawal contracts: {:?}", e),
+            )
+                .into_response()
+        }
+    };
+    let vaults = state.vaults();
+    vaults.sync(chain_id, &withdrawal_contracts, unix_timestamp());
+
+    let mut addresses = Vec::new();
+    let mut indexes = Vec::new();
+    let mut amounts = Vec::new();
+    let mut shortfalls = Vec::new();
+    for sweep in sweeps {
+        let Some(vault) = vaults.resolve(chain_id, &sweep.vault) else {
+            return (
+                StatusCode::NOT_FOUND,
+                format!("Vault {} not found", sweep.vault),
+            )
+                .into_response();
+        };
+        let index = match (vault.status, vault.index) {
+            (VaultStatus::Retired, _) => {
+                return (
+                    StatusCode::CONFLICT,
+                    format!("Vault {:?} is retired", vault.address),
+                )
+                    .into_response()
+            }
+            (_, Some(index)) => U256::from(index),
+            (_, None) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    format!("Vault {:?} is not deployed", vault.address),
+                )
+                    .into_response()
+            }
+        };
+        if indexes.contains(&index) {
             return (
                 StatusCode::BAD_REQUEST,
-                format!("Transaction failed: {:?}", e),
-            );
+                format!("Duplicate vault {:?}", vault.address),
+            )
+                .into_response();
         }
+
+        let balance = match chain.provider().get_balance(vault.address, None).await {
+            Ok(balance) => balance,
+            Err(e) => {
+                return (
+                    StatusCode::BAD_GATEWAY,
+                    format!("Failed to read balance of {:?}: {:?}", vault.address, e),
+                )
+                    .into_response()
+            }
+        };
+        let amount = match sweep.amount {
+            SweepAmount::All => balance,
+            SweepAmount::Exact(amount) => amount,
+        };
+        if amount > balance {
+            shortfalls.push(Shortfall {
+                address: vault.address,
+                asset: Asset::Eth,
+                kind: "balance",
+                required: amount,
+                available: balance,
+            });
+        }
+
+        addresses.push(vault.address);
+        indexes.push(index);
+        amounts.push(amount);
+    }
+
+    if !shortfalls.is_empty() {
+        return shortfall_report(chain, shortfalls).into_response();
+    }
+
+    let swept: Vec<(Address, U256, U256)> = addresses
+        .into_iter()
+        .zip(indexes)
+        .zip(amounts)
+        .map(|((address, index), amount)| (address, index, amount))
+        .filter(|(_, _, amount)| !amount.is_zero())
+        .collect();
+    if swept.is_empty() {
+        return (StatusCode::OK, "Nothing to collect".to_string()).into_response();
     }
+    let addresses: Vec<Address> = swept.iter().map(|(address, _, _)| *address).collect();
+    let indexes: Vec<U256> = swept.iter().map(|(_, index, _)| *index).collect();
+    let amounts: Vec<U256> = swept.iter().map(|(_, _, amount)| *amount).collect();
+
+    let mut job = Job::new(
+        chain_id,
+        JobKind::CollectEth,
+        None,
+        addresses,
+        amounts.clone(),
+        None,
+        0,
+        unix_timestamp(),
+    );
+    job.receiver = Some(receiver);
+
+    let call = chain
+        .collect_contract()
+        .collect_eth_to(receiver, indexes, amounts);
+    dispatch(state, chain, call, signing, ("collect", "eth"), job).await
 }
 
 // Handler for /collect/erc20
-pub async fn collect_erc20_handler(Json(payload): Json<CollectRequest>) -> (StatusCode, String) {
+pub async fn collect_erc20_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CollectRequest>,
+) -> Response {
+    let Some(chain) = state.chain(payload.chain_id) else {
+        return (StatusCode::BAD_REQUEST, "Unknown chain id".to_string()).into_response();
+    };
+    let signing = match Signing::new(chain, payload.unsigned, payload.from) {
+        Ok(signing) => signing,
+        Err(e) => return e.into_response(),
+    };
+
+    if payload.entries.is_some() && payload.authorizations.is_some() {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Provide either entries or authorizations".to_string(),
+        )
+            .into_response();
+    }
+
+    if let Some(authorizations) = payload.authorizations {
+        if !payload.values.is_empty() {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Provide either values or authorizations".to_string(),
+            )
+                .into_response();
+        }
+        return collect_erc20_authorizations(
+            &state,
+            chain,
+            payload.token,
+            payload.receiver,
+            authorizations,
+            signing,
+        )
+        .await;
+    }
+
+    if let Some(entries) = payload.entries {
+        if !payload.values.is_empty() {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Provide either values or entries".to_string(),
+            )
+                .into_response();
+        }
+        return collect_erc20_entries(
+            &state,
+            chain,
+            payload.token,
+            payload.receiver,
+            entries,
+            signing,
+        )
+        .await;
+    }
+
+    if payload.unsigned {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Unsigned requests require entries or authorizations".to_string(),
+        )
+            .into_response();
+    }
+
+    if payload.token.is_some() || payload.receiver.is_some() {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Token and receiver require entries or authorizations".to_string(),
+        )
+            .into_response();
+    }
+
     if payload.values.len() > 2 {
-        return (StatusCode::BAD_REQUEST, "Too many values".to_string());
+        return (StatusCode::BAD_REQUEST, "Too many values".to_string()).into_response();
     }
 
     if payload.values.is_empty() {
-        return (StatusCode::OK, "No values provided".to_string());
+        return (StatusCode::OK, "No values provided".to_string()).into_response();
     }
 
     let total_amount = payload.total_amount;
     match payload.values_type {
         ValuesType::Amount => {
-            return collect_erc20(payload.values).await;
+            return collect_erc20(&state, chain, payload.values).await;
         }
         ValuesType::Percentage => {
             if total_amount.is_none() {
                 return (
                     StatusCode::BAD_REQUEST,
                     "Total amount not provided".to_string(),
-                );
+                )
+                    .into_response();
             }
 
             let total_amount = total_amount.unwrap();
@@ -134,42 +434,28 @@ pub async fn collect_erc20_handler(Json(payload): Json<CollectRequest>) -> (Stat
                 services::calculate_amounts_from_percentages(&payload.values, total_amount);
 
             match result {
-                Ok(amounts) => return collect_erc20(amounts).await,
-                Err(message) => return (status, message),
+                Ok(amounts) => return collect_erc20(&state, chain, amounts).await,
+                Err(message) => return (status, message).into_response(),
             }
         }
     }
 }
 
-async fn collect_erc20(amounts: Vec<u128>) -> (StatusCode, String) {
-    let anvil = Anvil::new().spawn();
-    let wallet: LocalWallet = anvil.keys()[0].clone().into();
-    let sender1: LocalWallet = anvil.keys()[1].clone().into();
-    let sender2: LocalWallet = anvil.keys()[2].clone().into();
-    let receiver: LocalWallet = anvil.keys()[3].clone().into();
-
-    let provider = Provider::<Http>::try_from(anvil.endpoint())
-        .unwrap()
-        .interval(Duration::from_millis(10u64));
+async fn collect_erc20(state: &AppState, chain: &ChainContext, amounts: Vec<u128>) -> Response {
+    let sender1 = anvil_wallet(1, *chain.chain_id());
+    let sender2 = anvil_wallet(2, *chain.chain_id());
+    let receiver = anvil_wallet(3, *chain.chain_id());
 
-    let client = Arc::new(SignerMiddleware::new(
-        provider.clone(),
-        wallet.clone().with_chain_id(anvil.chain_id()),
-    ));
     let sender1_client = Arc::new(SignerMiddleware::new(
-        provider.clone(),
-        sender1.clone().with_chain_id(anvil.chain_id()),
+        chain.provider().clone(),
+        sender1.clone(),
     ));
     let sender2_client = Arc::new(SignerMiddleware::new(
-        provider.clone(),
-        sender2.clone().with_chain_id(anvil.chain_id()),
+        chain.provider().clone(),
+        sender2.clone(),
     ));
 
-    let collect_contract = Collect::deploy(client.clone(), ())
-        .unwrap()
-        .send()
-        .await
-        .unwrap();
+    let collect_contract = chain.collect_contract();
     let test_erc20_contract = TestToken::deploy(sender1_client.clone(), sender1.address())
         .unwrap()
         .send()
@@ -197,34 +483,524 @@ async fn collect_erc20(amounts: Vec<u128>) -> (StatusCode, String) {
         .await
         .unwrap();
 
-    let amounts_u256: Vec<U256> = amounts.iter().map(|&x| U256::from(x)).collect();
-    let collect_contract_call = collect_contract.collect_erc20(
+    let transfers: Vec<(Address, U256)> = [sender1.address(), sender2.address()]
+        .into_iter()
+        .zip(amounts.iter().map(|&x| U256::from(x)))
+        .collect();
+
+    collect_erc20_transfers(
+        state,
+        chain,
         test_erc20_contract.address(),
         receiver.address(),
-        vec![sender1.address(), sender2.address()],
-        amounts_u256,
+        transfers,
+        Signing::Operator,
+    )
+    .await
+}
+
+/// Collects from senders that approved the Collect contract beforehand
+async fn collect_erc20_entries(
+    state: &AppState,
+    chain: &ChainContext,
+    token: Option<Address>,
+    receiver: Option<Address>,
+    entries: Vec<CollectEntry>,
+    signing: Signing,
+) -> Response {
+    let Some(token) = token else {
+        return (StatusCode::BAD_REQUEST, "Token not provided".to_string()).into_response();
+    };
+
+    if entries.len() > chain.limits().max_recipients {
+        return (StatusCode::BAD_REQUEST, "Too many entries".to_string()).into_response();
+    }
+
+    if entries.is_empty() {
+        return (StatusCode::OK, "No entries provided".to_string()).into_response();
+    }
+
+    if let Some(entry) = entries.iter().find(|entry| entry.amount == 0) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("Zero amount for {:?}", entry.sender),
+        )
+            .into_response();
+    }
+
+    let receiver = receiver.unwrap_or(signing.address(chain));
+    let transfers = entries
+        .into_iter()
+        .map(|entry| (entry.sender, U256::from(entry.amount)))
+        .collect();
+
+    collect_erc20_transfers(state, chain, token, receiver, transfers, signing).await
+}
+
+/// Screens, checks and collects `(sender, amount)` transfers of `token` into `receiver`
+async fn collect_erc20_transfers(
+    state: &AppState,
+    chain: &ChainContext,
+    token: Address,
+    receiver: Address,
+    transfers: Vec<(Address, U256)>,
+    signing: Signing,
+) -> Response {
+    let mut addresses: Vec<Address> = transfers.iter().map(|(sender, _)| *sender).collect();
+    addresses.push(receiver);
+    if let Err(e) = screen_addresses(state, chain, &addresses) {
+        return e.into_response();
+    }
+
+    let collect_contract = chain.collect_contract();
+    if let Err(e) =
+        preflight_erc20(chain, token, Some(collect_contract.address()), &transfers).await
+    {
+        return e.into_response();
+    }
+
+    // senders and amounts line up by construction
+    let (senders, amounts): (Vec<Address>, Vec<U256>) = transfers.into_iter().unzip();
+    let mut job = Job::new(
+        *chain.chain_id(),
+        JobKind::CollectErc20,
+        Some(token),
+        senders.clone(),
+        amounts.clone(),
+        None,
+        0,
+        unix_timestamp(),
     );
-    let collect_contract_send = collect_contract_call.send().await;
+    job.receiver = Some(receiver);
 
-    match collect_contract_send {
-        Ok(tx_receipt) => {
-            return (
-                StatusCode::OK,
-                format!("Transaction successful: {:?}", tx_receipt.tx_hash()),
-            );
+    let call = collect_contract.collect_erc20(token, receiver, senders, amounts);
+    dispatch(state, chain, call, signing, ("collect", "erc20"), job).await
+}
+
+// Handler for /collect/erc20/permit
+pub async fn collect_erc20_permit_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<PermitCollectRequest>,
+) -> Response {
+    let Some(chain) = state.chain(payload.chain_id) else {
+        return (StatusCode::BAD_REQUEST, "Unknown chain id".to_string()).into_response();
+    };
+    let signing = match Signing::new(chain, payload.unsigned, payload.from) {
+        Ok(signing) => signing,
+        Err(e) => return e.into_response(),
+    };
+
+    if payload.permits.len() > chain.limits().max_recipients {
+        return (StatusCode::BAD_REQUEST, "Too many permits".to_string()).into_response();
+    }
+
+    if payload.permits.is_empty() {
+        return (StatusCode::OK, "No permits provided".to_string()).into_response();
+    }
+
+    let receiver = payload.receiver.unwrap_or(signing.address(chain));
+    let mut addresses: Vec<Address> = payload.permits.iter().map(|p| p.owner).collect();
+    addresses.push(receiver);
+    if let Err(e) = screen_addresses(&state, chain, &addresses) {
+        return e.into_response();
+    }
+
+    let permits = match verify_permits(chain, payload.token, &payload.permits).await {
+        Ok(permits) => permits,
+        Err(e) => return e.into_response(),
+    };
+
+    // the allowance is granted by the permits in the same transaction
+    let transfers: Vec<(Address, U256)> = permits.iter().map(|p| (p.owner, p.value)).collect();
+    if let Err(e) = preflight_erc20(chain, payload.token, None, &transfers).await {
+        return e.into_response();
+    }
+
+    let (senders, amounts): (Vec<Address>, Vec<U256>) = transfers.into_iter().unzip();
+    let mut job = Job::new(
+        *chain.chain_id(),
+        JobKind::CollectErc20,
+        Some(payload.token),
+        senders,
+        amounts,
+        None,
+        0,
+        unix_timestamp(),
+    );
+    job.receiver = Some(receiver);
+
+    let call = chain
+        .collect_contract()
+        .collect_erc20_with_permit(payload.token, receiver, permits);
+    dispatch(&state, chain, call, signing, ("collect", "erc20"), job).await
+}
+
+/// Checks the permit signatures, nonces and deadlines against the token before submitting them
+async fn verify_permits(
+    chain: &ChainContext,
+    token: Address,
+    signed_permits: &[SignedPermit],
+) -> Result<Vec<Permit>, (StatusCode, String)> {
+    let read_error = |e: ContractError<_>| {
+        (
+            StatusCode::BAD_GATEWAY,
+            format!("Failed to read permit state: {:?}", e),
+        )
+    };
+
+    let token_contract = TestToken::new(token, chain.client().clone());
+    let domain_separator = token_contract
+        .domain_separator()
+        .call()
+        .await
+        .map_err(read_error)?;
+    let spender = chain.collect_contract().address();
+    let now = unix_timestamp();
+
+    // permits of the same owner use consecutive nonces
+    let mut nonces: HashMap<Address, U256> = HashMap::new();
+    let mut permits = Vec::new();
+    let mut invalid = Vec::new();
+
+    for signed in signed_permits {
+        let nonce = match nonces.get(&signed.owner) {
+            Some(nonce) => *nonce,
+            None => token_contract
+                .nonces(signed.owner)
+                .call()
+                .await
+                .map_err(read_error)?,
+        };
+        nonces.insert(signed.owner, nonce + 1);
+
+        if signed.deadline < now {
+            invalid.push(format!("{:?}: permit expired", signed.owner));
+            continue;
         }
-        Err(e) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                format!("Transaction failed: {:?}", e),
-            );
+        let signature = match Signature::from_str(&signed.signature) {
+            Ok(signature) => signature,
+            Err(e) => {
+                invalid.push(format!("{:?}: invalid signature: {}", signed.owner, e));
+                continue;
+            }
+        };
+
+        let permit = Eip2612Permit {
+            owner: signed.owner,
+            spender,
+            value: signed.value.into(),
+            nonce,
+            deadline: signed.deadline.into(),
+        };
+        if let Err(e) = verify_signer(permit.digest(domain_separator), &signature, signed.owner) {
+            invalid.push(format!("{:?}: {}", signed.owner, e));
+            continue;
+        }
+        let v = match ecrecover_v(&signature) {
+            Ok(v) => v,
+            Err(e) => {
+                invalid.push(format!("{:?}: {}", signed.owner, e));
+                continue;
+            }
+        };
+
+        let (mut r, mut s) = ([0u8; 32], [0u8; 32]);
+        signature.r.to_big_endian(&mut r);
+        signature.s.to_big_endian(&mut s);
+        permits.push(Permit {
+            owner: signed.owner,
+            value: permit.value,
+            deadline: permit.deadline,
+            v,
+            r,
+            s,
+        });
+    }
+
+    if !invalid.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Invalid permits: {}", invalid.join("; ")),
+        ));
+    }
+
+    Ok(permits)
+}
+
+// Handler for /collect/erc20/permit2
+pub async fn collect_erc20_permit2_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<Permit2CollectRequest>,
+) -> Response {
+    let Some(chain) = state.chain(payload.chain_id) else {
+        return (StatusCode::BAD_REQUEST, "Unknown chain id".to_string()).into_response();
+    };
+    let signing = match Signing::new(chain, payload.unsigned, payload.from) {
+        Ok(signing) => signing,
+        Err(e) => return e.into_response(),
+    };
+
+    let permitted_count: usize = payload.transfers.iter().map(|t| t.permitted.len()).sum();
+    if permitted_count > chain.limits().max_recipients {
+        return (StatusCode::BAD_REQUEST, "Too many transfers".to_string()).into_response();
+    }
+
+    if permitted_count == 0 {
+        return (StatusCode::OK, "No transfers provided".to_string()).into_response();
+    }
+
+    let receiver = payload.receiver.unwrap_or(signing.address(chain));
+    let mut addresses: Vec<Address> = payload
+        .transfers
+        .iter()
+        .map(|t| t.authorization.owner)
+        .collect();
+    addresses.push(receiver);
+    if let Err(e) = screen_addresses(&state, chain, &addresses) {
+        return e.into_response();
+    }
+
+    let collect_contract = chain.collect_contract();
+    let mut transfers = Vec::new();
+    // owner and amount per token, for the balance and Permit2 allowance checks
+    let mut required: HashMap<Address, Vec<(Address, U256)>> = HashMap::new();
+
+    for transfer in payload.transfers {
+        let permitted: Vec<(Address, U256)> = transfer
+            .permitted
+            .iter()
+            .map(|p| (p.token, U256::from(p.amount)))
+            .collect();
+        let signature = match verify_permit2(
+            chain,
+            collect_contract.address(),
+            &permitted,
+            &transfer.authorization,
+        )
+        .await
+        {
+            Ok(signature) => signature,
+            Err(e) => return e.into_response(),
+        };
+
+        let owner = transfer.authorization.owner;
+        for &(token, amount) in &permitted {
+            required.entry(token).or_default().push((owner, amount));
+        }
+        transfers.push(Permit2Transfer {
+            owner,
+            permit: PermitBatchTransferFrom {
+                permitted: permitted
+                    .into_iter()
+                    .map(|(token, amount)| TokenPermissions { token, amount })
+                    .collect(),
+                nonce: transfer.authorization.nonce.0,
+                deadline: transfer.authorization.deadline.into(),
+            },
+            signature,
+        });
+    }
+
+    for (&token, owners) in &required {
+        if let Err(e) = preflight_erc20(chain, token, Some(*chain.permit2()), owners).await {
+            return e.into_response();
+        }
+    }
+
+    // a job lists a single token, batches spanning several are tracked without one
+    let tokens: Vec<Address> = required.keys().copied().collect();
+    let (senders, amounts): (Vec<Address>, Vec<U256>) = required.into_values().flatten().unzip();
+    let mut job = Job::new(
+        *chain.chain_id(),
+        JobKind::CollectErc20,
+        (tokens.len() == 1).then(|| tokens[0]),
+        senders,
+        amounts,
+        None,
+        0,
+        unix_timestamp(),
+    );
+    job.receiver = Some(receiver);
+
+    let call = collect_contract.collect_erc20_with_permit_2(*chain.permit2(), receiver, transfers);
+    dispatch(&state, chain, call, signing, ("collect", "erc20"), job).await
+}
+
+/// Collects EIP-3009 transfers signed over to the receiver in one `collectERC20WithAuthorization` call
+async fn collect_erc20_authorizations(
+    state: &AppState,
+    chain: &ChainContext,
+    token: Option<Address>,
+    receiver: Option<Address>,
+    signed_authorizations: Vec<SignedTransferAuthorization>,
+    signing: Signing,
+) -> Response {
+    let Some(token) = token else {
+        return (StatusCode::BAD_REQUEST, "Token not provided".to_string()).into_response();
+    };
+
+    if signed_authorizations.len() > chain.limits().max_recipients {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Too many authorizations".to_string(),
+        )
+            .into_response();
+    }
+
+    if signed_authorizations.is_empty() {
+        return (StatusCode::OK, "No authorizations provided".to_string()).into_response();
+    }
+
+    let receiver = receiver.unwrap_or(signing.address(chain));
+    let mut addresses: Vec<Address> = signed_authorizations.iter().map(|a| a.from).collect();
+    addresses.push(receiver);
+    if let Err(e) = screen_addresses(state, chain, &addresses) {
+        return e.into_response();
+    }
+
+    let authorizations =
+        match verify_authorizations(chain, token, receiver, &signed_authorizations).await {
+            Ok(authorizations) => authorizations,
+            Err(e) => return e.into_response(),
+        };
+
+    // transfers need no allowance, only the balances are checked
+    let transfers: Vec<(Address, U256)> =
+        authorizations.iter().map(|a| (a.from, a.value)).collect();
+    if let Err(e) = preflight_erc20(chain, token, None, &transfers).await {
+        return e.into_response();
+    }
+
+    let (senders, amounts): (Vec<Address>, Vec<U256>) = transfers.into_iter().unzip();
+    let mut job = Job::new(
+        *chain.chain_id(),
+        JobKind::CollectErc20,
+        Some(token),
+        senders,
+        amounts,
+        None,
+        0,
+        unix_timestamp(),
+    );
+    job.receiver = Some(receiver);
+
+    let call =
+        chain
+            .collect_contract()
+            .collect_erc20_with_authorization(token, receiver, authorizations);
+    dispatch(state, chain, call, signing, ("collect", "erc20"), job).await
+}
+
+/// Checks the authorization signatures, validity windows and nonces against the token
+async fn verify_authorizations(
+    chain: &ChainContext,
+    token: Address,
+    receiver: Address,
+    signed_authorizations: &[SignedTransferAuthorization],
+) -> Result<Vec<TransferAuthorization>, (StatusCode, String)> {
+    let read_error = |e: ContractError<_>| {
+        (
+            StatusCode::BAD_GATEWAY,
+            format!("Failed to read authorization state: {:?}", e),
+        )
+    };
+
+    let token_contract = Eip3009Token::new(token, chain.client().clone());
+    let domain_separator = token_contract
+        .domain_separator()
+        .call()
+        .await
+        .map_err(read_error)?;
+    let now = unix_timestamp();
+
+    let mut authorizations: Vec<TransferAuthorization> = Vec::new();
+    let mut invalid = Vec::new();
+
+    for signed in signed_authorizations {
+        let nonce = signed.nonce.to_fixed_bytes();
+        if signed.valid_after >= now {
+            invalid.push(format!("{:?}: authorization not yet valid", signed.from));
+            continue;
         }
+        if signed.valid_before <= now {
+            invalid.push(format!("{:?}: authorization expired", signed.from));
+            continue;
+        }
+        let reused = authorizations
+            .iter()
+            .any(|a| a.from == signed.from && a.nonce == nonce);
+        if reused
+            || token_contract
+                .authorization_state(signed.from, nonce)
+                .call()
+                .await
+                .map_err(read_error)?
+        {
+            invalid.push(format!("{:?}: nonce already used", signed.from));
+            continue;
+        }
+        let signature = match Signature::from_str(&signed.signature) {
+            Ok(signature) => signature,
+            Err(e) => {
+                invalid.push(format!("{:?}: invalid signature: {}", signed.from, e));
+                continue;
+            }
+        };
+
+        let authorization = TransferWithAuthorization {
+            from: signed.from,
+            to: receiver,
+            value: signed.value.into(),
+            valid_after: signed.valid_after.into(),
+            valid_before: signed.valid_before.into(),
+            nonce,
+        };
+        if let Err(e) = verify_signer(
+            authorization.digest(domain_separator),
+            &signature,
+            signed.from,
+        ) {
+            invalid.push(format!("{:?}: {}", signed.from, e));
+            continue;
+        }
+
+        let v = match ecrecover_v(&signature) {
+            Ok(v) => v,
+            Err(e) => {
+                invalid.push(format!("{:?}: {}", signed.from, e));
+                continue;
+            }
+        };
+
+        let (mut r, mut s) = ([0u8; 32], [0u8; 32]);
+        signature.r.to_big_endian(&mut r);
+        signature.s.to_big_endian(&mut s);
+        authorizations.push(TransferAuthorization {
+            from: signed.from,
+            value: authorization.value,
+            valid_after: authorization.valid_after,
+            valid_before: authorization.valid_before,
+            nonce,
+            v,
+            r,
+            s,
+        });
     }
+
+    if !invalid.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Invalid authorizations: {}", invalid.join("; ")),
+        ));
+    }
+
+    Ok(authorizations)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::state::test_state;
     use axum::http::StatusCode;
     use axum::{body::Body, routing::post, Router};
     use serde_json::json;
@@ -232,7 +1008,10 @@ mod tests {
 
     #[tokio::test]
     async fn test_collect_eth_valid_amounts() {
-        let app = Router::new().route("/collect/eth", post(collect_eth_handler));
+        let (_anvil, state) = test_state().await;
+        let app = Router::new()
+            .route("/collect/eth", post(collect_eth_handler))
+            .with_state(state);
 
         let payload = json!({
             "values": [100, 200, 300],
@@ -257,7 +1036,10 @@ mod tests {
 
     #[tokio::test]
     async fn test_collect_eth_invalid_percentage() {
-        let app = Router::new().route("/collect/eth", post(collect_eth_handler));
+        let (_anvil, state) = test_state().await;
+        let app = Router::new()
+            .route("/collect/eth", post(collect_eth_handler))
+            .with_state(state);
 
         let payload = json!({
             "values": [10, 20, 30],
@@ -279,4 +1061,97 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
+
+    abigen!(
+        AuthorizationToken,
+        "../contracts/out/AuthorizationToken.sol/AuthorizationToken.json"
+    );
+
+    #[tokio::test]
+    async fn test_verify_authorizations() {
+        let (anvil, state) = test_state().await;
+        let chain = state.chain(None).unwrap();
+        let sender = LocalWallet::from(anvil.keys()[1].clone());
+        let stranger = LocalWallet::from(anvil.keys()[2].clone());
+        let receiver = chain.wallet().address();
+
+        let token = AuthorizationToken::deploy(chain.client().clone(), sender.address())
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+        let domain_separator = token.domain_separator().call().await.unwrap();
+
+        let now = unix_timestamp();
+        let sign = |signer: &LocalWallet, valid_before: u64, nonce: H256| {
+            let authorization = TransferWithAuthorization {
+                from: sender.address(),
+                to: receiver,
+                value: 5.into(),
+                valid_after: 0.into(),
+                valid_before: valid_before.into(),
+                nonce: nonce.to_fixed_bytes(),
+            };
+            let signature = signer
+                .sign_hash(H256::from(authorization.digest(domain_separator)))
+                .unwrap();
+            SignedTransferAuthorization {
+                from: sender.address(),
+                value: 5,
+                valid_after: 0,
+                valid_before,
+                nonce,
+                signature: format!("0x{}", signature),
+            }
+        };
+
+        // written with a 0/1 recovery id, which the token only accepts as 27/28
+        let mut valid = sign(&sender, now + 3600, H256::repeat_byte(1));
+        let mut signature = Signature::from_str(&valid.signature).unwrap();
+        signature.v -= 27;
+        valid.signature = format!("0x{}", signature);
+        let authorizations = [
+            sign(&sender, now + 3600, H256::repeat_byte(1)),
+            sign(&sender, now - 1, H256::repeat_byte(2)),
+            sign(&sender, now + 3600, H256::repeat_byte(1)),
+            sign(&stranger, now + 3600, H256::repeat_byte(3)),
+        ];
+        let (status, message) =
+            verify_authorizations(chain, token.address(), receiver, &authorizations)
+                .await
+                .err()
+                .unwrap();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(message.contains("authorization expired"), "{}", message);
+        assert!(message.contains("nonce already used"), "{}", message);
+        assert!(
+            message.contains(&format!("Signed by {:?}", stranger.address())),
+            "{}",
+            message
+        );
+
+        let verified = verify_authorizations(chain, token.address(), receiver, &[valid])
+            .await
+            .unwrap();
+        chain
+            .collect_contract()
+            .collect_erc20_with_authorization(token.address(), receiver, verified)
+            .send()
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+        assert_eq!(
+            token.balance_of(receiver).call().await.unwrap(),
+            U256::from(5)
+        );
+
+        // the nonce is now spent on chain
+        let replay = sign(&sender, now + 3600, H256::repeat_byte(1));
+        let (_, message) = verify_authorizations(chain, token.address(), receiver, &[replay])
+            .await
+            .err()
+            .unwrap();
+        assert!(message.contains("nonce already used"), "{}", message);
+    }
 }
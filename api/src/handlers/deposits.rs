@@ -0,0 +1,351 @@
+use crate::deposits::{confirmations, Deposit, DepositStatus, ScanCursor};
+use crate::handlers::services::unix_timestamp;
+use crate::handlers::vaults::sync_vaults;
+use crate::state::{AppState, ChainContext};
+use crate::types::DepositQuery;
+use axum::extract::{Query, State};
+use axum::response::Json;
+use ethers::prelude::*;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+const MAX_BLOCKS_PER_POLL: u64 = 100;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Handler for GET /deposits
+pub async fn list_deposits_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<DepositQuery>,
+) -> Json<Vec<Deposit>> {
+    Json(
+        state
+            .deposits()
+            .list(query.chain_id, query.vault, query.status),
+    )
+}
+
+/// Starts a deposit watcher for every chain with `deposits.enabled`
+pub fn spawn_deposit_watchers(state: Arc<AppState>) {
+    for (&chain_id, chain) in state.chains() {
+        if chain.deposits().enabled {
+            info!(chain_id, "Watching vaults for deposits");
+            tokio::spawn(watch_deposits(state.clone(), chain_id));
+        }
+    }
+}
+
+async fn watch_deposits(state: Arc<AppState>, chain_id: u64) {
+    let chain = state
+        .chain(Some(chain_id))
+        .expect("watched chain is configured");
+    let mut interval = tokio::time::interval(Duration::from_secs(
+        chain.deposits().poll_interval_secs.max(1),
+    ));
+    let client = reqwest::Client::new();
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = poll_deposits(&state, chain, &client).await {
+            warn!(chain_id, error = ?e, "Deposit poll failed");
+        }
+    }
+}
+
+/// Scans the blocks from the stored cursor to the head and confirms the pending deposits that are
+/// deep enough and still canonical
+async fn poll_deposits(
+    state: &AppState,
+    chain: &ChainContext,
+    client: &reqwest::Client,
+) -> anyhow::Result<()> {
+    let chain_id = *chain.chain_id();
+    let required = chain.deposits().confirmations;
+    let head = chain.provider().get_block_number().await?.as_u64();
+    let from = match state.deposits().cursor(chain_id) {
+        Some(cursor) => resume_block(chain, cursor, required).await?,
+        None => chain.deposits().start_block.unwrap_or(head),
+    };
+
+    if from <= head {
+        let to = head.min(from + MAX_BLOCKS_PER_POLL - 1);
+        if let Err(e) = sync_vaults(state, chain).await {
+            warn!(chain_id, error = %e.message, "Failed to sync vaults");
+        }
+        let vaults: HashSet<Address> = state
+            .vaults()
+            .list(chain_id)
+            .iter()
+            .map(|vault| vault.address)
+            .collect();
+
+        let mut found = Vec::new();
+        if !vaults.is_empty() {
+            found = eth_deposits(chain, &vaults, from, to, head).await?;
+            found.extend(token_deposits(chain, &vaults, from, to, head).await?);
+        }
+        let last_block = chain
+            .provider()
+            .get_block(to)
+            .await?
+            .and_then(|block| block.hash)
+            .ok_or_else(|| anyhow::anyhow!("Block {} not found", to))?;
+        let cursor = ScanCursor {
+            next_block: to + 1,
+            last_block_hash: last_block,
+        };
+
+        for deposit in state.deposits().record(chain_id, found, cursor) {
+            info!(chain_id, vault = ?deposit.vault, tx_hash = ?deposit.tx_hash, "Deposit detected");
+            notify(state, client, &deposit).await;
+        }
+    }
+
+    for pending in state.deposits().due(chain_id, head, required) {
+        let key = pending.key();
+        let Some(mut deposit) = canonical_deposit(chain, &pending).await? else {
+            let mut reorged = pending;
+            reorged.status = DepositStatus::Reorged;
+            if state.deposits().update(key, reorged.clone()) {
+                warn!(chain_id, vault = ?reorged.vault, tx_hash = ?reorged.tx_hash, "Deposit reorged out");
+                notify(state, client, &reorged).await;
+            }
+            continue;
+        };
+
+        deposit.confirmations = confirmations(deposit.block_number, head);
+        if deposit.confirmations >= required {
+            deposit.status = DepositStatus::Confirmed;
+        }
+        if state.deposits().update(key, deposit.clone())
+            && deposit.status == DepositStatus::Confirmed
+        {
+            info!(chain_id, vault = ?deposit.vault, tx_hash = ?deposit.tx_hash, "Deposit confirmed");
+            notify(state, client, &deposit).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Block to resume scanning from, going back `depth` blocks when the last scanned one was reorged out
+async fn resume_block(chain: &ChainContext, cursor: ScanCursor, depth: u64) -> anyhow::Result<u64> {
+    let last_block = cursor.next_block.saturating_sub(1);
+    let hash = chain
+        .provider()
+        .get_block(last_block)
+        .await?
+        .and_then(|block| block.hash);
+    if hash == Some(cursor.last_block_hash) {
+        return Ok(cursor.next_block);
+    }
+
+    let from = cursor.next_block.saturating_sub(depth.max(1));
+    warn!(
+        chain_id = *chain.chain_id(),
+        block = last_block,
+        from,
+        "Scanned block reorged out, rescanning"
+    );
+    Ok(from)
+}
+
+/// The deposit as it is on the canonical chain now, `None` when its transaction is gone, failed or
+/// no longer makes the transfer
+///
+/// A transaction included again in another block keeps the deposit pending with the new block.
+async fn canonical_deposit(
+    chain: &ChainContext,
+    deposit: &Deposit,
+) -> anyhow::Result<Option<Deposit>> {
+    let Some(receipt) = chain
+        .provider()
+        .get_transaction_receipt(deposit.tx_hash)
+        .await?
+    else {
+        return Ok(None);
+    };
+    if receipt.status != Some(1u64.into()) {
+        return Ok(None);
+    }
+    let (Some(block_number), Some(block_hash)) = (receipt.block_number, receipt.block_hash) else {
+        return Ok(None);
+    };
+
+    let mut canonical = deposit.clone();
+    if let Some(token) = deposit.token {
+        let log = receipt.logs.iter().find(|log| {
+            log.address == token
+                && transfer(log) == Some((deposit.from, deposit.vault, deposit.amount))
+        });
+        let Some(log) = log else {
+            return Ok(None);
+        };
+        canonical.log_index = log.log_index.map(|index| index.as_u64());
+    }
+    canonical.block_number = block_number.as_u64();
+    canonical.block_hash = block_hash;
+
+    Ok(Some(canonical))
+}
+
+/// Sender, recipient and amount of an ERC20 `Transfer` log that is still canonical
+///
+/// ERC721 transfers index the token id as a fourth topic and have no data, so they are rejected
+/// along with any log whose data isn't a single word.
+fn transfer(log: &Log) -> Option<(Address, Address, U256)> {
+    if log.removed == Some(true) || log.topics.len() != 3 || log.data.len() != 32 {
+        return None;
+    }
+    if log.topics[0]
+        != H256::from(ethers::utils::keccak256(
+            "Transfer(address,address,uint256)",
+        ))
+    {
+        return None;
+    }
+
+    Some((
+        Address::from(log.topics[1]),
+        Address::from(log.topics[2]),
+        U256::from_big_endian(&log.data),
+    ))
+}
+
+/// Successful transactions sending ETH directly to a vault
+async fn eth_deposits(
+    chain: &ChainContext,
+    vaults: &HashSet<Address>,
+    from: u64,
+    to: u64,
+    head: u64,
+) -> anyhow::Result<Vec<Deposit>> {
+    let provider = chain.provider();
+    let mut deposits = Vec::new();
+
+    for block_number in from..=to {
+        let Some(block) = provider.get_block_with_txs(block_number).await? else {
+            continue;
+        };
+
+        for tx in block.transactions {
+            let Some(vault) = tx.to.filter(|to| vaults.contains(to)) else {
+                continue;
+            };
+            if tx.value.is_zero() {
+                continue;
+            }
+
+            let receipt = provider.get_transaction_receipt(tx.hash).await?;
+            if receipt.and_then(|receipt| receipt.status) != Some(1u64.into()) {
+                continue;
+            }
+
+            let Some(block_hash) = block.hash else {
+                continue;
+            };
+            deposits.push(deposit(
+                chain,
+                vault,
+                None,
+                tx.from,
+                tx.value,
+                tx.hash,
+                None,
+                block_number,
+                block_hash,
+                head,
+            ));
+        }
+    }
+
+    Ok(deposits)
+}
+
+/// ERC20 `Transfer` events to a vault
+async fn token_deposits(
+    chain: &ChainContext,
+    vaults: &HashSet<Address>,
+    from: u64,
+    to: u64,
+    head: u64,
+) -> anyhow::Result<Vec<Deposit>> {
+    let filter = Filter::new()
+        .from_block(from)
+        .to_block(to)
+        .event("Transfer(address,address,uint256)")
+        .topic2(
+            vaults
+                .iter()
+                .map(|vault| H256::from(*vault))
+                .collect::<Vec<_>>(),
+        );
+    let logs = chain.provider().get_logs(&filter).await?;
+
+    Ok(logs
+        .into_iter()
+        .filter_map(|log| {
+            let (from, vault, amount) = transfer(&log)?;
+            Some(deposit(
+                chain,
+                vault,
+                Some(log.address),
+                from,
+                amount,
+                log.transaction_hash?,
+                log.log_index.map(|index| index.as_u64()),
+                log.block_number?.as_u64(),
+                log.block_hash?,
+                head,
+            ))
+        })
+        .collect())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn deposit(
+    chain: &ChainContext,
+    vault: Address,
+    token: Option<Address>,
+    from: Address,
+    amount: U256,
+    tx_hash: H256,
+    log_index: Option<u64>,
+    block_number: u64,
+    block_hash: H256,
+    head: u64,
+) -> Deposit {
+    Deposit {
+        chain_id: *chain.chain_id(),
+        vault,
+        token,
+        from,
+        amount,
+        tx_hash,
+        log_index,
+        block_number,
+        block_hash,
+        confirmations: confirmations(block_number, head),
+        status: DepositStatus::Pending,
+        detected_at: unix_timestamp(),
+    }
+}
+
+/// Posts the deposit to the webhook, failures are logged and not retried
+async fn notify(state: &AppState, client: &reqwest::Client, deposit: &Deposit) {
+    let Some(url) = state.deposit_webhook_url() else {
+        return;
+    };
+
+    let sent = client
+        .post(url.clone())
+        .timeout(WEBHOOK_TIMEOUT)
+        .json(deposit)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    if let Err(e) = sent {
+        warn!(tx_hash = ?deposit.tx_hash, error = %e, "Deposit webhook failed");
+    }
+}
@@ -1,213 +1,857 @@
-use crate::handlers::services;
-use crate::handlers::services::{generate_addresses, get_solidity_address, sum_u256_vector};
-use crate::types::{DisperseRequest, ValuesType};
+use crate::auth::ApiKeyIdentity;
+use crate::eip712::Payout;
+use crate::handlers::jobs::hold_for_approval;
+use crate::handlers::services::{
+    approve_disperse, authorize_payout, confirm_transaction, consume_authorization,
+    disperse_allowance, normalize_payments, preflight_erc20, preflight_eth, prepare_unsigned,
+    reserve_spend, resolve_amounts, resolve_recipients, screen_addresses, settle_transaction,
+    sum_u256_vector, unix_timestamp, verify_permit2, Signing,
+};
+use crate::jobs::{Job, JobKind};
+use crate::policy::{Asset, Reservation};
+use crate::state::{AppState, ChainContext, Client};
+use crate::types::{
+    DisperseRequest, DisperseResponse, LegResult, LegStatus, MultiDisperseRequest,
+    MultiDisperseResponse, Payment, PayoutAuthorization, Permit2Signature,
+};
+use axum::extract::{Extension, State};
 use axum::http::StatusCode;
 use axum::response::Json as AxumJson;
+use ethers::contract::parse_log;
 use ethers::prelude::*;
-use ethers::utils::Anvil;
-use std::{convert::TryFrom, sync::Arc, time::Duration};
+use std::sync::Arc;
+use tracing::warn;
 
 abigen!(Disperse, "../contracts/out/Disperse.sol/Disperse.json");
 abigen!(TestToken, "../contracts/out/TestToken.sol/TestToken.json");
 
 // Handler for /disperse/eth
 pub async fn disperse_eth_handler(
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<ApiKeyIdentity>>,
     AxumJson(payload): AxumJson<DisperseRequest>,
-) -> (StatusCode, String) {
-    if payload.values.len() > 100 {
-        return (StatusCode::BAD_REQUEST, "Too many values".to_string());
+) -> (StatusCode, AxumJson<DisperseResponse>) {
+    handle_disperse(&state, identity, payload, JobKind::DisperseEth).await
+}
+
+// Handler for /disperse/erc20
+pub async fn disperse_erc20_handler(
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    AxumJson(payload): AxumJson<DisperseRequest>,
+) -> (StatusCode, AxumJson<DisperseResponse>) {
+    handle_disperse(&state, identity, payload, JobKind::DisperseErc20).await
+}
+
+// Handler for /disperse/multi
+pub async fn disperse_multi_handler(
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    AxumJson(payload): AxumJson<MultiDisperseRequest>,
+) -> (StatusCode, AxumJson<MultiDisperseResponse>) {
+    let Some(chain) = state.chain(payload.chain_id) else {
+        return respond_multi(
+            (StatusCode::BAD_REQUEST, "Unknown chain id".to_string()),
+            Vec::new(),
+        );
+    };
+    let legs = match plan_legs(&state, chain, payload) {
+        Ok(legs) => legs,
+        Err(e) => return respond_multi(e, Vec::new()),
+    };
+
+    // every leg is checked against the spending policy of its asset
+    let principal = identity.map(|identity| identity.name.clone());
+    let mut reservations: Vec<Reservation> = Vec::with_capacity(legs.len());
+    for leg in &legs {
+        let asset = leg.token.map_or(Asset::Eth, Asset::Token);
+        match reserve_spend(chain, asset, principal.as_deref(), &leg.amounts) {
+            Ok(reservation) => reservations.push(reservation),
+            Err(e) => {
+                release_all(chain, &reservations);
+                return respond_multi(e, Vec::new());
+            }
+        }
+    }
+    if reservations
+        .iter()
+        .any(|reservation| reservation.needs_approval)
+    {
+        release_all(chain, &reservations);
+        return respond_multi(
+            (
+                StatusCode::FORBIDDEN,
+                "Legs above the second approval threshold must be dispersed on their own"
+                    .to_string(),
+            ),
+            Vec::new(),
+        );
     }
 
-    if payload.values.is_empty() {
-        return (StatusCode::OK, "No values provided".to_string());
+    let ((status, message), results) = disperse_multi(chain, &legs).await;
+    if status != StatusCode::OK {
+        release_all(chain, &reservations);
     }
 
-    let total_amount = payload.total_amount;
-    match payload.values_type {
-        ValuesType::Amount => {
-            return disperse_eth(payload.values).await;
+    respond_multi((status, message), results)
+}
+
+/// Normalized payments of one asset of a multi-token disperse, ETH without a `token`
+struct PlannedLeg {
+    token: Option<Address>,
+    recipients: Vec<Address>,
+    amounts: Vec<U256>,
+}
+
+impl PlannedLeg {
+    fn total(&self) -> U256 {
+        sum_u256_vector(self.amounts.clone())
+    }
+
+    fn transfers(&self) -> Vec<TransferData> {
+        self.recipients
+            .iter()
+            .zip(&self.amounts)
+            .map(|(&wallet, &amount)| TransferData { wallet, amount })
+            .collect()
+    }
+
+    fn result(&self, status: LegStatus, error: Option<String>) -> LegResult {
+        LegResult {
+            token: self.token,
+            total: self.total(),
+            payments: self
+                .recipients
+                .iter()
+                .zip(&self.amounts)
+                .map(|(&wallet, &amount)| Payment { wallet, amount })
+                .collect(),
+            status,
+            error,
         }
-        ValuesType::Percentage => {
-            if total_amount.is_none() {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    "Total amount not provided".to_string(),
-                );
-            }
+    }
+}
+
+fn plan_legs(
+    state: &AppState,
+    chain: &ChainContext,
+    payload: MultiDisperseRequest,
+) -> Result<Vec<PlannedLeg>, (StatusCode, String)> {
+    if payload.legs.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "No legs provided".to_string()));
+    }
+    let values: usize = payload.legs.iter().map(|leg| leg.values.len()).sum();
+    if values > chain.limits().max_recipients {
+        return Err((StatusCode::BAD_REQUEST, "Too many values".to_string()));
+    }
 
-            let total_amount = total_amount.unwrap();
-            let (status, result) =
-                services::calculate_amounts_from_percentages(&payload.values, total_amount);
+    let mut legs: Vec<PlannedLeg> = Vec::with_capacity(payload.legs.len());
+    for leg in payload.legs {
+        if leg.token == Some(Address::zero()) {
+            return Err((StatusCode::BAD_REQUEST, "Invalid token".to_string()));
+        }
+        if legs.iter().any(|planned| planned.token == leg.token) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Assets can only have one leg".to_string(),
+            ));
+        }
 
-            match result {
-                Ok(amounts) => return disperse_eth(amounts).await,
-                Err(message) => return (status, message),
-            }
+        let recipients = resolve_recipients(Some(leg.recipients), leg.values.len())?;
+        let amounts = leg.values.iter().map(|&x| U256::from(x)).collect();
+        let (recipients, amounts) = normalize_payments(
+            recipients,
+            amounts,
+            payload.duplicates,
+            payload.zero_amounts,
+        )?;
+        if recipients.is_empty() {
+            return Err((StatusCode::BAD_REQUEST, "Leg has no payments".to_string()));
         }
+        screen_addresses(state, chain, &recipients)?;
+
+        legs.push(PlannedLeg {
+            token: leg.token,
+            recipients,
+            amounts,
+        });
     }
+
+    Ok(legs)
 }
 
-async fn disperse_eth(amounts: Vec<u128>) -> (StatusCode, String) {
-    let anvil = Anvil::new().spawn();
-    let wallet: LocalWallet = anvil.keys()[0].clone().into(); // client wallet and token receiver
+fn release_all(chain: &ChainContext, reservations: &[Reservation]) {
+    for reservation in reservations {
+        chain.spend_policy().release(reservation.id);
+    }
+}
 
-    let provider = Provider::<Http>::try_from(anvil.endpoint())
-        .unwrap()
-        .interval(Duration::from_millis(10u64));
+fn respond_multi(
+    (status, message): (StatusCode, String),
+    legs: Vec<LegResult>,
+) -> (StatusCode, AxumJson<MultiDisperseResponse>) {
+    (status, AxumJson(MultiDisperseResponse { message, legs }))
+}
 
-    let client = Arc::new(SignerMiddleware::new(
-        provider.clone(),
-        wallet.clone().with_chain_id(anvil.chain_id()),
-    ));
+/// Pays every leg in one `disperseMulti` call, tokens come out of the operator's wallet
+async fn disperse_multi(
+    chain: &ChainContext,
+    legs: &[PlannedLeg],
+) -> ((StatusCode, String), Vec<LegResult>) {
+    let disperse_contract = chain.disperse_contract();
+    let operator = chain.wallet().address();
+    let just_in_time = *chain.just_in_time_approval();
 
-    let disperse_contract = Disperse::deploy(client, ()).unwrap().send().await.unwrap();
+    let mut errors: Vec<Option<String>> = vec![None; legs.len()];
+    let mut approved = Vec::new();
+    for (leg, error) in legs.iter().zip(errors.iter_mut()) {
+        let Some(token) = leg.token else {
+            continue;
+        };
 
-    let disperce_addresses = generate_addresses(amounts.len() as u8).unwrap();
-    let amounts_u256: Vec<U256> = amounts.iter().map(|&x| U256::from(x)).collect();
+        if just_in_time {
+            let (status, message) = approve_disperse(chain, token, leg.total()).await;
+            if status != StatusCode::OK {
+                *error = Some(format!("Approval failed: {}", message));
+                continue;
+            }
+            approved.push(token);
+        }
+        if let Err((_, message)) = preflight_erc20(
+            chain,
+            token,
+            Some(disperse_contract.address()),
+            &[(operator, leg.total())],
+        )
+        .await
+        {
+            *error = Some(message);
+        }
+    }
 
-    let disperse_data: Vec<TransferData> = disperce_addresses
-        .into_iter()
-        .zip(amounts_u256.clone().into_iter())
-        .map(|(wallet, amount)| TransferData {
-            wallet: get_solidity_address(wallet),
-            amount,
+    let eth_leg = legs.iter().position(|leg| leg.token.is_none());
+    let eth_transfers = eth_leg.map_or_else(Vec::new, |index| legs[index].transfers());
+    let value = eth_leg.map_or_else(U256::zero, |index| legs[index].total());
+    let token_legs = legs
+        .iter()
+        .filter_map(|leg| {
+            leg.token.map(|token| TokenLeg {
+                token,
+                transfers: leg.transfers(),
+            })
         })
         .collect();
+    let call = chain.prepare(
+        disperse_contract
+            .disperse_multi(eth_transfers, token_legs)
+            .value(value),
+    );
 
-    let collect_eth_call = disperse_contract
-        .disperse_eth(disperse_data)
-        .value(sum_u256_vector(amounts_u256));
-    let collect_eth_send = collect_eth_call.send().await;
+    // gas can only be estimated once every token leg is covered
+    let preflight_passed = errors.iter().all(Option::is_none);
+    let result = if !preflight_passed {
+        Err((
+            StatusCode::BAD_REQUEST,
+            "Preflight failed, see the legs for details".to_string(),
+        ))
+    } else if let Err((status, message)) = preflight_eth(chain, &call, value).await {
+        if let Some(index) = eth_leg {
+            errors[index] = Some(message.clone());
+        }
+        Err((status, message))
+    } else {
+        confirm_transaction(chain, "disperse", "multi", call.send().await).await
+    };
+
+    for token in approved {
+        reset_allowance(chain, token, true).await;
+    }
+
+    match result {
+        Ok(receipt) => {
+            let completed: Vec<LegCompletedFilter> = receipt
+                .logs
+                .into_iter()
+                .filter(|log| log.address == disperse_contract.address())
+                .filter_map(|log| parse_log::<LegCompletedFilter>(log).ok())
+                .collect();
+            let results = legs
+                .iter()
+                .map(|leg| {
+                    let token = leg.token.unwrap_or_default();
+                    if completed.iter().any(|event| event.token == token) {
+                        leg.result(LegStatus::Paid, None)
+                    } else {
+                        leg.result(
+                            LegStatus::Failed,
+                            Some("Leg completion event not found in receipt".to_string()),
+                        )
+                    }
+                })
+                .collect();
 
-    match collect_eth_send {
-        Ok(tx_receipt) => {
-            return (
-                StatusCode::OK,
-                format!("Transaction successful: {:?}", tx_receipt.tx_hash()),
-            );
+            (
+                (
+                    StatusCode::OK,
+                    format!("Transaction successful: {:?}", receipt.transaction_hash),
+                ),
+                results,
+            )
         }
         Err(e) => {
-            return (
+            let results = legs
+                .iter()
+                .zip(errors)
+                .map(|(leg, error)| match (error, preflight_passed) {
+                    (Some(error), _) => leg.result(LegStatus::Failed, Some(error)),
+                    (None, true) => leg.result(LegStatus::Failed, None),
+                    (None, false) => leg.result(LegStatus::Skipped, None),
+                })
+                .collect();
+
+            (e, results)
+        }
+    }
+}
+
+/// Validated disperse request with its spend reserved
+struct PlannedDisperse<'a> {
+    chain: &'a ChainContext,
+    token: Option<Address>,
+    recipients: Vec<Address>,
+    amounts: Vec<U256>,
+    principal: Option<String>,
+    reservation: Reservation,
+    permit2: Option<Permit2Signature>,
+    /// Approver and signature of a signed request, whose nonce is spent once the payout is checked
+    signed: Option<(Address, PayoutAuthorization)>,
+}
+
+async fn handle_disperse(
+    state: &AppState,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    payload: DisperseRequest,
+    kind: JobKind,
+) -> (StatusCode, AxumJson<DisperseResponse>) {
+    if payload.values.is_empty() {
+        return respond(
+            (StatusCode::OK, "No values provided".to_string()),
+            Vec::new(),
+        );
+    }
+
+    if payload.unsigned && payload.permit2.is_some() {
+        return respond(
+            (
                 StatusCode::BAD_REQUEST,
-                format!("Transaction failed: {:?}", e),
-            );
+                "Permit2 is not supported for unsigned requests".to_string(),
+            ),
+            Vec::new(),
+        );
+    }
+    if payload.unsigned && kind == JobKind::DisperseErc20 && payload.token.is_none() {
+        return respond(
+            (
+                StatusCode::BAD_REQUEST,
+                "Unsigned requests must specify a token".to_string(),
+            ),
+            Vec::new(),
+        );
+    }
+
+    let (unsigned, from) = (payload.unsigned, payload.from);
+    let plan = match plan_disperse(state, identity.as_deref(), payload, kind).await {
+        Ok(plan) => plan,
+        Err(e) => return respond(e, Vec::new()),
+    };
+    let payments = plan
+        .recipients
+        .iter()
+        .zip(&plan.amounts)
+        .map(|(&wallet, &amount)| Payment { wallet, amount })
+        .collect();
+
+    let chain = plan.chain;
+    let mut job = Job::new(
+        *chain.chain_id(),
+        kind,
+        plan.token,
+        plan.recipients,
+        plan.amounts,
+        plan.principal,
+        plan.reservation.id,
+        unix_timestamp(),
+    );
+    job.permit2 = plan.permit2;
+
+    let signing = match Signing::new(chain, unsigned, from) {
+        Ok(signing) => signing,
+        Err(e) => {
+            chain.spend_policy().release(plan.reservation.id);
+            return respond(e, payments);
         }
+    };
+    let signed = plan.signed.as_ref();
+
+    // offline jobs are built for their signer once approved
+    if plan.reservation.needs_approval {
+        if let Err(e) = consume_authorization(state, signed) {
+            chain.spend_policy().release(plan.reservation.id);
+            return respond(e, payments);
+        }
+        if let Signing::Offline(from) = signing {
+            job.signer = Some(from);
+        }
+        return respond(hold_for_approval(state, job), payments);
     }
+
+    if let Signing::Offline(from) = signing {
+        let call = offline_disperse_call(chain, &job);
+        let unsigned = match prepare_unsigned(state, chain, call, from, job).await {
+            Ok(unsigned) => unsigned,
+            Err(e) => {
+                chain.spend_policy().release(plan.reservation.id);
+                return respond(e, payments);
+            }
+        };
+        // building the transaction was its pre-flight check
+        if let Err(e) = consume_authorization(state, signed) {
+            let _ = state.jobs().cancel(&unsigned.job_id, unix_timestamp());
+            chain.spend_policy().release(plan.reservation.id);
+            return respond(e, payments);
+        }
+
+        return (
+            StatusCode::ACCEPTED,
+            AxumJson(DisperseResponse {
+                message: format!("Awaiting signature: job {}", unsigned.job_id),
+                payments,
+                transaction: Some(unsigned),
+            }),
+        );
+    }
+
+    if signed.is_some() {
+        let checked = match preflight_disperse(chain, &job).await {
+            Ok(()) => consume_authorization(state, signed),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = checked {
+            chain.spend_policy().release(plan.reservation.id);
+            return respond(e, payments);
+        }
+    }
+
+    let (status, message) = execute_disperse(chain, job).await;
+    if status != StatusCode::OK {
+        chain.spend_policy().release(plan.reservation.id);
+    }
+
+    respond((status, message), payments)
 }
 
-// Handler for /disperse/erc20
-pub async fn disperse_erc20_handler(
-    AxumJson(payload): AxumJson<DisperseRequest>,
+async fn plan_disperse<'a>(
+    state: &'a AppState,
+    identity: Option<&ApiKeyIdentity>,
+    payload: DisperseRequest,
+    kind: JobKind,
+) -> Result<PlannedDisperse<'a>, (StatusCode, String)> {
+    let Some(chain) = state.chain(payload.chain_id) else {
+        return Err((StatusCode::BAD_REQUEST, "Unknown chain id".to_string()));
+    };
+
+    if payload.values.len() > chain.limits().max_recipients {
+        return Err((StatusCode::BAD_REQUEST, "Too many values".to_string()));
+    }
+
+    let (asset, token) = match kind {
+        JobKind::DisperseEth if payload.permit2.is_some() => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Permit2 is only supported for ERC20".to_string(),
+            ));
+        }
+        JobKind::DisperseEth => (Asset::Eth, None),
+        JobKind::DisperseErc20 => {
+            if payload.token.is_none() && payload.authorization.is_some() {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Signed requests must specify a token".to_string(),
+                ));
+            }
+            if payload.token.is_none() && payload.permit2.is_some() {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Permit2 requests must specify a token".to_string(),
+                ));
+            }
+            if payload.token.is_none() && !chain.is_dev() {
+                return Err((StatusCode::BAD_REQUEST, "Token not provided".to_string()));
+            }
+            (
+                Asset::Token(payload.token.unwrap_or_default()),
+                payload.token,
+            )
+        }
+        JobKind::CollectEth | JobKind::CollectErc20 => {
+            return Err((StatusCode::BAD_REQUEST, "Not a disperse job".to_string()))
+        }
+    };
+
+    let amounts = resolve_amounts(&payload.values, &payload.values_type, payload.total_amount)?;
+    let recipients = resolve_recipients(payload.recipients, amounts.len())?;
+    let amounts_u256: Vec<U256> = amounts.iter().map(|&x| U256::from(x)).collect();
+
+    // the normalized list is what gets screened, signed, reserved and paid
+    let (recipients, amounts_u256) = normalize_payments(
+        recipients,
+        amounts_u256,
+        payload.duplicates,
+        payload.zero_amounts,
+    )?;
+    screen_addresses(state, chain, &recipients)?;
+
+    if let (Some(permit2), Some(token)) = (&payload.permit2, token) {
+        screen_addresses(state, chain, &[permit2.owner])?;
+        let permitted = permitted(token, &amounts_u256);
+        verify_permit2(
+            chain,
+            chain.disperse_contract().address(),
+            &permitted,
+            permit2,
+        )
+        .await?;
+    }
+
+    let payout = Payout {
+        chain_id: *chain.chain_id(),
+        verifying_contract: chain.disperse_contract().address(),
+        recipients: &recipients,
+        amounts: &amounts_u256,
+        token: token.unwrap_or_default(),
+    };
+    let signer = authorize_payout(state, identity, payload.authorization.as_ref(), &payout)?;
+    let signed = signer.zip(payload.authorization);
+
+    // spend caps are tracked per API key, or per approver for signed requests
+    let principal = match (identity, signer) {
+        (Some(identity), _) => Some(identity.name.clone()),
+        (None, Some(signer)) => Some(format!("{:?}", signer)),
+        (None, None) => None,
+    };
+    // Permit2 payouts are funded by the token owner, not the operator whose spend the caps limit
+    let reservation = if payload.permit2.is_some() {
+        chain
+            .spend_policy()
+            .check_asset(asset)
+            .map_err(|violation| (StatusCode::FORBIDDEN, violation.to_string()))?;
+        Reservation::exempt()
+    } else {
+        reserve_spend(chain, asset, principal.as_deref(), &amounts_u256)?
+    };
+
+    Ok(PlannedDisperse {
+        chain,
+        token,
+        recipients,
+        amounts: amounts_u256,
+        principal,
+        reservation,
+        permit2: payload.permit2,
+        signed,
+    })
+}
+
+/// Permit2 token permissions, one per transfer
+fn permitted(token: Address, amounts: &[U256]) -> Vec<(Address, U256)> {
+    amounts.iter().map(|&amount| (token, amount)).collect()
+}
+
+fn respond(
+    (status, message): (StatusCode, String),
+    payments: Vec<Payment>,
+) -> (StatusCode, AxumJson<DisperseResponse>) {
+    (
+        status,
+        AxumJson(DisperseResponse {
+            message,
+            payments,
+            transaction: None,
+        }),
+    )
+}
+
+async fn disperse_eth(
+    chain: &ChainContext,
+    recipients: Vec<Address>,
+    amounts_u256: Vec<U256>,
 ) -> (StatusCode, String) {
-    if payload.values.len() > 100 {
-        return (StatusCode::BAD_REQUEST, "Too many values".to_string());
+    let disperse_contract = chain.disperse_contract();
+
+    let disperse_data: Vec<TransferData> = recipients
+        .into_iter()
+        .zip(amounts_u256.clone())
+        .map(|(wallet, amount)| TransferData { wallet, amount })
+        .collect();
+
+    let value = sum_u256_vector(amounts_u256);
+    let collect_eth_call =
+        chain.prepare(disperse_contract.disperse_eth(disperse_data).value(value));
+    if let Err(e) = preflight_eth(chain, &collect_eth_call, value).await {
+        return e;
     }
+    let collect_eth_send = collect_eth_call.send().await;
 
-    if payload.values.is_empty() {
-        return (StatusCode::OK, "No values provided".to_string());
+    settle_transaction(chain, "disperse", "eth", collect_eth_send).await
+}
+
+/// Checks the operator, or the Permit2 owner, can fund a job before an approver's nonce is spent
+async fn preflight_disperse(chain: &ChainContext, job: &Job) -> Result<(), (StatusCode, String)> {
+    let total = sum_u256_vector(job.amounts.clone());
+
+    match (job.kind, job.token, &job.permit2) {
+        (JobKind::DisperseEth, _, _) => {
+            let call = chain.prepare(offline_disperse_call(chain, job));
+            preflight_eth(chain, &call, total).await
+        }
+        (_, Some(token), Some(permit2)) => {
+            preflight_erc20(
+                chain,
+                token,
+                Some(*chain.permit2()),
+                &[(permit2.owner, total)],
+            )
+            .await
+        }
+        (_, Some(token), None) => {
+            // just-in-time allowances are only granted when the payout runs
+            let spender =
+                (!*chain.just_in_time_approval()).then(|| chain.disperse_contract().address());
+            preflight_erc20(chain, token, spender, &[(chain.wallet().address(), total)]).await
+        }
+        // the test token is deployed when the payout runs
+        (_, None, _) => Ok(()),
     }
+}
 
-    let total_amount = payload.total_amount;
-    match payload.values_type {
-        ValuesType::Amount => {
-            return disperse_erc20(payload.values).await;
+/// `disperseETH` or `disperseERC20` call of a job signed offline, funded by its signer
+pub(crate) fn offline_disperse_call(chain: &ChainContext, job: &Job) -> ContractCall<Client, ()> {
+    let disperse_contract = chain.disperse_contract();
+    let disperse_data: Vec<TransferData> = job
+        .recipients
+        .iter()
+        .zip(&job.amounts)
+        .map(|(&wallet, &amount)| TransferData { wallet, amount })
+        .collect();
+
+    match job.token {
+        None => disperse_contract
+            .disperse_eth(disperse_data)
+            .value(sum_u256_vector(job.amounts.clone())),
+        Some(token) => disperse_contract.disperse_erc20(token, disperse_data),
+    }
+}
+
+/// Signs and broadcasts an approved disperse job
+pub(crate) async fn execute_disperse(chain: &ChainContext, job: Job) -> (StatusCode, String) {
+    match job.kind {
+        JobKind::DisperseEth => disperse_eth(chain, job.recipients, job.amounts).await,
+        JobKind::DisperseErc20 => {
+            disperse_erc20(chain, job.token, job.recipients, job.amounts, job.permit2).await
         }
-        ValuesType::Percentage => {
-            if total_amount.is_none() {
+        JobKind::CollectEth | JobKind::CollectErc20 => {
+            (StatusCode::BAD_REQUEST, "Not a disperse job".to_string())
+        }
+    }
+}
+
+async fn disperse_erc20(
+    chain: &ChainContext,
+    token: Option<Address>,
+    recipients: Vec<Address>,
+    amounts_u256: Vec<U256>,
+    permit2: Option<Permit2Signature>,
+) -> (StatusCode, String) {
+    if let (Some(permit2), Some(token)) = (permit2, token) {
+        return disperse_erc20_with_permit2(chain, token, recipients, amounts_u256, permit2).await;
+    }
+
+    let wallet = chain.wallet(); // client account and sender
+    let disperse_contract = chain.disperse_contract();
+
+    let test_token_deployed = token.is_none();
+    let test_erc20_contract = match token {
+        Some(token) => TestToken::new(token, chain.client().clone()),
+        None => match deploy_test_token(chain).await {
+            Ok(contract) => contract,
+            Err(e) => {
                 return (
-                    StatusCode::BAD_REQUEST,
-                    "Total amount not provided".to_string(),
-                );
+                    StatusCode::BAD_GATEWAY,
+                    format!("Failed to deploy test token: {:?}", e),
+                )
             }
+        },
+    };
 
-            let total_amount = total_amount.unwrap();
-            let (status, result) =
-                services::calculate_amounts_from_percentages(&payload.values, total_amount);
+    let token = test_erc20_contract.address();
+    let total = sum_u256_vector(amounts_u256.clone());
 
-            match result {
-                Ok(amounts) => return disperse_erc20(amounts).await,
-                Err(message) => return (status, message),
-            }
+    // deployed test tokens have no standing allowance
+    let just_in_time = *chain.just_in_time_approval() || test_token_deployed;
+    if just_in_time {
+        let (status, message) = approve_disperse(chain, token, total).await;
+        if status != StatusCode::OK {
+            return (status, format!("Approval failed: {}", message));
         }
     }
-}
 
-async fn disperse_erc20(amounts: Vec<u128>) -> (StatusCode, String) {
-    let anvil = Anvil::new().spawn();
-    let wallet: LocalWallet = anvil.keys()[0].clone().into(); // client account and sender
+    if let Err(e) = preflight_erc20(
+        chain,
+        token,
+        Some(disperse_contract.address()),
+        &[(wallet.address(), total)],
+    )
+    .await
+    {
+        reset_allowance(chain, token, just_in_time).await;
+        return e;
+    }
 
-    let provider = Provider::<Http>::try_from(anvil.endpoint())
-        .unwrap()
-        .interval(Duration::from_millis(10u64));
+    let disperse_data: Vec<TransferData> = recipients
+        .into_iter()
+        .zip(amounts_u256)
+        .map(|(wallet, amount)| TransferData { wallet, amount })
+        .collect();
 
-    let client = Arc::new(SignerMiddleware::new(
-        provider.clone(),
-        wallet.clone().with_chain_id(anvil.chain_id()),
-    ));
+    let disperse_contract_call = chain
+        .prepare(disperse_contract.disperse_erc20(test_erc20_contract.address(), disperse_data));
+    let disperse_contract_send = disperse_contract_call.send().await;
 
-    let disperse_contract = Disperse::deploy(client.clone(), ())
-        .unwrap()
-        .send()
-        .await
-        .unwrap();
-    let test_erc20_contract = TestToken::deploy(client.clone(), wallet.address())
-        .unwrap()
-        .send()
-        .await
-        .unwrap();
+    let result = settle_transaction(chain, "disperse", "erc20", disperse_contract_send).await;
+    reset_allowance(chain, token, just_in_time).await;
+
+    result
+}
 
-    test_erc20_contract
-        .approve(disperse_contract.address().clone(), U256::from(1000000))
+async fn deploy_test_token(
+    chain: &ChainContext,
+) -> Result<TestToken<Client>, ContractError<Client>> {
+    TestToken::deploy(chain.client().clone(), chain.wallet().address())?
         .send()
         .await
-        .unwrap();
+}
 
-    let disperce_addresses = generate_addresses(amounts.len() as u8).unwrap();
-    let amounts_u256: Vec<U256> = amounts.iter().map(|&x| U256::from(x)).collect();
+/// Disperses tokens of the Permit2 signer, who approved the Permit2 contract instead of Disperse
+async fn disperse_erc20_with_permit2(
+    chain: &ChainContext,
+    token: Address,
+    recipients: Vec<Address>,
+    amounts_u256: Vec<U256>,
+    permit2: Permit2Signature,
+) -> (StatusCode, String) {
+    let disperse_contract = chain.disperse_contract();
+
+    // checked again as held jobs may run close to the deadline
+    let permitted = permitted(token, &amounts_u256);
+    let signature =
+        match verify_permit2(chain, disperse_contract.address(), &permitted, &permit2).await {
+            Ok(signature) => signature,
+            Err(e) => return e,
+        };
 
-    let disperse_data: Vec<TransferData> = disperce_addresses
+    let total = sum_u256_vector(amounts_u256.clone());
+    if let Err(e) = preflight_erc20(
+        chain,
+        token,
+        Some(*chain.permit2()),
+        &[(permit2.owner, total)],
+    )
+    .await
+    {
+        return e;
+    }
+
+    let permit = PermitBatchTransferFrom {
+        permitted: permitted
+            .into_iter()
+            .map(|(token, amount)| TokenPermissions { token, amount })
+            .collect(),
+        nonce: permit2.nonce.0,
+        deadline: permit2.deadline.into(),
+    };
+    let disperse_data: Vec<TransferData> = recipients
         .into_iter()
-        .zip(amounts_u256.clone().into_iter())
-        .map(|(wallet, amount)| TransferData {
-            wallet: get_solidity_address(wallet),
-            amount,
-        })
+        .zip(amounts_u256)
+        .map(|(wallet, amount)| TransferData { wallet, amount })
         .collect();
 
-    let disperse_contract_call = disperse_contract.disperse_erc20(
-        test_erc20_contract.address(),
-        wallet.address(),
+    let disperse_contract_call = chain.prepare(disperse_contract.disperse_erc20_with_permit_2(
+        *chain.permit2(),
+        permit2.owner,
+        permit,
         disperse_data,
-    );
+        signature,
+    ));
     let disperse_contract_send = disperse_contract_call.send().await;
 
-    match disperse_contract_send {
-        Ok(tx_receipt) => {
-            return (
-                StatusCode::OK,
-                format!("Transaction successful: {:?}", tx_receipt.tx_hash()),
-            );
-        }
-        Err(e) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                format!("Transaction failed: {:?}", e),
-            );
+    settle_transaction(chain, "disperse", "erc20", disperse_contract_send).await
+}
+
+/// Revokes what is left of a just-in-time approval, a mined disperse already spends all of it
+async fn reset_allowance(chain: &ChainContext, token: Address, just_in_time: bool) {
+    if !just_in_time {
+        return;
+    }
+
+    match disperse_allowance(chain, token).await {
+        Ok(allowance) if allowance.is_zero() => {}
+        Ok(_) => {
+            let (status, message) = approve_disperse(chain, token, U256::zero()).await;
+            if status != StatusCode::OK {
+                warn!(token = ?token, message, "Failed to reset allowance");
+            }
         }
+        Err(e) => warn!(token = ?token, error = ?e, "Failed to read allowance"),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{Amount, AssetPolicy};
+    use crate::handlers::collect::collect_erc20_permit2_handler;
+    use crate::permits::Permit2Batch;
+    use crate::state::{test_config, test_state};
     use axum::http::StatusCode;
     use axum::{body::Body, routing::post, Router};
-    use serde_json::json;
+    use serde_json::{json, Value};
     use tower::ServiceExt;
 
+    abigen!(
+        TestPermit2,
+        "../contracts/out/TestPermit2.sol/TestPermit2.json"
+    );
+
+    async fn post_json(app: Router, uri: &str, payload: Value) -> StatusCode {
+        app.oneshot(
+            axum::http::Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("Content-Type", "application/json")
+                .body(Body::from(payload.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+    }
+
     #[tokio::test]
     async fn test_collect_eth_valid_amounts() {
-        let app = Router::new().route("/disperse/eth", post(disperse_eth_handler));
+        let (_anvil, state) = test_state().await;
+        let app = Router::new()
+            .route("/disperse/eth", post(disperse_eth_handler))
+            .with_state(state);
 
         let payload = json!({
             "values": [100, 300],
@@ -229,9 +873,76 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_rejected_signed_payout_keeps_nonce() {
+        let anvil = ethers::utils::Anvil::new().spawn();
+        let approver = LocalWallet::from(anvil.keys()[1].clone());
+        let mut config = test_config(&anvil);
+        config.approvers = vec![approver.address()];
+        config.chains = config.chain_configs();
+        config.chains[0].spend_policy.assets.insert(
+            "eth".to_string(),
+            AssetPolicy {
+                max_per_transaction: Some(Amount(500.into())),
+                ..Default::default()
+            },
+        );
+        let state = AppState::init(config).await.unwrap();
+        let chain = state.chain(None).unwrap();
+        let app = Router::new()
+            .route("/disperse/eth", post(disperse_eth_handler))
+            .with_state(state.clone());
+
+        let recipients = [Address::repeat_byte(0x42)];
+        let amounts = [U256::from(1_000)];
+        let payout = Payout {
+            chain_id: *chain.chain_id(),
+            verifying_contract: chain.disperse_contract().address(),
+            recipients: &recipients,
+            amounts: &amounts,
+            token: Address::zero(),
+        };
+        let deadline = unix_timestamp() + 3600;
+        let signature = approver
+            .sign_typed_data(&payout.typed_data(1, deadline))
+            .await
+            .unwrap();
+        let authorization = PayoutAuthorization {
+            nonce: 1,
+            deadline,
+            signature: format!("0x{}", signature),
+        };
+
+        let payload = json!({
+            "values": [1000],
+            "values_type": "Amount",
+            "recipients": recipients,
+            "authorization": {
+                "nonce": authorization.nonce,
+                "deadline": authorization.deadline,
+                "signature": authorization.signature,
+            },
+        });
+        assert_eq!(
+            post_json(app, "/disperse/eth", payload).await,
+            StatusCode::FORBIDDEN
+        );
+
+        // the payout broke the policy, so the approver's signature can still be used
+        assert_eq!(
+            state
+                .approvers()
+                .verify(&payout, &authorization, unix_timestamp()),
+            Ok(approver.address())
+        );
+    }
+
     #[tokio::test]
     async fn test_collect_eth_invalid_percentage() {
-        let app = Router::new().route("/disperse/eth", post(disperse_eth_handler));
+        let (_anvil, state) = test_state().await;
+        let app = Router::new()
+            .route("/disperse/eth", post(disperse_eth_handler))
+            .with_state(state);
 
         let payload = json!({
             "values": [10, 30],
@@ -253,4 +964,122 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
+
+    #[tokio::test]
+    async fn test_permit2_disperse_and_collect() {
+        let anvil = ethers::utils::Anvil::new().spawn();
+        let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
+        let wallets: Vec<LocalWallet> = anvil.keys()[..2]
+            .iter()
+            .map(|key| LocalWallet::from(key.clone()).with_chain_id(anvil.chain_id()))
+            .collect();
+        let operator = Arc::new(SignerMiddleware::new(provider.clone(), wallets[0].clone()));
+        let owner = Arc::new(SignerMiddleware::new(provider, wallets[1].clone()));
+
+        let permit2 = TestPermit2::deploy(operator.clone(), ())
+            .unwrap()
+            .send()
+            .await
+            .unwrap()
+            .address();
+        let token = TestToken::deploy(operator.clone(), owner.address())
+            .unwrap()
+            .send()
+            .await
+            .unwrap()
+            .address();
+        TestToken::new(token, owner.clone())
+            .approve(permit2, U256::MAX)
+            .send()
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+
+        // a cap the owner-funded payouts would exceed if they were charged to the operator
+        let mut config = test_config(&anvil);
+        config.chains = config.chain_configs();
+        config.chains[0].permit2_address = Some(permit2);
+        config.chains[0].spend_policy.assets.insert(
+            format!("{:?}", token),
+            AssetPolicy {
+                cap: Some(Amount(U256::one())),
+                ..Default::default()
+            },
+        );
+        let state = AppState::init(config).await.unwrap();
+        let chain = state.chain(None).unwrap();
+        let app = Router::new()
+            .route("/disperse/erc20", post(disperse_erc20_handler))
+            .route(
+                "/collect/erc20/permit2",
+                post(collect_erc20_permit2_handler),
+            )
+            .with_state(state.clone());
+
+        let deadline = unix_timestamp() + 3600;
+        let sign = |spender: Address, permitted: Vec<(Address, U256)>, nonce: u64| {
+            let batch = Permit2Batch {
+                chain_id: *chain.chain_id(),
+                permit2,
+                spender,
+                permitted: &permitted,
+                nonce: nonce.into(),
+                deadline: deadline.into(),
+            };
+            let signature = wallets[1]
+                .sign_hash(H256::from(batch.digest().unwrap()))
+                .unwrap();
+            json!({
+                "owner": owner.address(),
+                "nonce": nonce,
+                "deadline": deadline,
+                "signature": format!("0x{}", signature),
+            })
+        };
+
+        let recipients = [Address::repeat_byte(0x21), Address::repeat_byte(0x22)];
+        let disperse = json!({
+            "values": [5, 7],
+            "values_type": "Amount",
+            "recipients": recipients,
+            "token": token,
+            "permit2": sign(
+                chain.disperse_contract().address(),
+                vec![(token, 5.into()), (token, 7.into())],
+                1
+            ),
+        });
+        assert_eq!(
+            post_json(app.clone(), "/disperse/erc20", disperse.clone()).await,
+            StatusCode::OK
+        );
+        let balance_of = |address| TestToken::new(token, owner.clone()).balance_of(address);
+        assert_eq!(balance_of(recipients[0]).call().await.unwrap(), 5.into());
+        assert_eq!(balance_of(recipients[1]).call().await.unwrap(), 7.into());
+
+        // the nonce is spent
+        assert_eq!(
+            post_json(app.clone(), "/disperse/erc20", disperse).await,
+            StatusCode::BAD_REQUEST
+        );
+
+        let receiver = Address::repeat_byte(0x23);
+        let collect = json!({
+            "receiver": receiver,
+            "transfers": [{
+                "permitted": [{ "token": token, "amount": 3 }],
+                "authorization": sign(
+                    chain.collect_contract().address(),
+                    vec![(token, 3.into())],
+                    2
+                ),
+            }],
+        });
+        assert_eq!(
+            post_json(app, "/collect/erc20/permit2", collect).await,
+            StatusCode::OK
+        );
+        assert_eq!(balance_of(receiver).call().await.unwrap(), 3.into());
+    }
 }
@@ -0,0 +1,173 @@
+use crate::state::{AppState, ChainContext};
+use crate::types::{ChainReadiness, CheckResult, ReadinessResponse};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::Json;
+use ethers::prelude::*;
+use ethers::utils::format_units;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Handler for /healthz
+pub async fn healthz_handler() -> (StatusCode, Json<serde_json::Value>) {
+    (StatusCode::OK, Json(serde_json::json!({ "status": "ok" })))
+}
+
+// Handler for /readyz
+pub async fn readyz_handler(
+    State(state): State<Arc<AppState>>,
+) -> (StatusCode, Json<ReadinessResponse>) {
+    let mut chain_ids: Vec<&u64> = state.chains().keys().collect();
+    chain_ids.sort();
+
+    let mut chains = Vec::new();
+    for chain_id in chain_ids {
+        chains.push(check_chain(&state.chains()[chain_id]).await);
+    }
+
+    let ready = chains.iter().all(|chain| chain.ready);
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(ReadinessResponse { ready, chains }))
+}
+
+async fn check_chain(chain: &ChainContext) -> ChainReadiness {
+    let mut checks = BTreeMap::new();
+
+    checks.insert("rpc", check_rpc(chain).await);
+    checks.insert("latest_block", check_latest_block(chain).await);
+    checks.insert("operator_balance", check_operator_balance(chain).await);
+    checks.insert(
+        "disperse_contract",
+        check_code(chain, chain.disperse_contract().address()).await,
+    );
+    checks.insert(
+        "collect_contract",
+        check_code(chain, chain.collect_contract().address()).await,
+    );
+
+    ChainReadiness {
+        chain_id: *chain.chain_id(),
+        ready: checks.values().all(|check| check.ok),
+        checks,
+    }
+}
+
+fn check_result(ok: bool, detail: String) -> CheckResult {
+    CheckResult { ok, detail }
+}
+
+async fn check_rpc(chain: &ChainContext) -> CheckResult {
+    match chain.provider().get_chainid().await {
+        Ok(chain_id) if chain_id.as_u64() == *chain.chain_id() => {
+            check_result(true, format!("Chain id {}", chain_id))
+        }
+        Ok(chain_id) => check_result(
+            false,
+            format!("Chain id {}, expected {}", chain_id, chain.chain_id()),
+        ),
+        Err(e) => check_result(false, format!("RPC unreachable: {}", e)),
+    }
+}
+
+async fn check_latest_block(chain: &ChainContext) -> CheckResult {
+    let block = match chain.provider().get_block(BlockNumber::Latest).await {
+        Ok(Some(block)) => block,
+        Ok(None) => return check_result(false, "Latest block not found".to_string()),
+        Err(e) => return check_result(false, format!("Failed to fetch latest block: {}", e)),
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let age = now.saturating_sub(block.timestamp.as_u64());
+    let number = block.number.unwrap_or_default();
+
+    match chain.readiness().max_block_age_secs {
+        Some(max_age) if age > max_age => check_result(
+            false,
+            format!("Block {} is {}s old, max {}s", number, age, max_age),
+        ),
+        _ => check_result(true, format!("Block {} is {}s old", number, age)),
+    }
+}
+
+async fn check_operator_balance(chain: &ChainContext) -> CheckResult {
+    let address = chain.wallet().address();
+    let balance = match chain.provider().get_balance(address, None).await {
+        Ok(balance) => balance,
+        Err(e) => return check_result(false, format!("Failed to fetch balance: {}", e)),
+    };
+
+    let min_balance = U256::from(chain.readiness().min_operator_balance_gwei) * U256::exp10(9);
+    let balance_eth = format_units(balance, "ether").unwrap_or_default();
+    let min_balance_eth = format_units(min_balance, "ether").unwrap_or_default();
+
+    check_result(
+        balance >= min_balance,
+        format!(
+            "Operator {:?} holds {} ETH, min {} ETH",
+            address, balance_eth, min_balance_eth
+        ),
+    )
+}
+
+async fn check_code(chain: &ChainContext, address: Address) -> CheckResult {
+    match chain.provider().get_code(address, None).await {
+        Ok(code) if !code.is_empty() => check_result(true, format!("Code found at {:?}", address)),
+        Ok(_) => check_result(false, format!("No code at {:?}", address)),
+        Err(e) => check_result(false, format!("Failed to fetch code: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::test_state;
+    use axum::http::StatusCode;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_healthz() {
+        let app = Router::new().route("/healthz", get(healthz_handler));
+
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri("/healthz")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_readyz_deployed_contracts() {
+        let (_anvil, state) = test_state().await;
+        let app = Router::new()
+            .route("/readyz", get(readyz_handler))
+            .with_state(state);
+
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri("/readyz")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
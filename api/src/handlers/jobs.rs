@@ -0,0 +1,402 @@
+use crate::auth::ApiKeyIdentity;
+use crate::error::ApiError;
+use crate::handlers::disperse::{execute_disperse, offline_disperse_call};
+use crate::handlers::services::{
+    prepare_unsigned, screen_addresses, settle_transaction, unix_timestamp,
+};
+use crate::jobs::{Job, JobError, JobKind, JobStatus};
+use crate::signing::verify_signed;
+use crate::state::{AppState, ChainContext};
+use crate::types::BroadcastRequest;
+use axum::extract::{Extension, Path, State};
+use axum::http::StatusCode;
+use axum::response::Json;
+use ethers::prelude::{Address, Bytes, ContractError, Middleware};
+use std::sync::Arc;
+use tracing::{info, info_span, Instrument, Span};
+
+/// Stores a payout that needs a second approval instead of sending it
+pub fn hold_for_approval(state: &AppState, job: Job) -> (StatusCode, String) {
+    Span::current().record("job_id", job.id.as_str());
+    info!(job_id = %job.id, "Payout is awaiting a second approval");
+
+    let message = format!("Awaiting approval: job {}", job.id);
+    state.jobs().insert(job);
+
+    (StatusCode::ACCEPTED, message)
+}
+
+// Handler for GET /jobs
+pub async fn list_jobs_handler(State(state): State<Arc<AppState>>) -> Json<Vec<Job>> {
+    Json(state.jobs().list())
+}
+
+// Handler for GET /jobs/:id
+pub async fn get_job_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, ApiError> {
+    state
+        .jobs()
+        .get(&id)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Job not found"))
+}
+
+// Handler for POST /jobs/:id/approve
+pub async fn approve_job_handler(
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<Job>), ApiError> {
+    let approver = approver(identity)?;
+    Span::current().record("job_id", id.as_str());
+
+    let job = state
+        .jobs()
+        .decide(&id, &approver, true, unix_timestamp())?;
+    info!(job_id = %job.id, approver, "Job approved");
+
+    if let Some(from) = job.signer {
+        return build_approved(&state, job, from).await;
+    }
+
+    let span = info_span!("job", job_id = %job.id);
+    tokio::spawn(run_job(state.clone(), job.clone()).instrument(span));
+
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+// Handler for POST /jobs/:id/reject
+pub async fn reject_job_handler(
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, ApiError> {
+    let approver = approver(identity)?;
+    Span::current().record("job_id", id.as_str());
+
+    let job = state
+        .jobs()
+        .decide(&id, &approver, false, unix_timestamp())?;
+    info!(job_id = %job.id, approver, "Job rejected");
+
+    if let Some(chain) = state.chain(Some(job.chain_id)) {
+        chain.spend_policy().release(job.spend_id);
+    }
+
+    Ok(Json(job))
+}
+
+// Handler for DELETE /jobs/:id
+pub async fn cancel_job_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, ApiError> {
+    Span::current().record("job_id", id.as_str());
+
+    let job = state.jobs().cancel(&id, unix_timestamp())?;
+    info!(job_id = %job.id, "Job cancelled");
+
+    if let Some(chain) = state.chain(Some(job.chain_id)) {
+        chain.spend_policy().release(job.spend_id);
+    }
+
+    Ok(Json(job))
+}
+
+// Handler for POST /broadcast
+pub async fn broadcast_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<BroadcastRequest>,
+) -> Result<(StatusCode, Json<Job>), ApiError> {
+    Span::current().record("job_id", payload.job_id.as_str());
+
+    let job = state
+        .jobs()
+        .get(&payload.job_id)
+        .ok_or_else(|| ApiError::not_found("Job not found"))?;
+    let Some(prepared) = job
+        .transaction
+        .as_ref()
+        .filter(|_| job.status == JobStatus::AwaitingSignature)
+    else {
+        return Err(JobError::NotAwaitingSignature(job.status).into());
+    };
+    verify_signed(prepared, &payload.raw_transaction)
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let chain = state
+        .chain(Some(job.chain_id))
+        .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "Chain is no longer configured"))?;
+
+    // the blocklist may have changed while the transaction was being signed
+    let mut addresses = job.recipients.clone();
+    addresses.extend(job.receiver);
+    if let Err((status, message)) = screen_addresses(&state, chain, &addresses) {
+        // a concurrent broadcast or cancellation may have moved the job on already
+        state
+            .jobs()
+            .fail_if_awaiting_signature(&job.id, message.clone(), unix_timestamp())?;
+        chain.spend_policy().release(job.spend_id);
+        return Err(ApiError::new(status, message));
+    }
+
+    let job = state.jobs().submit_signed(&job.id, unix_timestamp())?;
+    info!(job_id = %job.id, "Signed transaction received");
+
+    let span = info_span!("job", job_id = %job.id);
+    tokio::spawn(send_signed(state.clone(), job.clone(), payload.raw_transaction).instrument(span));
+
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+fn approver(identity: Option<Extension<ApiKeyIdentity>>) -> Result<String, ApiError> {
+    identity
+        .map(|Extension(identity)| identity.name)
+        .ok_or_else(|| ApiError::forbidden("Approvals require an API key"))
+}
+
+/// Builds the transaction of an approved offline job, which then awaits its signature
+async fn build_approved(
+    state: &AppState,
+    job: Job,
+    from: Address,
+) -> Result<(StatusCode, Json<Job>), ApiError> {
+    let (id, spend_id) = (job.id.clone(), job.spend_id);
+    let fail = |chain: Option<&ChainContext>, (status, message): (StatusCode, String)| {
+        if let Some(chain) = chain {
+            chain.spend_policy().release(spend_id);
+        }
+        state
+            .jobs()
+            .finish(&id, false, message.clone(), unix_timestamp());
+        ApiError::new(status, message)
+    };
+
+    let Some(chain) = state.chain(Some(job.chain_id)) else {
+        return Err(fail(
+            None,
+            (
+                StatusCode::BAD_REQUEST,
+                "Chain is no longer configured".to_string(),
+            ),
+        ));
+    };
+
+    // the blocklist may have changed while the job was waiting
+    if let Err(e) = screen_addresses(state, chain, &job.recipients) {
+        return Err(fail(Some(chain), e));
+    }
+
+    let call = offline_disperse_call(chain, &job);
+    if let Err(e) = prepare_unsigned(state, chain, call, from, job).await {
+        return Err(fail(Some(chain), e));
+    }
+
+    let job = state
+        .jobs()
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found("Job not found"))?;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// Worker of an approved job: signs and broadcasts the payout and stores the outcome
+async fn run_job(state: Arc<AppState>, job: Job) {
+    let Some(chain) = state.chain(Some(job.chain_id)) else {
+        state.jobs().finish(
+            &job.id,
+            false,
+            "Chain is no longer configured".to_string(),
+            unix_timestamp(),
+        );
+        return;
+    };
+
+    let (id, spend_id) = (job.id.clone(), job.spend_id);
+
+    // the blocklist may have changed while the job was waiting
+    let mut addresses = job.recipients.clone();
+    addresses.extend(job.permit2.as_ref().map(|permit2| permit2.owner));
+    let (status, message) = match screen_addresses(&state, chain, &addresses) {
+        Ok(()) => execute_disperse(chain, job).await,
+        Err(e) => e,
+    };
+    let succeeded = status == StatusCode::OK;
+    if !succeeded {
+        chain.spend_policy().release(spend_id);
+    }
+
+    info!(succeeded, result = %message, "Job finished");
+    state
+        .jobs()
+        .finish(&id, succeeded, message, unix_timestamp());
+}
+
+/// Broadcasts an offline-signed job and stores the outcome
+async fn send_signed(state: Arc<AppState>, job: Job, raw_transaction: Bytes) {
+    let chain = state
+        .chain(Some(job.chain_id))
+        .expect("broadcast chain is configured");
+    let (operation, asset) = match job.kind {
+        JobKind::DisperseEth => ("disperse", "eth"),
+        JobKind::DisperseErc20 => ("disperse", "erc20"),
+        JobKind::CollectEth => ("collect", "eth"),
+        JobKind::CollectErc20 => ("collect", "erc20"),
+    };
+
+    let sent = chain
+        .provider()
+        .send_raw_transaction(raw_transaction)
+        .await
+        .map_err(|e| ContractError::ProviderError { e });
+    let (status, message) = settle_transaction(chain, operation, asset, sent).await;
+    let succeeded = status == StatusCode::OK;
+    if !succeeded {
+        chain.spend_policy().release(job.spend_id);
+    }
+
+    info!(succeeded, result = %message, "Job finished");
+    state
+        .jobs()
+        .finish(&job.id, succeeded, message, unix_timestamp());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::test_state;
+    use axum::{
+        body::Body,
+        routing::{delete, post},
+        Router,
+    };
+    use ethers::types::{Address, U256};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    fn held_job(state: &AppState, recipient: Address) -> Job {
+        let chain = state.chain(None).unwrap();
+        Job::new(
+            *chain.chain_id(),
+            JobKind::DisperseEth,
+            None,
+            vec![recipient],
+            vec![U256::from(1_000)],
+            Some("payroll".to_string()),
+            0,
+            unix_timestamp(),
+        )
+    }
+
+    async fn decide(state: Arc<AppState>, approver: &str, uri: String) -> StatusCode {
+        send(state, approver, "POST", uri).await
+    }
+
+    async fn send(state: Arc<AppState>, approver: &str, method: &str, uri: String) -> StatusCode {
+        let app = Router::new()
+            .route("/jobs/:id/approve", post(approve_job_handler))
+            .route("/jobs/:id/reject", post(reject_job_handler))
+            .route("/jobs/:id", delete(cancel_job_handler))
+            .layer(Extension(ApiKeyIdentity {
+                name: approver.to_string(),
+            }))
+            .with_state(state);
+
+        app.oneshot(
+            axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+    }
+
+    #[tokio::test]
+    async fn test_approved_job_runs() {
+        let (_anvil, state) = test_state().await;
+        let recipient = Address::repeat_byte(0x42);
+        let job = held_job(&state, recipient);
+        let id = job.id.clone();
+        state.jobs().insert(job);
+
+        let uri = format!("/jobs/{}/approve", id);
+        assert_eq!(
+            decide(state.clone(), "payroll", uri.clone()).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            decide(state.clone(), "treasury", uri).await,
+            StatusCode::ACCEPTED
+        );
+
+        let mut status = JobStatus::Submitted;
+        for _ in 0..100 {
+            status = state.jobs().get(&id).unwrap().status;
+            if status != JobStatus::Submitted {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(status, JobStatus::Succeeded);
+
+        let balance = state
+            .chain(None)
+            .unwrap()
+            .provider()
+            .get_balance(recipient, None)
+            .await
+            .unwrap();
+        assert_eq!(balance, U256::from(1_000));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_job_is_not_run() {
+        let (_anvil, state) = test_state().await;
+        let job = held_job(&state, Address::repeat_byte(0x43));
+        let id = job.id.clone();
+        state.jobs().insert(job);
+
+        assert_eq!(
+            decide(state.clone(), "treasury", format!("/jobs/{}/reject", id)).await,
+            StatusCode::OK
+        );
+        assert_eq!(state.jobs().get(&id).unwrap().status, JobStatus::Rejected);
+        assert_eq!(
+            decide(state.clone(), "treasury", format!("/jobs/{}/approve", id)).await,
+            StatusCode::CONFLICT
+        );
+    }
+
+    #[tokio::test]
+    async fn test_approved_offline_job_awaits_signature() {
+        let (anvil, state) = test_state().await;
+        let mut job = held_job(&state, Address::repeat_byte(0x44));
+        job.signer = Some(anvil.addresses()[1]);
+        let id = job.id.clone();
+        state.jobs().insert(job);
+
+        assert_eq!(
+            decide(state.clone(), "treasury", format!("/jobs/{}/approve", id)).await,
+            StatusCode::ACCEPTED
+        );
+        let job = state.jobs().get(&id).unwrap();
+        assert_eq!(job.status, JobStatus::AwaitingSignature);
+        let transaction = job.transaction.unwrap();
+        assert_eq!(transaction.from(), Some(&anvil.addresses()[1]));
+        assert_eq!(transaction.value(), Some(&U256::from(1_000)));
+
+        assert_eq!(
+            send(state.clone(), "treasury", "DELETE", format!("/jobs/{}", id)).await,
+            StatusCode::OK
+        );
+        assert_eq!(state.jobs().get(&id).unwrap().status, JobStatus::Cancelled);
+        assert_eq!(
+            send(state.clone(), "treasury", "DELETE", format!("/jobs/{}", id)).await,
+            StatusCode::CONFLICT
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use crate::state::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use ethers::prelude::*;
+use std::sync::Arc;
+
+// Handler for /metrics
+pub async fn metrics_handler(State(state): State<Arc<AppState>>) -> Response {
+    for chain in state.chains().values() {
+        if let Ok(balance) = chain
+            .provider()
+            .get_balance(chain.wallet().address(), None)
+            .await
+        {
+            state
+                .metrics()
+                .set_operator_balance(*chain.chain_id(), balance);
+        }
+    }
+
+    match state.metrics().encode() {
+        Ok(body) => (
+            StatusCode::OK,
+            [("Content-Type", prometheus::TEXT_FORMAT)],
+            body,
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to encode metrics: {:?}", e),
+        )
+            .into_response(),
+    }
+}
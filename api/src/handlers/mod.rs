@@ -1,3 +1,12 @@
+pub mod allowances;
 pub mod collect;
+pub mod deposits;
 pub mod disperse;
+pub mod health;
+pub mod jobs;
+pub mod metrics;
+pub mod nft;
+pub mod planner;
 pub mod services;
+pub mod sweeps;
+pub mod vaults;
@@ -0,0 +1,411 @@
+use crate::auth::ApiKeyIdentity;
+use crate::handlers::disperse::{Erc1155Transfer, Erc721Transfer};
+use crate::handlers::services::{
+    preflight_erc1155, preflight_erc721, reserve_spend, screen_addresses, settle_transaction, Nft,
+};
+use crate::policy::Asset;
+use crate::state::{AppState, ChainContext};
+use crate::types::{Erc1155DisperseRequest, Erc721DisperseRequest};
+use axum::extract::{Extension, State};
+use axum::http::StatusCode;
+use axum::response::Json;
+use ethers::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Just-in-time approvals of the Disperse contract, counted per collection
+///
+/// Concurrent disperses of a collection share one approval, granted by the first and revoked when
+/// the last one finishes, so a revoke never pulls the approval from under a running disperse.
+#[derive(Default)]
+pub struct CollectionApprovals {
+    holders: Mutex<HashMap<Address, Arc<tokio::sync::Mutex<usize>>>>,
+}
+
+impl CollectionApprovals {
+    /// Disperses relying on the approval of `token`, locked while it is granted or revoked
+    fn holders(&self, token: Address) -> Arc<tokio::sync::Mutex<usize>> {
+        self.holders
+            .lock()
+            .unwrap()
+            .entry(token)
+            .or_default()
+            .clone()
+    }
+}
+
+// Handler for /disperse/erc721
+pub async fn disperse_erc721_handler(
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    Json(payload): Json<Erc721DisperseRequest>,
+) -> (StatusCode, String) {
+    let Some(chain) = state.chain(payload.chain_id) else {
+        return (StatusCode::BAD_REQUEST, "Unknown chain id".to_string());
+    };
+    if payload.transfers.is_empty() {
+        return (StatusCode::OK, "No transfers provided".to_string());
+    }
+    if payload.transfers.len() > chain.limits().max_recipients {
+        return (StatusCode::BAD_REQUEST, "Too many transfers".to_string());
+    }
+
+    let transfers: Vec<Erc721Transfer> = payload
+        .transfers
+        .into_iter()
+        .map(|entry| Erc721Transfer {
+            wallet: entry.recipient,
+            token_id: entry.token_id.0,
+        })
+        .collect();
+    let token_ids: Vec<U256> = transfers.iter().map(|transfer| transfer.token_id).collect();
+    if let Some(index) = (1..token_ids.len()).find(|&i| token_ids[..i].contains(&token_ids[i])) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("Token id {} listed more than once", token_ids[index]),
+        );
+    }
+
+    let recipients: Vec<Address> = transfers.iter().map(|transfer| transfer.wallet).collect();
+    if let Err(e) = screen_addresses(&state, chain, &recipients) {
+        return e;
+    }
+
+    // every token counts as one unit against the collection's spending policy
+    let amounts = vec![U256::one(); transfers.len()];
+    let reservation = match reserve(chain, payload.token, identity.as_deref(), &amounts) {
+        Ok(reservation) => reservation,
+        Err(e) => return e,
+    };
+
+    let result = async {
+        preflight_erc721(chain, payload.token, &token_ids).await?;
+        let revoke = approve_collection(chain, payload.token).await?;
+
+        let call = chain.prepare(
+            chain
+                .disperse_contract()
+                .disperse_erc721(payload.token, transfers),
+        );
+        let result = settle_transaction(chain, "disperse", "erc721", call.send().await).await;
+        revoke_collection(chain, payload.token, revoke).await;
+
+        Ok(result)
+    }
+    .await
+    .unwrap_or_else(|e| e);
+
+    if result.0 != StatusCode::OK {
+        chain.spend_policy().release(reservation);
+    }
+    result
+}
+
+// Handler for /disperse/erc1155
+pub async fn disperse_erc1155_handler(
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    Json(payload): Json<Erc1155DisperseRequest>,
+) -> (StatusCode, String) {
+    let Some(chain) = state.chain(payload.chain_id) else {
+        return (StatusCode::BAD_REQUEST, "Unknown chain id".to_string());
+    };
+    if payload.transfers.is_empty() {
+        return (StatusCode::OK, "No transfers provided".to_string());
+    }
+    if payload.transfers.len() > chain.limits().max_recipients {
+        return (StatusCode::BAD_REQUEST, "Too many transfers".to_string());
+    }
+
+    let transfers: Vec<Erc1155Transfer> = payload
+        .transfers
+        .into_iter()
+        .map(|entry| Erc1155Transfer {
+            wallet: entry.recipient,
+            id: entry.id.0,
+            amount: entry.amount.0,
+        })
+        .collect();
+    if transfers.iter().any(|transfer| transfer.amount.is_zero()) {
+        return (StatusCode::BAD_REQUEST, "Zero amount".to_string());
+    }
+
+    let recipients: Vec<Address> = transfers.iter().map(|transfer| transfer.wallet).collect();
+    if let Err(e) = screen_addresses(&state, chain, &recipients) {
+        return e;
+    }
+
+    // amounts of all ids count against the contract's spending policy
+    let amounts: Vec<U256> = transfers.iter().map(|transfer| transfer.amount).collect();
+    let reservation = match reserve(chain, payload.token, identity.as_deref(), &amounts) {
+        Ok(reservation) => reservation,
+        Err(e) => return e,
+    };
+
+    let result = async {
+        let required: Vec<(U256, U256)> = transfers
+            .iter()
+            .map(|transfer| (transfer.id, transfer.amount))
+            .collect();
+        preflight_erc1155(chain, payload.token, &required).await?;
+        let revoke = approve_collection(chain, payload.token).await?;
+
+        let call = chain.prepare(
+            chain
+                .disperse_contract()
+                .disperse_erc1155(payload.token, transfers),
+        );
+        let result = settle_transaction(chain, "disperse", "erc1155", call.send().await).await;
+        revoke_collection(chain, payload.token, revoke).await;
+
+        Ok(result)
+    }
+    .await
+    .unwrap_or_else(|e| e);
+
+    if result.0 != StatusCode::OK {
+        chain.spend_policy().release(reservation);
+    }
+    result
+}
+
+/// Reserves the transfers against the spending policy of `token`, returning the reservation id
+///
+/// Held payouts only exist for ETH and ERC20, so transfers above the second approval threshold
+/// are rejected.
+fn reserve(
+    chain: &ChainContext,
+    token: Address,
+    identity: Option<&ApiKeyIdentity>,
+    amounts: &[U256],
+) -> Result<u64, (StatusCode, String)> {
+    let principal = identity.map(|identity| identity.name.as_str());
+    let reservation = reserve_spend(chain, Asset::Token(token), principal, amounts)?;
+    if reservation.needs_approval {
+        chain.spend_policy().release(reservation.id);
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Transfers above the second approval threshold are not supported for NFTs".to_string(),
+        ));
+    }
+
+    Ok(reservation.id)
+}
+
+/// Checks the Disperse contract may move the operator's tokens of `token`
+///
+/// With `just_in_time_approval` a missing approval is granted, returning whether the disperse holds
+/// it and has to pass it to `revoke_collection` afterwards.
+async fn approve_collection(
+    chain: &ChainContext,
+    token: Address,
+) -> Result<bool, (StatusCode, String)> {
+    let holders = chain.collection_approvals().holders(token);
+    let mut holders = holders.lock().await;
+    if *holders > 0 {
+        *holders += 1;
+        return Ok(true);
+    }
+
+    let contract = Nft::new(token, chain.client().clone());
+    let disperse = chain.disperse_contract().address();
+
+    let approved = contract
+        .is_approved_for_all(chain.wallet().address(), disperse)
+        .call()
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::BAD_GATEWAY,
+                format!("Pre-flight check failed: {:?}", e),
+            )
+        })?;
+    if approved {
+        return Ok(false);
+    }
+    if !*chain.just_in_time_approval() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "The operator has not approved the Disperse contract for {:?}",
+                token
+            ),
+        ));
+    }
+
+    let call = chain.prepare(contract.set_approval_for_all(disperse, true));
+    let (status, message) = settle_transaction(chain, "approve", "nft", call.send().await).await;
+    if status != StatusCode::OK {
+        return Err((status, format!("Approval failed: {}", message)));
+    }
+
+    *holders = 1;
+    Ok(true)
+}
+
+/// Releases the disperse's hold on the approval, revoking it once no other disperse holds it
+async fn revoke_collection(chain: &ChainContext, token: Address, revoke: bool) {
+    if !revoke {
+        return;
+    }
+
+    let holders = chain.collection_approvals().holders(token);
+    let mut holders = holders.lock().await;
+    *holders -= 1;
+    if *holders > 0 {
+        return;
+    }
+
+    let contract = Nft::new(token, chain.client().clone());
+    let call =
+        chain.prepare(contract.set_approval_for_all(chain.disperse_contract().address(), false));
+    let (status, message) = settle_transaction(chain, "approve", "nft", call.send().await).await;
+    if status != StatusCode::OK {
+        warn!(token = ?token, message, "Failed to revoke approval");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{test_config, test_state};
+
+    abigen!(TestNFT, "../contracts/out/TestNFT.sol/TestNFT.json");
+    abigen!(
+        TestMultiToken,
+        "../contracts/out/TestMultiToken.sol/TestMultiToken.json"
+    );
+
+    #[tokio::test]
+    async fn test_preflight_erc721() {
+        let (_anvil, state) = test_state().await;
+        let chain = state.chain(None).unwrap();
+        let token = TestNFT::deploy(
+            chain.client().clone(),
+            (chain.wallet().address(), U256::from(3)),
+        )
+        .unwrap()
+        .send()
+        .await
+        .unwrap()
+        .address();
+
+        assert!(preflight_erc721(chain, token, &[1.into(), 3.into()])
+            .await
+            .is_ok());
+
+        // token 4 was never minted, `ownerOf` reverts
+        let (status, message) = preflight_erc721(chain, token, &[2.into(), 4.into()])
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(
+            message.ends_with("not owned by the operator: 4"),
+            "{}",
+            message
+        );
+    }
+
+    #[tokio::test]
+    async fn test_preflight_erc1155() {
+        let (_anvil, state) = test_state().await;
+        let chain = state.chain(None).unwrap();
+        let token = TestMultiToken::deploy(chain.client().clone(), chain.wallet().address())
+            .unwrap()
+            .send()
+            .await
+            .unwrap()
+            .address();
+
+        assert!(preflight_erc1155(
+            chain,
+            token,
+            &[(1.into(), 100.into()), (2.into(), 10.into())]
+        )
+        .await
+        .is_ok());
+
+        // amounts of the same id add up beyond the minted 100
+        let (status, message) = preflight_erc1155(
+            chain,
+            token,
+            &[(1.into(), 60.into()), (1.into(), 50.into())],
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(message.contains("id 1 is 100, 110 required"), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn test_approve_collection() {
+        let anvil = ethers::utils::Anvil::new().spawn();
+        let mut config = test_config(&anvil);
+        config.chains = config.chain_configs();
+        config.chains[0].just_in_time_approval = true;
+        let state = AppState::init(config).await.unwrap();
+        let chain = state.chain(None).unwrap();
+        let token = TestNFT::deploy(
+            chain.client().clone(),
+            (chain.wallet().address(), U256::from(3)),
+        )
+        .unwrap()
+        .send()
+        .await
+        .unwrap()
+        .address();
+        let nft = Nft::new(token, chain.client().clone());
+        let approved = || async {
+            nft.is_approved_for_all(
+                chain.wallet().address(),
+                chain.disperse_contract().address(),
+            )
+            .call()
+            .await
+            .unwrap()
+        };
+
+        // concurrent disperses share the approval granted by the first one
+        let (first, second) = tokio::join!(
+            approve_collection(chain, token),
+            approve_collection(chain, token)
+        );
+        assert_eq!((first.unwrap(), second.unwrap()), (true, true));
+        assert!(approved().await);
+
+        revoke_collection(chain, token, true).await;
+        assert!(approved().await, "revoked while a disperse still holds it");
+        revoke_collection(chain, token, true).await;
+        assert!(!approved().await);
+    }
+
+    #[tokio::test]
+    async fn test_approve_collection_without_just_in_time_approval() {
+        let (_anvil, state) = test_state().await;
+        let chain = state.chain(None).unwrap();
+        let token = TestNFT::deploy(
+            chain.client().clone(),
+            (chain.wallet().address(), U256::from(1)),
+        )
+        .unwrap()
+        .send()
+        .await
+        .unwrap()
+        .address();
+
+        let (status, message) = approve_collection(chain, token).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(message.contains("has not approved"), "{}", message);
+
+        // an approval the operator granted itself is never revoked
+        let nft = Nft::new(token, chain.client().clone());
+        nft.set_approval_for_all(chain.disperse_contract().address(), true)
+            .send()
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+        assert!(!approve_collection(chain, token).await.unwrap());
+    }
+}
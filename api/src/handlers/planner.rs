@@ -0,0 +1,225 @@
+use crate::error::ApiError;
+use crate::handlers::services::Erc20;
+use crate::handlers::vaults::sync_vaults;
+use crate::planner::{plan_collect, PlanError, Source};
+use crate::state::{AppState, ChainContext, Client};
+use crate::types::{
+    CollectEntry, CollectPlan, CollectRequest, PlanCollectRequest, Simulation, SweepAmount,
+    ValuesType, VaultSweep,
+};
+use crate::vaults::VaultStatus;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::Json;
+use ethers::abi::Detokenize;
+use ethers::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Gas of withdrawing from one more vault, vaults holding less than its cost are skipped
+pub(crate) const VAULT_WITHDRAW_GAS: u64 = 15_000;
+
+// Handler for POST /collect/plan
+pub async fn plan_collect_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<PlanCollectRequest>,
+) -> Result<Json<CollectPlan>, ApiError> {
+    let chain = state
+        .chain(payload.chain_id)
+        .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "Unknown chain id"))?;
+    let receiver = payload.receiver.unwrap_or(chain.wallet().address());
+    let gas_price = chain
+        .provider()
+        .get_gas_price()
+        .await
+        .map_err(|e| rpc_error("gas price", e))?;
+
+    let (sources, min_available, vault_indexes) = match payload.token {
+        None => {
+            if !payload.senders.is_empty() {
+                return Err(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    "Senders require a token",
+                ));
+            }
+            let (sources, vault_indexes) = vault_sources(&state, chain).await?;
+            (sources, gas_price * VAULT_WITHDRAW_GAS, vault_indexes)
+        }
+        Some(token) => {
+            if payload.senders.is_empty() {
+                return Err(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    "Senders not provided",
+                ));
+            }
+            let mut distinct = payload.senders.clone();
+            distinct.sort();
+            distinct.dedup();
+            if distinct.len() != payload.senders.len() {
+                return Err(ApiError::new(StatusCode::BAD_REQUEST, "Duplicate senders"));
+            }
+            let sources = sender_sources(chain, token, &payload.senders).await?;
+            (sources, U256::zero(), HashMap::new())
+        }
+    };
+
+    let skipped = sources
+        .iter()
+        .filter(|source| !source.available.is_zero() && source.available <= min_available)
+        .map(|source| source.address)
+        .collect();
+    let pulls = plan_collect(
+        sources,
+        payload.target.0,
+        payload.strategy,
+        min_available,
+        chain.limits().max_recipients,
+    )
+    .map_err(|PlanError::Insufficient { available, target }| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("Sources hold {}, {} required", available, target),
+        )
+    })?;
+
+    let (senders, amounts): (Vec<Address>, Vec<U256>) = pulls.iter().copied().unzip();
+    let collect_contract = chain.collect_contract();
+    let simulation = match (payload.simulate, payload.token) {
+        (false, _) => None,
+        (true, None) => {
+            let indexes = senders
+                .iter()
+                .map(|vault| U256::from(vault_indexes[vault]))
+                .collect();
+            Some(simulate(collect_contract.collect_eth_to(receiver, indexes, amounts)).await)
+        }
+        (true, Some(token)) => {
+            Some(simulate(collect_contract.collect_erc20(token, receiver, senders, amounts)).await)
+        }
+    };
+
+    let mut request = CollectRequest {
+        values: Vec::new(),
+        total_amount: None,
+        values_type: ValuesType::Amount,
+        chain_id: Some(*chain.chain_id()),
+        token: payload.token,
+        receiver: Some(receiver),
+        entries: None,
+        vaults: None,
+        authorizations: None,
+        unsigned: false,
+        from: None,
+    };
+    if payload.token.is_some() {
+        let entries = pulls
+            .into_iter()
+            .map(|(sender, amount)| {
+                u128::try_from(amount)
+                    .map(|amount| CollectEntry { sender, amount })
+                    .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "Amount too large"))
+            })
+            .collect::<Result<_, _>>()?;
+        request.entries = Some(entries);
+    } else {
+        let vaults = pulls
+            .into_iter()
+            .map(|(vault, amount)| VaultSweep {
+                vault: format!("{:?}", vault),
+                amount: SweepAmount::Exact(amount),
+            })
+            .collect();
+        request.vaults = Some(vaults);
+    }
+
+    Ok(Json(CollectPlan {
+        strategy: payload.strategy,
+        target: payload.target.0,
+        gas_price,
+        skipped,
+        request,
+        simulation,
+    }))
+}
+
+/// Live ETH balances of the deployed active vaults, with their indexes
+async fn vault_sources(
+    state: &AppState,
+    chain: &ChainContext,
+) -> Result<(Vec<Source>, HashMap<Address, usize>), ApiError> {
+    sync_vaults(state, chain).await?;
+
+    let mut sources = Vec::new();
+    let mut indexes = HashMap::new();
+    for vault in state.vaults().list(*chain.chain_id()) {
+        let Some(index) = vault.index.filter(|_| vault.status == VaultStatus::Active) else {
+            continue;
+        };
+
+        let available = chain
+            .provider()
+            .get_balance(vault.address, None)
+            .await
+            .map_err(|e| rpc_error("balance", e))?;
+        sources.push(Source {
+            address: vault.address,
+            available,
+        });
+        indexes.insert(vault.address, index);
+    }
+
+    Ok((sources, indexes))
+}
+
+/// Amounts the Collect contract can pull from each sender: the lower of balance and allowance
+async fn sender_sources(
+    chain: &ChainContext,
+    token: Address,
+    senders: &[Address],
+) -> Result<Vec<Source>, ApiError> {
+    let contract = Erc20::new(token, chain.client().clone());
+    let spender = chain.collect_contract().address();
+
+    let mut sources = Vec::new();
+    for &sender in senders {
+        let balance = contract
+            .balance_of(sender)
+            .call()
+            .await
+            .map_err(|e| rpc_error("balance", e))?;
+        let allowance = contract
+            .allowance(sender, spender)
+            .call()
+            .await
+            .map_err(|e| rpc_error("allowance", e))?;
+
+        sources.push(Source {
+            address: sender,
+            available: balance.min(allowance),
+        });
+    }
+
+    Ok(sources)
+}
+
+async fn simulate<D: Detokenize>(call: ContractCall<Client, D>) -> Simulation {
+    match call.estimate_gas().await {
+        Ok(gas) => Simulation {
+            succeeded: true,
+            gas: Some(gas.as_u64()),
+            error: None,
+        },
+        Err(e) => Simulation {
+            succeeded: false,
+            gas: None,
+            error: Some(format!("{:?}", e)),
+        },
+    }
+}
+
+fn rpc_error(what: &str, e: impl std::fmt::Debug) -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_GATEWAY,
+        format!("Failed to read {}: {:?}", what, e),
+    )
+}
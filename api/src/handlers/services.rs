@@ -1,11 +1,112 @@
+use crate::auth::ApiKeyIdentity;
+use crate::eip712::Payout;
+use crate::jobs::{Job, JobStatus};
+use crate::metrics::MeteredHttp;
+use crate::permits::{permit2_nonce_position, verify_signer, Permit2Batch};
+use crate::policy::{Asset, Reservation, Spend};
+use crate::state::{AppState, ChainContext, Client};
+use crate::types::{
+    DuplicateRecipients, PayoutAuthorization, Permit2Signature, UnsignedTransaction, ValuesType,
+    ZeroAmounts,
+};
 use axum::http::StatusCode;
-use ethers::types::{H160, U256};
+use axum::response::{IntoResponse, Json, Response};
+use ethers::abi::Detokenize;
+use ethers::prelude::{
+    abigen, ContractCall, ContractError, Middleware, PendingTransaction, Signer,
+};
+use ethers::types::{BlockNumber, Bytes, Signature, TransactionReceipt, H160, U256};
 use ethers::utils::keccak256;
 use secp256k1::{
     rand::{rngs, SeedableRng},
     Secp256k1, SecretKey,
 };
 use std::convert::TryInto;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{field, info, warn, Span};
+
+abigen!(
+    Erc20,
+    r#"[
+        function balanceOf(address account) external view returns (uint256)
+        function allowance(address owner, address spender) external view returns (uint256)
+        function approve(address spender, uint256 amount) external returns (bool)
+    ]"#
+);
+
+// Shared surface of ERC-721 and ERC-1155 collections
+abigen!(
+    Nft,
+    r#"[
+        function ownerOf(uint256 tokenId) external view returns (address)
+        function balanceOf(address account, uint256 id) external view returns (uint256)
+        function isApprovedForAll(address owner, address operator) external view returns (bool)
+        function setApprovalForAll(address operator, bool approved) external
+    ]"#
+);
+
+abigen!(
+    Permit2,
+    r#"[
+        function nonceBitmap(address owner, uint256 wordPos) external view returns (uint256)
+    ]"#
+);
+
+/// Who signs a built payout transaction
+#[derive(Clone, Copy)]
+pub enum Signing {
+    /// The chain wallet signs and sends it right away
+    Operator,
+    /// Returned unsigned for the address to sign offline and `POST /broadcast`
+    Offline(H160),
+}
+
+impl Signing {
+    pub fn new(
+        chain: &ChainContext,
+        unsigned: bool,
+        from: Option<H160>,
+    ) -> Result<Self, (StatusCode, String)> {
+        match (unsigned, from) {
+            (true, from) => Ok(Signing::Offline(from.unwrap_or(chain.wallet().address()))),
+            (false, None) => Ok(Signing::Operator),
+            (false, Some(_)) => Err((
+                StatusCode::BAD_REQUEST,
+                "From requires an unsigned request".to_string(),
+            )),
+        }
+    }
+
+    /// Address the transaction is sent from
+    pub fn address(&self, chain: &ChainContext) -> H160 {
+        match self {
+            Signing::Operator => chain.wallet().address(),
+            Signing::Offline(from) => *from,
+        }
+    }
+}
+
+/// Amount an address is missing to cover a payout
+pub struct Shortfall {
+    pub address: H160,
+    pub asset: Asset,
+    /// `balance` or `allowance`
+    pub kind: &'static str,
+    pub required: U256,
+    pub available: U256,
+}
+
+impl fmt::Display for Shortfall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} {} of {} is {}, {} required",
+            self.address, self.kind, self.asset, self.available, self.required
+        )
+    }
+}
 
 pub fn calculate_amounts_from_percentages(
     percentages: &[u128],
@@ -26,6 +127,512 @@ pub fn calculate_amounts_from_percentages(
     (StatusCode::OK, Ok(amounts))
 }
 
+/// Turns request values into amounts according to `values_type`
+pub fn resolve_amounts(
+    values: &[u128],
+    values_type: &ValuesType,
+    total_amount: Option<u128>,
+) -> Result<Vec<u128>, (StatusCode, String)> {
+    match values_type {
+        ValuesType::Amount => Ok(values.to_vec()),
+        ValuesType::Percentage => {
+            let Some(total_amount) = total_amount else {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Total amount not provided".to_string(),
+                ));
+            };
+
+            let (status, result) = calculate_amounts_from_percentages(values, total_amount);
+            result.map_err(|message| (status, message))
+        }
+    }
+}
+
+/// Uses the requested recipients or generates one address per amount
+pub fn resolve_recipients(
+    recipients: Option<Vec<H160>>,
+    count: usize,
+) -> Result<Vec<H160>, (StatusCode, String)> {
+    match recipients {
+        Some(recipients) if recipients.len() != count => Err((
+            StatusCode::BAD_REQUEST,
+            "Recipients and values lengths differ".to_string(),
+        )),
+        Some(recipients) => Ok(recipients),
+        None => Ok(generate_addresses(count)
+            .unwrap()
+            .into_iter()
+            .map(get_solidity_address)
+            .collect()),
+    }
+}
+
+/// Applies the duplicate and zero amount modes, recipients keep their request order
+pub fn normalize_payments(
+    recipients: Vec<H160>,
+    amounts: Vec<U256>,
+    duplicates: DuplicateRecipients,
+    zero_amounts: ZeroAmounts,
+) -> Result<(Vec<H160>, Vec<U256>), (StatusCode, String)> {
+    let mut normalized_recipients: Vec<H160> = Vec::with_capacity(recipients.len());
+    let mut normalized_amounts: Vec<U256> = Vec::with_capacity(amounts.len());
+
+    for (recipient, amount) in recipients.into_iter().zip(amounts) {
+        match normalized_recipients.iter().position(|r| *r == recipient) {
+            None => {
+                normalized_recipients.push(recipient);
+                normalized_amounts.push(amount);
+            }
+            Some(_) if matches!(duplicates, DuplicateRecipients::Reject) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("Duplicate recipient {:?}", recipient),
+                ));
+            }
+            Some(index) => {
+                normalized_amounts[index] = normalized_amounts[index].saturating_add(amount);
+            }
+        }
+    }
+
+    let zero: Vec<String> = normalized_recipients
+        .iter()
+        .zip(&normalized_amounts)
+        .filter(|(_, amount)| amount.is_zero())
+        .map(|(recipient, _)| format!("{:?}", recipient))
+        .collect();
+    if !zero.is_empty() {
+        if let ZeroAmounts::Reject = zero_amounts {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Zero amounts for {}", zero.join(", ")),
+            ));
+        }
+
+        let (recipients, amounts) = normalized_recipients
+            .into_iter()
+            .zip(normalized_amounts)
+            .filter(|(_, amount)| !amount.is_zero())
+            .unzip();
+        normalized_recipients = recipients;
+        normalized_amounts = amounts;
+    }
+
+    if normalized_recipients.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "No non-zero amounts".to_string()));
+    }
+
+    Ok((normalized_recipients, normalized_amounts))
+}
+
+/// Rejects blocklisted addresses, the zero address and the chain's own contracts
+pub fn screen_addresses(
+    state: &AppState,
+    chain: &ChainContext,
+    addresses: &[H160],
+) -> Result<(), (StatusCode, String)> {
+    let contracts = [
+        H160::zero(),
+        chain.disperse_contract().address(),
+        chain.collect_contract().address(),
+    ];
+
+    let mut rejected: Vec<H160> = addresses
+        .iter()
+        .filter(|address| contracts.contains(address))
+        .copied()
+        .collect();
+    rejected.sort();
+    rejected.dedup();
+    rejected.extend(state.blocklist().blocked(addresses));
+
+    if rejected.is_empty() {
+        return Ok(());
+    }
+
+    let rejected: Vec<String> = rejected
+        .iter()
+        .map(|address| format!("{:?}", address))
+        .collect();
+    warn!(
+        chain_id = *chain.chain_id(),
+        addresses = ?rejected,
+        "Rejected blocked addresses"
+    );
+
+    Err((
+        StatusCode::FORBIDDEN,
+        format!("Blocked addresses: {}", rejected.join(", ")),
+    ))
+}
+
+/// Checks the EIP-712 authorization of a disperse request
+///
+/// Requests authenticated with an API key may omit it, any provided signature is verified. Its
+/// nonce is left unused until [`consume_authorization`].
+pub fn authorize_payout(
+    state: &AppState,
+    identity: Option<&ApiKeyIdentity>,
+    authorization: Option<&PayoutAuthorization>,
+    payout: &Payout,
+) -> Result<Option<H160>, (StatusCode, String)> {
+    let Some(authorization) = authorization else {
+        if identity.is_none() && state.api_keys().is_enabled() {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                "Missing API key or signature".to_string(),
+            ));
+        }
+        return Ok(None);
+    };
+
+    match state
+        .approvers()
+        .verify(payout, authorization, unix_timestamp())
+    {
+        Ok(signer) => {
+            info!(signer = ?signer, nonce = authorization.nonce, "Payout authorized by signature");
+            Ok(Some(signer))
+        }
+        Err(message) => Err((StatusCode::UNAUTHORIZED, message)),
+    }
+}
+
+/// Spends the nonce of a payout authorized by `signer`, once the payout passed its checks
+pub fn consume_authorization(
+    state: &AppState,
+    signed: Option<&(H160, PayoutAuthorization)>,
+) -> Result<(), (StatusCode, String)> {
+    let Some((signer, authorization)) = signed else {
+        return Ok(());
+    };
+
+    state
+        .approvers()
+        .consume(*signer, authorization, unix_timestamp())
+        .map_err(|message| (StatusCode::UNAUTHORIZED, message))
+}
+
+/// Verifies a Permit2 batch signature off-chain, including its deadline and unused nonce
+///
+/// Returns the signature bytes to submit with the transfer.
+pub async fn verify_permit2(
+    chain: &ChainContext,
+    spender: H160,
+    permitted: &[(H160, U256)],
+    authorization: &Permit2Signature,
+) -> Result<Bytes, (StatusCode, String)> {
+    let owner = authorization.owner;
+    let invalid = |reason: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid Permit2 signature of {:?}: {}", owner, reason),
+        )
+    };
+
+    if authorization.deadline < unix_timestamp() {
+        return Err(invalid("signature expired".to_string()));
+    }
+    let signature = Signature::from_str(&authorization.signature)
+        .map_err(|e| invalid(format!("invalid signature: {}", e)))?;
+
+    let batch = Permit2Batch {
+        chain_id: *chain.chain_id(),
+        permit2: *chain.permit2(),
+        spender,
+        permitted,
+        nonce: authorization.nonce.0,
+        deadline: authorization.deadline.into(),
+    };
+    let digest = batch.digest().map_err(invalid)?;
+    verify_signer(digest, &signature, owner).map_err(invalid)?;
+
+    let (word, bit) = permit2_nonce_position(authorization.nonce.0);
+    let bitmap = Permit2::new(*chain.permit2(), chain.client().clone())
+        .nonce_bitmap(owner, word)
+        .call()
+        .await
+        .map_err(|e| preflight_error(chain, e))?;
+    if bitmap.bit(bit) {
+        return Err(invalid("nonce already used".to_string()));
+    }
+
+    Ok(signature.to_vec().into())
+}
+
+/// Reserves the payout against the chain spending policy
+pub fn reserve_spend(
+    chain: &ChainContext,
+    asset: Asset,
+    principal: Option<&str>,
+    amounts: &[U256],
+) -> Result<Reservation, (StatusCode, String)> {
+    let spend = Spend {
+        asset,
+        principal,
+        amounts,
+    };
+
+    chain
+        .spend_policy()
+        .reserve(&spend, unix_timestamp())
+        .map_err(|violation| {
+            warn!(
+                chain_id = *chain.chain_id(),
+                asset = %asset,
+                principal,
+                violation = %violation,
+                "Spending policy violation"
+            );
+            (StatusCode::FORBIDDEN, violation.to_string())
+        })
+}
+
+/// Checks the operator can pay `value` plus the fee of `call` at the max fee per gas
+pub async fn preflight_eth<D: Detokenize>(
+    chain: &ChainContext,
+    call: &ContractCall<Client, D>,
+    value: U256,
+) -> Result<(), (StatusCode, String)> {
+    let operator = chain.wallet().address();
+    let balance = chain
+        .provider()
+        .get_balance(operator, None)
+        .await
+        .map_err(|e| preflight_error(chain, e))?;
+
+    // gas can't be estimated for a value above the balance
+    let required = if balance < value {
+        value
+    } else {
+        let gas = call.estimate_gas().await.map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                format!("Gas estimation failed: {:?}", e),
+            )
+        })?;
+        value.saturating_add(gas.saturating_mul(max_fee_per_gas(chain).await?))
+    };
+
+    if balance < required {
+        return Err(shortfall_report(
+            chain,
+            vec![Shortfall {
+                address: operator,
+                asset: Asset::Eth,
+                kind: "balance",
+                required,
+                available: balance,
+            }],
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks every owner's token balance and allowance to `spender`, amounts of the same owner add up
+///
+/// The allowance is not checked without a `spender`, e.g. when it is granted by a permit.
+pub async fn preflight_erc20(
+    chain: &ChainContext,
+    token: H160,
+    spender: Option<H160>,
+    transfers: &[(H160, U256)],
+) -> Result<(), (StatusCode, String)> {
+    let mut required: Vec<(H160, U256)> = Vec::new();
+    for &(owner, amount) in transfers {
+        match required.iter_mut().find(|(address, _)| *address == owner) {
+            Some((_, total)) => *total = total.saturating_add(amount),
+            None => required.push((owner, amount)),
+        }
+    }
+
+    let contract = Erc20::new(token, chain.client().clone());
+    let mut shortfalls = Vec::new();
+    for (owner, amount) in required {
+        let balance = contract
+            .balance_of(owner)
+            .call()
+            .await
+            .map_err(|e| preflight_error(chain, e))?;
+        let mut available = vec![("balance", balance)];
+        if let Some(spender) = spender {
+            let allowance = contract
+                .allowance(owner, spender)
+                .call()
+                .await
+                .map_err(|e| preflight_error(chain, e))?;
+            available.push(("allowance", allowance));
+        }
+
+        for (kind, available) in available {
+            if available < amount {
+                shortfalls.push(Shortfall {
+                    address: owner,
+                    asset: Asset::Token(token),
+                    kind,
+                    required: amount,
+                    available,
+                });
+            }
+        }
+    }
+
+    if !shortfalls.is_empty() {
+        return Err(shortfall_report(chain, shortfalls));
+    }
+
+    Ok(())
+}
+
+async fn max_fee_per_gas(chain: &ChainContext) -> Result<U256, (StatusCode, String)> {
+    if let Some(max_fee_per_gas) = chain.fee_policy().max_fee_per_gas {
+        return Ok(max_fee_per_gas.into());
+    }
+
+    let provider = chain.provider();
+    let fee = if chain.fee_policy().legacy {
+        provider.get_gas_price().await
+    } else {
+        provider
+            .estimate_eip1559_fees(None)
+            .await
+            .map(|(max_fee_per_gas, _)| max_fee_per_gas)
+    };
+
+    fee.map_err(|e| preflight_error(chain, e))
+}
+
+/// Checks the operator owns every ERC-721 token id
+pub async fn preflight_erc721(
+    chain: &ChainContext,
+    token: H160,
+    token_ids: &[U256],
+) -> Result<(), (StatusCode, String)> {
+    let contract = Nft::new(token, chain.client().clone());
+    let operator = chain.wallet().address();
+
+    let mut missing = Vec::new();
+    for &token_id in token_ids {
+        // `ownerOf` reverts for tokens that don't exist
+        match contract.owner_of(token_id).call().await {
+            Ok(owner) if owner == operator => {}
+            Ok(_) | Err(ContractError::Revert(_)) => missing.push(token_id.to_string()),
+            Err(e) => return Err(preflight_error(chain, e)),
+        }
+    }
+
+    if !missing.is_empty() {
+        warn!(chain_id = *chain.chain_id(), token = ?token, missing = ?missing, "Tokens not owned");
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Token ids of {:?} not owned by the operator: {}",
+                token,
+                missing.join(", ")
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks the operator's ERC-1155 balance of every id, amounts of the same id add up
+pub async fn preflight_erc1155(
+    chain: &ChainContext,
+    token: H160,
+    transfers: &[(U256, U256)],
+) -> Result<(), (StatusCode, String)> {
+    let mut required: Vec<(U256, U256)> = Vec::new();
+    for &(id, amount) in transfers {
+        match required.iter_mut().find(|(token_id, _)| *token_id == id) {
+            Some((_, total)) => *total = total.saturating_add(amount),
+            None => required.push((id, amount)),
+        }
+    }
+
+    let contract = Nft::new(token, chain.client().clone());
+    let operator = chain.wallet().address();
+    let mut shortfalls = Vec::new();
+    for (id, amount) in required {
+        let balance = contract
+            .balance_of(operator, id)
+            .call()
+            .await
+            .map_err(|e| preflight_error(chain, e))?;
+        if balance < amount {
+            shortfalls.push(format!("id {} is {}, {} required", id, balance, amount));
+        }
+    }
+
+    if !shortfalls.is_empty() {
+        warn!(chain_id = *chain.chain_id(), token = ?token, shortfalls = ?shortfalls, "Insufficient funds");
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Insufficient funds: {:?} balance of {:?} {}",
+                operator,
+                token,
+                shortfalls.join("; ")
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+fn preflight_error(chain: &ChainContext, e: impl fmt::Debug) -> (StatusCode, String) {
+    warn!(chain_id = *chain.chain_id(), error = ?e, "Pre-flight check failed");
+    (
+        StatusCode::BAD_GATEWAY,
+        format!("Pre-flight check failed: {:?}", e),
+    )
+}
+
+pub fn shortfall_report(chain: &ChainContext, shortfalls: Vec<Shortfall>) -> (StatusCode, String) {
+    let report: Vec<String> = shortfalls.iter().map(Shortfall::to_string).collect();
+    warn!(chain_id = *chain.chain_id(), shortfalls = ?report, "Insufficient funds");
+
+    (
+        StatusCode::BAD_REQUEST,
+        format!("Insufficient funds: {}", report.join("; ")),
+    )
+}
+
+/// Sets the operator's allowance of `token` to the Disperse contract
+pub async fn approve_disperse(
+    chain: &ChainContext,
+    token: H160,
+    amount: U256,
+) -> (StatusCode, String) {
+    let contract = Erc20::new(token, chain.client().clone());
+    let call = chain.prepare(contract.approve(chain.disperse_contract().address(), amount));
+
+    settle_transaction(chain, "approve", "erc20", call.send().await).await
+}
+
+/// Returns the operator's allowance of `token` to the Disperse contract
+pub async fn disperse_allowance(
+    chain: &ChainContext,
+    token: H160,
+) -> Result<U256, ContractError<Client>> {
+    Erc20::new(token, chain.client().clone())
+        .allowance(
+            chain.wallet().address(),
+            chain.disperse_contract().address(),
+        )
+        .call()
+        .await
+}
+
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 pub fn sum_u256_vector(amounts: Vec<U256>) -> U256 {
     let mut sum = U256::zero();
 
@@ -36,6 +643,147 @@ pub fn sum_u256_vector(amounts: Vec<U256>) -> U256 {
     sum
 }
 
+/// Waits for a sent payout transaction to be mined and records its outcome
+pub async fn settle_transaction(
+    chain: &ChainContext,
+    operation: &str,
+    asset: &str,
+    sent: Result<PendingTransaction<'_, MeteredHttp>, ContractError<Client>>,
+) -> (StatusCode, String) {
+    match confirm_transaction(chain, operation, asset, sent).await {
+        Ok(receipt) => (
+            StatusCode::OK,
+            format!("Transaction successful: {:?}", receipt.transaction_hash),
+        ),
+        Err(e) => e,
+    }
+}
+
+/// Fills in `call` for `from` to sign offline and stores it with `job` until it is broadcast
+pub async fn prepare_unsigned<D: Detokenize>(
+    state: &AppState,
+    chain: &ChainContext,
+    call: ContractCall<Client, D>,
+    from: H160,
+    mut job: Job,
+) -> Result<UnsignedTransaction, (StatusCode, String)> {
+    let provider = chain.provider();
+    let mut tx = chain.prepare(call).from(from).tx;
+    let nonce = provider
+        .get_transaction_count(from, Some(BlockNumber::Pending.into()))
+        .await
+        .map_err(|e| preflight_error(chain, e))?;
+    tx.set_nonce(nonce).set_chain_id(*chain.chain_id());
+
+    // estimating gas for `from` also catches payouts it can't cover
+    provider
+        .fill_transaction(&mut tx, None)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                format!("Failed to build transaction: {:?}", e),
+            )
+        })?;
+
+    job.status = JobStatus::AwaitingSignature;
+    job.transaction = Some(tx.clone());
+    let job_id = job.id.clone();
+    Span::current().record("job_id", job_id.as_str());
+    info!(job_id, from = ?from, "Payout is awaiting an offline signature");
+    state.jobs().insert(job);
+
+    Ok(UnsignedTransaction {
+        job_id,
+        rlp: tx.rlp(),
+        transaction: tx,
+    })
+}
+
+/// Sends `call` with the operator wallet, or stores it as `job` for offline signing
+pub async fn dispatch<D: Detokenize>(
+    state: &AppState,
+    chain: &ChainContext,
+    call: ContractCall<Client, D>,
+    signing: Signing,
+    (operation, asset): (&str, &str),
+    job: Job,
+) -> Response {
+    match signing {
+        Signing::Operator => {
+            let call = chain.prepare(call);
+            settle_transaction(chain, operation, asset, call.send().await)
+                .await
+                .into_response()
+        }
+        Signing::Offline(from) => match prepare_unsigned(state, chain, call, from, job).await {
+            Ok(unsigned) => (StatusCode::ACCEPTED, Json(unsigned)).into_response(),
+            Err(e) => e.into_response(),
+        },
+    }
+}
+
+/// Like [`settle_transaction`], returning the receipt of a successful transaction
+pub async fn confirm_transaction(
+    chain: &ChainContext,
+    operation: &str,
+    asset: &str,
+    sent: Result<PendingTransaction<'_, MeteredHttp>, ContractError<Client>>,
+) -> Result<TransactionReceipt, (StatusCode, String)> {
+    let metrics = chain.metrics();
+    let chain_id = *chain.chain_id();
+
+    let pending_tx = match sent {
+        Ok(pending_tx) => pending_tx,
+        Err(e) => {
+            metrics.record_payout(chain_id, operation, asset, "failed");
+            warn!(chain_id, operation, asset, error = ?e, "Transaction failed");
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Transaction failed: {:?}", e),
+            ));
+        }
+    };
+    let tx_hash = pending_tx.tx_hash();
+    Span::current().record("tx_hash", field::debug(tx_hash));
+    info!(chain_id, operation, asset, "Transaction sent");
+
+    match pending_tx.await {
+        Ok(Some(receipt)) => {
+            metrics.record_receipt(chain_id, operation, asset, &receipt);
+
+            if receipt.status == Some(1u64.into()) {
+                metrics.record_payout(chain_id, operation, asset, "success");
+                info!(chain_id, operation, asset, "Transaction successful");
+                Ok(receipt)
+            } else {
+                metrics.record_payout(chain_id, operation, asset, "reverted");
+                warn!(chain_id, operation, asset, "Transaction reverted");
+                Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("Transaction reverted: {:?}", tx_hash),
+                ))
+            }
+        }
+        Ok(None) => {
+            metrics.record_payout(chain_id, operation, asset, "dropped");
+            warn!(chain_id, operation, asset, "Transaction dropped");
+            Err((
+                StatusCode::BAD_REQUEST,
+                format!("Transaction dropped: {:?}", tx_hash),
+            ))
+        }
+        Err(e) => {
+            metrics.record_payout(chain_id, operation, asset, "failed");
+            warn!(chain_id, operation, asset, error = ?e, "Failed to fetch receipt");
+            Err((
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to fetch receipt for {:?}: {:?}", tx_hash, e),
+            ))
+        }
+    }
+}
+
 pub fn get_solidity_address(s: String) -> H160 {
     let address_str = s.trim_start_matches("0x");
     let address_bytes = hex::decode(address_str).expect("Invalid hex string");
@@ -48,13 +796,11 @@ pub fn get_solidity_address(s: String) -> H160 {
     H160::from(address_bytes)
 }
 
-fn generate_ethereum_keypair() -> (SecretKey, String) {
+fn generate_ethereum_keypair(rng: &mut rngs::StdRng) -> (SecretKey, String) {
     let secp = Secp256k1::new();
-    let random_seed = 12345;
-    let mut rng = rngs::StdRng::seed_from_u64(random_seed);
 
     // Generate the keypair
-    let (private_key, public_key) = secp.generate_keypair(&mut rng);
+    let (private_key, public_key) = secp.generate_keypair(rng);
 
     // Get the public key as bytes (uncompressed form)
     let public_key = public_key.serialize_uncompressed();
@@ -66,13 +812,145 @@ fn generate_ethereum_keypair() -> (SecretKey, String) {
     (private_key, ethereum_address)
 }
 
-pub fn generate_addresses(amount: u8) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+pub fn generate_addresses(amount: usize) -> Result<Vec<String>, Box<dyn std::error::Error>> {
     let mut accounts: Vec<String> = Vec::new();
+    // one seeded generator per batch so the generated addresses are distinct
+    let random_seed = 12345;
+    let mut rng = rngs::StdRng::seed_from_u64(random_seed);
 
     for _ in 0..amount {
-        let (_, ethereum_address) = generate_ethereum_keypair();
+        let (_, ethereum_address) = generate_ethereum_keypair(&mut rng);
         accounts.push(ethereum_address);
     }
 
     Ok(accounts)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::disperse::{TestToken, TransferData};
+    use crate::state::test_state;
+
+    #[test]
+    fn test_resolve_recipients_above_255() {
+        let recipients = resolve_recipients(None, 256).unwrap();
+        assert_eq!(recipients.len(), 256);
+    }
+
+    #[test]
+    fn test_normalize_payments() {
+        let (a, b) = (H160::repeat_byte(1), H160::repeat_byte(2));
+        let recipients = vec![a, b, a, b];
+        let amounts: Vec<U256> = [100, 0, 50, 0].into_iter().map(U256::from).collect();
+
+        let (status, _) = normalize_payments(
+            recipients.clone(),
+            amounts.clone(),
+            DuplicateRecipients::Reject,
+            ZeroAmounts::Drop,
+        )
+        .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (_, message) = normalize_payments(
+            recipients.clone(),
+            amounts.clone(),
+            DuplicateRecipients::Merge,
+            ZeroAmounts::Reject,
+        )
+        .unwrap_err();
+        assert!(message.contains(&format!("{:?}", b)));
+
+        let normalized = normalize_payments(
+            recipients,
+            amounts,
+            DuplicateRecipients::Merge,
+            ZeroAmounts::Drop,
+        )
+        .unwrap();
+        assert_eq!(normalized, (vec![a], vec![U256::from(150)]));
+
+        assert!(normalize_payments(
+            vec![b],
+            vec![U256::zero()],
+            DuplicateRecipients::Merge,
+            ZeroAmounts::Drop
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_preflight_eth() {
+        let (_anvil, state) = test_state().await;
+        let chain = state.chain(None).unwrap();
+        let balance = chain
+            .provider()
+            .get_balance(chain.wallet().address(), None)
+            .await
+            .unwrap();
+        let call = |value: U256| {
+            let transfers = vec![TransferData {
+                wallet: H160::repeat_byte(1),
+                amount: value,
+            }];
+            chain.prepare(
+                chain
+                    .disperse_contract()
+                    .disperse_eth(transfers)
+                    .value(value),
+            )
+        };
+
+        let value = U256::from(1_000);
+        assert!(preflight_eth(chain, &call(value), value).await.is_ok());
+
+        // the whole balance leaves nothing for gas
+        let (status, message) = preflight_eth(chain, &call(balance), balance)
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(message.contains("balance of ETH"));
+    }
+
+    #[tokio::test]
+    async fn test_preflight_erc20() {
+        let (_anvil, state) = test_state().await;
+        let chain = state.chain(None).unwrap();
+        let operator = chain.wallet().address();
+        let disperse = chain.disperse_contract().address();
+        let token = TestToken::deploy(chain.client().clone(), operator)
+            .unwrap()
+            .send()
+            .await
+            .unwrap()
+            .address();
+        let amount = U256::exp10(18);
+
+        let (status, message) =
+            preflight_erc20(chain, token, Some(disperse), &[(operator, amount)])
+                .await
+                .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(message.contains("allowance"));
+        assert!(preflight_erc20(chain, token, None, &[(operator, amount)])
+            .await
+            .is_ok());
+
+        let (status, _) = approve_disperse(chain, token, amount).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            preflight_erc20(chain, token, Some(disperse), &[(operator, amount)])
+                .await
+                .is_ok()
+        );
+
+        // amounts of the same owner add up beyond the minted 100 tokens
+        let part = U256::exp10(18) * 60;
+        let (_, message) =
+            preflight_erc20(chain, token, None, &[(operator, part), (operator, part)])
+                .await
+                .unwrap_err();
+        assert!(message.contains("balance"));
+    }
+}
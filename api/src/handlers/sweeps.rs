@@ -0,0 +1,236 @@
+use crate::handlers::planner::VAULT_WITHDRAW_GAS;
+use crate::handlers::services::{screen_addresses, settle_transaction, unix_timestamp, Erc20};
+use crate::handlers::vaults::{deploy_funded_vaults, sync_vaults};
+use crate::jobs::{Job, JobKind, JobStatus};
+use crate::policy::Asset;
+use crate::state::{AppState, ChainContext};
+use crate::vaults::{Vault, VaultStatus};
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use ethers::prelude::*;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, info_span, warn, Instrument};
+
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+const AUTO_SWEEP_PRINCIPAL: &str = "auto-sweep";
+
+/// Starts the auto-sweep scheduler of every chain with `auto_sweep.enabled`
+pub fn spawn_auto_sweepers(state: Arc<AppState>) {
+    for (&chain_id, chain) in state.chains() {
+        if chain.auto_sweep().enabled {
+            info!(chain_id, "Auto-sweeping vaults");
+            tokio::spawn(auto_sweep(state.clone(), chain_id));
+        }
+    }
+}
+
+async fn auto_sweep(state: Arc<AppState>, chain_id: u64) {
+    let chain = state
+        .chain(Some(chain_id))
+        .expect("swept chain is configured");
+    let interval_secs = chain
+        .auto_sweep()
+        .interval_secs
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = sweep_vaults(&state, chain).await {
+            warn!(chain_id, error = ?e, "Auto-sweep failed");
+        }
+    }
+}
+
+/// Sweeps each configured asset out of the vaults over its threshold, or all non-empty vaults
+/// while gas is cheap
+async fn sweep_vaults(state: &AppState, chain: &ChainContext) -> anyhow::Result<()> {
+    let chain_id = *chain.chain_id();
+    let config = chain.auto_sweep();
+
+    if let Err((_, message)) = deploy_funded_vaults(state, chain).await {
+        warn!(chain_id, error = %message, "Failed to deploy funded vaults");
+    }
+    sync_vaults(state, chain)
+        .await
+        .map_err(|e| anyhow!(e.message))?;
+    let vaults: Vec<Vault> = state
+        .vaults()
+        .list(chain_id)
+        .into_iter()
+        .filter(|vault| vault.status == VaultStatus::Active && vault.index.is_some())
+        .collect();
+    if vaults.is_empty() {
+        return Ok(());
+    }
+
+    let gas_price = chain.provider().get_gas_price().await?;
+    let cheap_gas = config
+        .max_gas_price_gwei
+        .is_some_and(|max| gas_price <= U256::from(max) * U256::exp10(9));
+
+    for &(asset, threshold) in chain.sweep_thresholds() {
+        let mut balances = Vec::new();
+        for vault in &vaults {
+            balances.push((vault.clone(), balance(chain, asset, vault.address).await?));
+        }
+
+        // token balances can't be weighed against gas, so only ETH is swept early
+        let floor = match asset {
+            Asset::Eth if cheap_gas => Some(gas_price * VAULT_WITHDRAW_GAS),
+            _ => None,
+        };
+        let selected = select_sweeps(balances, threshold, floor, chain.limits().max_recipients);
+        if !selected.is_empty() {
+            run_sweep(state, chain, asset, selected).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Vaults to sweep, largest balances first: those at or over `threshold`, or over `cheap_gas_floor`
+/// (the gas cost of withdrawing them) while gas is cheap
+fn select_sweeps(
+    mut balances: Vec<(Vault, U256)>,
+    threshold: U256,
+    cheap_gas_floor: Option<U256>,
+    max_vaults: usize,
+) -> Vec<(Vault, U256)> {
+    balances.retain(|(_, balance)| {
+        !balance.is_zero()
+            && (*balance >= threshold || cheap_gas_floor.is_some_and(|floor| *balance > floor))
+    });
+    balances.sort_by_key(|(_, balance)| std::cmp::Reverse(*balance));
+    balances.truncate(max_vaults);
+    balances
+}
+
+/// Stores the sweep as a job and sends it
+async fn run_sweep(
+    state: &AppState,
+    chain: &ChainContext,
+    asset: Asset,
+    sweeps: Vec<(Vault, U256)>,
+) {
+    let (kind, token) = match asset {
+        Asset::Eth => (JobKind::CollectEth, None),
+        Asset::Token(token) => (JobKind::CollectErc20, Some(token)),
+    };
+    let receiver = chain
+        .auto_sweep()
+        .receiver
+        .unwrap_or(chain.wallet().address());
+
+    let mut job = Job::new(
+        *chain.chain_id(),
+        kind,
+        token,
+        sweeps.iter().map(|(vault, _)| vault.address).collect(),
+        sweeps.iter().map(|(_, amount)| *amount).collect(),
+        Some(AUTO_SWEEP_PRINCIPAL.to_string()),
+        0,
+        unix_timestamp(),
+    );
+    job.status = JobStatus::Submitted;
+    job.receiver = Some(receiver);
+    let id = job.id.clone();
+    state.jobs().insert(job);
+
+    let span = info_span!("job", job_id = %id);
+    let (status, message) = async {
+        info!(vaults = sweeps.len(), "Auto-sweep started");
+        if let Err(e) = screen_addresses(state, chain, &[receiver]) {
+            return e;
+        }
+
+        let indexes: Vec<U256> = sweeps
+            .iter()
+            .filter_map(|(vault, _)| vault.index.map(U256::from))
+            .collect();
+        let amounts: Vec<U256> = sweeps.iter().map(|(_, amount)| *amount).collect();
+        let collect_contract = chain.collect_contract();
+        match token {
+            None => {
+                let call =
+                    chain.prepare(collect_contract.collect_eth_to(receiver, indexes, amounts));
+                settle_transaction(chain, "collect", "eth", call.send().await).await
+            }
+            Some(token) => {
+                let call = chain.prepare(
+                    collect_contract.collect_erc20_from_vaults(token, receiver, indexes, amounts),
+                );
+                settle_transaction(chain, "collect", "erc20", call.send().await).await
+            }
+        }
+    }
+    .instrument(span.clone())
+    .await;
+
+    let succeeded = status == StatusCode::OK;
+    span.in_scope(|| info!(succeeded, result = %message, "Job finished"));
+    state
+        .jobs()
+        .finish(&id, succeeded, message, unix_timestamp());
+}
+
+async fn balance(chain: &ChainContext, asset: Asset, vault: Address) -> anyhow::Result<U256> {
+    Ok(match asset {
+        Asset::Eth => chain.provider().get_balance(vault, None).await?,
+        Asset::Token(token) => {
+            Erc20::new(token, chain.client().clone())
+                .balance_of(vault)
+                .call()
+                .await?
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault(byte: u8) -> Vault {
+        Vault {
+            chain_id: 31337,
+            address: Address::repeat_byte(byte),
+            index: Some(byte.into()),
+            salt: None,
+            label: None,
+            customer: None,
+            status: VaultStatus::Active,
+            created_at: 0,
+            retired_at: None,
+        }
+    }
+
+    #[test]
+    fn test_select_sweeps() {
+        let balances = vec![
+            (vault(1), U256::from(50)),
+            (vault(2), U256::from(300)),
+            (vault(3), U256::zero()),
+            (vault(4), U256::from(100)),
+        ];
+
+        let selected = select_sweeps(balances.clone(), 100.into(), None, 10);
+        let addresses: Vec<Address> = selected.iter().map(|(vault, _)| vault.address).collect();
+        assert_eq!(
+            addresses,
+            vec![Address::repeat_byte(2), Address::repeat_byte(4)]
+        );
+
+        let selected = select_sweeps(balances.clone(), 100.into(), Some(0.into()), 2);
+        assert_eq!(selected.len(), 2);
+        assert_eq!(selected[1].1, U256::from(100));
+
+        // dust worth less than the gas to withdraw it stays put
+        let selected = select_sweeps(balances, 1000.into(), Some(50.into()), 10);
+        let addresses: Vec<Address> = selected.iter().map(|(vault, _)| vault.address).collect();
+        assert_eq!(
+            addresses,
+            vec![Address::repeat_byte(2), Address::repeat_byte(4)]
+        );
+    }
+}
@@ -0,0 +1,381 @@
+use crate::create2::{customer_salt, init_code_hash, vault_address};
+use crate::error::ApiError;
+use crate::handlers::collect::{WithdrawalContractCreatedFilter, WITHDRAWALCONTRACT_BYTECODE};
+use crate::handlers::services::{confirm_transaction, unix_timestamp, Erc20};
+use crate::policy::Asset;
+use crate::state::{AppState, ChainContext};
+use crate::types::{
+    ChainQuery, CreateVaultRequest, DepositAddressRequest, UpdateVaultRequest, VaultResponse,
+};
+use crate::vaults::{Vault, VaultStatus};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::Json;
+use ethers::contract::parse_log;
+use ethers::prelude::*;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+// Handler for GET /vaults
+pub async fn list_vaults_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ChainQuery>,
+) -> Result<Json<Vec<VaultResponse>>, ApiError> {
+    let chain = chain(&state, query.chain_id)?;
+    sync_vaults(&state, chain).await?;
+
+    let mut vaults = Vec::new();
+    for vault in state.vaults().list(*chain.chain_id()) {
+        vaults.push(with_balance(chain, vault).await?);
+    }
+
+    Ok(Json(vaults))
+}
+
+// Handler for POST /vaults
+pub async fn create_vault_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateVaultRequest>,
+) -> Result<Json<VaultResponse>, ApiError> {
+    let chain = chain(&state, payload.chain_id)?;
+    let chain_id = *chain.chain_id();
+    sync_vaults(&state, chain).await?;
+
+    if let Some(label) = &payload.label {
+        if state.vaults().find_by_label(chain_id, label).is_some() {
+            return Err(ApiError::conflict(format!(
+                "Label {} is already used",
+                label
+            )));
+        }
+    }
+
+    let collect_contract = chain.collect_contract();
+    let call = chain.prepare(collect_contract.create_withdrawal_contract());
+    let receipt = confirm_transaction(chain, "create_vault", "eth", call.send().await)
+        .await
+        .map_err(|(status, message)| ApiError::new(status, message))?;
+
+    let created = receipt
+        .logs
+        .into_iter()
+        .filter(|log| log.address == collect_contract.address())
+        .find_map(|log| parse_log::<WithdrawalContractCreatedFilter>(log).ok())
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::BAD_GATEWAY,
+                "Vault creation event not found in receipt",
+            )
+        })?;
+
+    let vault = state.vaults().register(Vault {
+        chain_id,
+        address: created.withdrawal_contract,
+        index: Some(created.index.as_usize()),
+        salt: None,
+        label: payload.label,
+        customer: payload.customer,
+        status: VaultStatus::Active,
+        created_at: unix_timestamp(),
+        retired_at: None,
+    })?;
+    info!(chain_id, vault = ?vault.address, "Vault created");
+
+    with_balance(chain, vault).await.map(Json)
+}
+
+// Handler for POST /vaults/deposit-address
+pub async fn deposit_address_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<DepositAddressRequest>,
+) -> Result<Json<VaultResponse>, ApiError> {
+    let chain = chain(&state, payload.chain_id)?;
+    let chain_id = *chain.chain_id();
+    if payload.customer.is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Customer is empty"));
+    }
+    sync_vaults(&state, chain).await?;
+
+    let salt = customer_salt(&payload.customer);
+    let address = vault_address(
+        chain.collect_contract().address(),
+        salt,
+        init_code_hash(&WITHDRAWALCONTRACT_BYTECODE),
+    );
+
+    // a stale artifact would hand out an address the contract never deploys to
+    let predicted = chain
+        .collect_contract()
+        .predict_withdrawal_contract_address(salt.0)
+        .call()
+        .await
+        .map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to predict the vault address: {:?}", e),
+            )
+        })?;
+    if predicted != address {
+        warn!(chain_id, computed = ?address, ?predicted, "WithdrawalContract bytecode differs from the deployed Collect contract");
+        return Err(ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Vault address doesn't match the Collect contract, the WithdrawalContract artifact is out of date",
+        ));
+    }
+
+    let vault = match state.vaults().get(chain_id, address) {
+        Some(vault) => vault,
+        None => {
+            let vault = state.vaults().register(Vault {
+                chain_id,
+                address,
+                index: None,
+                salt: Some(salt),
+                label: payload.label,
+                customer: Some(payload.customer),
+                status: VaultStatus::Pending,
+                created_at: unix_timestamp(),
+                retired_at: None,
+            })?;
+            info!(chain_id, vault = ?vault.address, "Deposit address issued");
+            vault
+        }
+    };
+
+    with_balance(chain, vault).await.map(Json)
+}
+
+// Handler for PATCH /vaults/:address
+pub async fn update_vault_handler(
+    State(state): State<Arc<AppState>>,
+    Path(address): Path<Address>,
+    Json(payload): Json<UpdateVaultRequest>,
+) -> Result<Json<VaultResponse>, ApiError> {
+    let chain = chain(&state, payload.chain_id)?;
+    sync_vaults(&state, chain).await?;
+
+    let vault =
+        state
+            .vaults()
+            .update(*chain.chain_id(), address, payload.label, payload.customer)?;
+
+    with_balance(chain, vault).await.map(Json)
+}
+
+// Handler for POST /vaults/:address/retire
+pub async fn retire_vault_handler(
+    State(state): State<Arc<AppState>>,
+    Path(address): Path<Address>,
+    Query(query): Query<ChainQuery>,
+) -> Result<Json<VaultResponse>, ApiError> {
+    let chain = chain(&state, query.chain_id)?;
+    let chain_id = *chain.chain_id();
+    sync_vaults(&state, chain).await?;
+
+    let vault = state
+        .vaults()
+        .get(chain_id, address)
+        .ok_or_else(|| ApiError::not_found("Vault not found"))?;
+    let balance = balance(chain, vault.address).await?;
+    if !balance.is_zero() {
+        return Err(ApiError::conflict(format!(
+            "Vault still holds {} wei, collect it before retiring",
+            balance
+        )));
+    }
+
+    let vault = state.vaults().retire(chain_id, address, unix_timestamp())?;
+    info!(chain_id, vault = ?vault.address, "Vault retired");
+
+    Ok(Json(VaultResponse { vault, balance }))
+}
+
+fn chain(state: &AppState, chain_id: Option<u64>) -> Result<&ChainContext, ApiError> {
+    state
+        .chain(chain_id)
+        .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "Unknown chain id"))
+}
+
+/// Registers the withdrawal contracts created outside the API, e.g. at deployment
+pub async fn sync_vaults(state: &AppState, chain: &ChainContext) -> Result<(), ApiError> {
+    let addresses = chain
+        .collect_contract()
+        .get_withdrawal_contracts()
+        .call()
+        .await
+        .map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to read withdrawal contracts: {:?}", e),
+            )
+        })?;
+
+    state
+        .vaults()
+        .sync(*chain.chain_id(), &addresses, unix_timestamp());
+    Ok(())
+}
+
+/// Deploys the pending vaults that received ETH or a swept token so they can be swept
+///
+/// Tokens are the ones with an auto-sweep threshold, the only ones swept out of vaults. Vaults
+/// deployed outside the API are synced first so their CREATE2 deployment isn't attempted twice,
+/// and each deployment is synced so the vault is active with its index.
+pub async fn deploy_funded_vaults(
+    state: &AppState,
+    chain: &ChainContext,
+) -> Result<(), (StatusCode, String)> {
+    let collect_contract = chain.collect_contract();
+    sync_vaults(state, chain)
+        .await
+        .map_err(|e| (e.status, e.message))?;
+
+    let tokens: Vec<Address> = chain
+        .sweep_thresholds()
+        .iter()
+        .filter_map(|(asset, _)| match asset {
+            Asset::Token(token) => Some(*token),
+            Asset::Eth => None,
+        })
+        .collect();
+
+    for vault in state.vaults().pending(*chain.chain_id()) {
+        let Some(salt) = vault.salt else {
+            continue;
+        };
+
+        match is_funded(chain, vault.address, &tokens).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                warn!(vault = ?vault.address, error = %e, "Failed to read vault balance");
+                continue;
+            }
+        }
+
+        let call = chain.prepare(collect_contract.create_deterministic_withdrawal_contract(salt.0));
+        confirm_transaction(chain, "deploy_vault", "eth", call.send().await).await?;
+        info!(chain_id = *chain.chain_id(), vault = ?vault.address, "Vault deployed");
+        sync_vaults(state, chain)
+            .await
+            .map_err(|e| (e.status, e.message))?;
+    }
+
+    Ok(())
+}
+
+async fn is_funded(
+    chain: &ChainContext,
+    address: Address,
+    tokens: &[Address],
+) -> Result<bool, String> {
+    let balance = chain
+        .provider()
+        .get_balance(address, None)
+        .await
+        .map_err(|e| format!("{:?}", e))?;
+    if !balance.is_zero() {
+        return Ok(true);
+    }
+
+    for &token in tokens {
+        let erc20 = Erc20::new(token, chain.client().clone());
+        let balance = erc20
+            .balance_of(address)
+            .call()
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+        if !balance.is_zero() {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+async fn balance(chain: &ChainContext, address: Address) -> Result<U256, ApiError> {
+    chain
+        .provider()
+        .get_balance(address, None)
+        .await
+        .map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to read balance: {:?}", e),
+            )
+        })
+}
+
+async fn with_balance(chain: &ChainContext, vault: Vault) -> Result<VaultResponse, ApiError> {
+    let balance = balance(chain, vault.address).await?;
+
+    Ok(VaultResponse { vault, balance })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Amount;
+    use crate::handlers::collect::TestToken;
+    use crate::state::test_config;
+
+    #[tokio::test]
+    async fn test_token_funded_vault_is_deployed() {
+        let anvil = ethers::utils::Anvil::new().spawn();
+        let holder = Arc::new(SignerMiddleware::new(
+            Provider::<Http>::try_from(anvil.endpoint()).unwrap(),
+            LocalWallet::from(anvil.keys()[1].clone()).with_chain_id(anvil.chain_id()),
+        ));
+        let token = TestToken::deploy(holder.clone(), holder.address())
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        let mut config = test_config(&anvil);
+        config.chains = config.chain_configs();
+        config.chains[0]
+            .auto_sweep
+            .thresholds
+            .insert(format!("{:?}", token.address()), Amount(U256::one()));
+        let state = AppState::init(config).await.unwrap();
+        let chain = state.chain(None).unwrap();
+
+        let Json(issued) = deposit_address_handler(
+            State(state.clone()),
+            Json(DepositAddressRequest {
+                chain_id: None,
+                customer: "alice".to_string(),
+                label: None,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(issued.vault.status, VaultStatus::Pending);
+
+        token
+            .transfer(issued.vault.address, 7.into())
+            .send()
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+        deploy_funded_vaults(&state, chain).await.unwrap();
+
+        let vault = state
+            .vaults()
+            .get(*chain.chain_id(), issued.vault.address)
+            .unwrap();
+        assert_eq!(vault.status, VaultStatus::Active);
+
+        // already deployed, so nothing is created again
+        deploy_funded_vaults(&state, chain).await.unwrap();
+        let deployed = state
+            .vaults()
+            .list(*chain.chain_id())
+            .into_iter()
+            .filter(|vault| vault.address == issued.vault.address)
+            .count();
+        assert_eq!(deployed, 1);
+    }
+}
@@ -0,0 +1,494 @@
+use crate::error::ApiError;
+use crate::metrics::Metrics;
+use crate::persist::JsonFile;
+use crate::types::Permit2Signature;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    AwaitingApproval,
+    /// Built for offline signing, waiting for `POST /broadcast`
+    AwaitingSignature,
+    /// Withdrawn with `DELETE /jobs/{id}` before it was signed
+    Cancelled,
+    Rejected,
+    Submitted,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    DisperseEth,
+    DisperseErc20,
+    CollectEth,
+    CollectErc20,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Approval {
+    pub approver: String,
+    pub approved: bool,
+    pub at: u64,
+}
+
+/// Payout held for a second approval or an offline signature before it is broadcast
+///
+/// Collect jobs are vault sweeps or ERC20 collects: `recipients` are the swept vaults or the senders
+/// and `receiver` gets the funds.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Job {
+    pub id: String,
+    pub chain_id: u64,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub token: Option<Address>,
+    pub recipients: Vec<Address>,
+    #[serde(
+        serialize_with = "serialize_amounts",
+        deserialize_with = "deserialize_amounts"
+    )]
+    pub amounts: Vec<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receiver: Option<Address>,
+    pub created_by: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub approvals: Vec<Approval>,
+    pub result: Option<String>,
+    #[serde(skip)]
+    pub spend_id: u64,
+    /// Set when the token owner funds the payout through Permit2
+    #[serde(skip)]
+    pub permit2: Option<Permit2Signature>,
+    /// Offline signer of a held payout, its transaction is built once the job is approved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signer: Option<Address>,
+    /// Unsigned transaction of an offline-signed payout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<TypedTransaction>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum JobError {
+    NotFound,
+    NotAwaitingApproval(JobStatus),
+    NotAwaitingSignature(JobStatus),
+    SelfApproval,
+}
+
+/// Job with the fields the API doesn't show, as written to the jobs file
+#[derive(Deserialize, Serialize)]
+struct StoredJob {
+    job: Job,
+    spend_id: u64,
+    permit2: Option<Permit2Signature>,
+}
+
+/// Registry of held and offline-signed payouts
+///
+/// Jobs are written to `file` so that approvals and signatures can still be given after a
+/// restart.
+pub struct JobStore {
+    jobs: Mutex<HashMap<String, Job>>,
+    metrics: Arc<Metrics>,
+    file: Option<JsonFile>,
+}
+
+impl Job {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        chain_id: u64,
+        kind: JobKind,
+        token: Option<Address>,
+        recipients: Vec<Address>,
+        amounts: Vec<U256>,
+        created_by: Option<String>,
+        spend_id: u64,
+        now: u64,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            chain_id,
+            kind,
+            status: JobStatus::AwaitingApproval,
+            token,
+            recipients,
+            amounts,
+            receiver: None,
+            created_by,
+            created_at: now,
+            updated_at: now,
+            approvals: Vec::new(),
+            result: None,
+            spend_id,
+            permit2: None,
+            signer: None,
+            transaction: None,
+        }
+    }
+
+    fn is_pending(&self) -> bool {
+        matches!(
+            self.status,
+            JobStatus::AwaitingApproval | JobStatus::AwaitingSignature | JobStatus::Submitted
+        )
+    }
+}
+
+impl From<JobError> for ApiError {
+    fn from(e: JobError) -> Self {
+        match e {
+            JobError::NotFound => ApiError::not_found("Job not found"),
+            JobError::NotAwaitingApproval(status) => ApiError::conflict(format!(
+                "Job is not awaiting approval, status is {:?}",
+                status
+            )),
+            JobError::NotAwaitingSignature(status) => ApiError::conflict(format!(
+                "Job is not awaiting a signature, status is {:?}",
+                status
+            )),
+            JobError::SelfApproval => {
+                ApiError::forbidden("Jobs must be approved by a different API key")
+            }
+        }
+    }
+}
+
+impl JobStore {
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+            metrics,
+            file: None,
+        }
+    }
+
+    /// Restores the jobs from `file` and records changes there
+    pub fn load(file: JsonFile, metrics: Arc<Metrics>) -> anyhow::Result<Self> {
+        let stored: Vec<StoredJob> = file.load()?;
+        let jobs = stored
+            .into_iter()
+            .map(|stored| {
+                let mut job = stored.job;
+                job.spend_id = stored.spend_id;
+                job.permit2 = stored.permit2;
+                (job.id.clone(), job)
+            })
+            .collect();
+
+        let store = Self {
+            jobs: Mutex::new(jobs),
+            metrics,
+            file: Some(file),
+        };
+        store.update_pending(&store.jobs.lock().unwrap());
+        Ok(store)
+    }
+
+    fn save(&self, jobs: &HashMap<String, Job>) {
+        let Some(file) = &self.file else {
+            return;
+        };
+
+        let stored: Vec<StoredJob> = jobs
+            .values()
+            .map(|job| StoredJob {
+                job: job.clone(),
+                spend_id: job.spend_id,
+                permit2: job.permit2.clone(),
+            })
+            .collect();
+        if let Err(e) = file.save(&stored) {
+            error!(error = %e, "Failed to record jobs");
+        }
+    }
+
+    pub fn insert(&self, job: Job) {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.insert(job.id.clone(), job);
+        self.save(&jobs);
+        self.update_pending(&jobs);
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.jobs.lock().unwrap().get(id).cloned()
+    }
+
+    /// All jobs, newest first
+    pub fn list(&self) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self.jobs.lock().unwrap().values().cloned().collect();
+        jobs.sort_by_key(|job| Reverse(job.created_at));
+        jobs
+    }
+
+    /// Records an approval or rejection, an approved job moves to `Submitted`
+    pub fn decide(
+        &self,
+        id: &str,
+        approver: &str,
+        approved: bool,
+        now: u64,
+    ) -> Result<Job, JobError> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(id).ok_or(JobError::NotFound)?;
+
+        if job.status != JobStatus::AwaitingApproval {
+            return Err(JobError::NotAwaitingApproval(job.status));
+        }
+        if job.created_by.as_deref() == Some(approver) {
+            return Err(JobError::SelfApproval);
+        }
+
+        job.approvals.push(Approval {
+            approver: approver.to_string(),
+            approved,
+            at: now,
+        });
+        job.status = if approved {
+            JobStatus::Submitted
+        } else {
+            JobStatus::Rejected
+        };
+        job.updated_at = now;
+
+        let job = job.clone();
+        self.save(&jobs);
+        self.update_pending(&jobs);
+        Ok(job)
+    }
+
+    /// Moves a job awaiting its offline signature to `Submitted`, so it is broadcast only once
+    pub fn submit_signed(&self, id: &str, now: u64) -> Result<Job, JobError> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(id).ok_or(JobError::NotFound)?;
+
+        if job.status != JobStatus::AwaitingSignature {
+            return Err(JobError::NotAwaitingSignature(job.status));
+        }
+        job.status = JobStatus::Submitted;
+        job.updated_at = now;
+
+        let job = job.clone();
+        self.save(&jobs);
+        Ok(job)
+    }
+
+    /// Withdraws a job awaiting its offline signature, its transaction is no longer accepted
+    pub fn cancel(&self, id: &str, now: u64) -> Result<Job, JobError> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(id).ok_or(JobError::NotFound)?;
+
+        if job.status != JobStatus::AwaitingSignature {
+            return Err(JobError::NotAwaitingSignature(job.status));
+        }
+        job.status = JobStatus::Cancelled;
+        job.updated_at = now;
+
+        let job = job.clone();
+        self.save(&jobs);
+        self.update_pending(&jobs);
+        Ok(job)
+    }
+
+    /// Fails a job still awaiting its offline signature, e.g. when screening rejects it at broadcast
+    pub fn fail_if_awaiting_signature(
+        &self,
+        id: &str,
+        result: String,
+        now: u64,
+    ) -> Result<Job, JobError> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(id).ok_or(JobError::NotFound)?;
+
+        if job.status != JobStatus::AwaitingSignature {
+            return Err(JobError::NotAwaitingSignature(job.status));
+        }
+        job.status = JobStatus::Failed;
+        job.result = Some(result);
+        job.updated_at = now;
+
+        let job = job.clone();
+        self.save(&jobs);
+        self.update_pending(&jobs);
+        Ok(job)
+    }
+
+    /// Stores the outcome of a submitted job
+    pub fn finish(&self, id: &str, succeeded: bool, result: String, now: u64) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.get_mut(id) {
+            job.status = if succeeded {
+                JobStatus::Succeeded
+            } else {
+                JobStatus::Failed
+            };
+            job.result = Some(result);
+            job.updated_at = now;
+        }
+        self.save(&jobs);
+        self.update_pending(&jobs);
+    }
+
+    fn update_pending(&self, jobs: &HashMap<String, Job>) {
+        let pending = jobs.values().filter(|job| job.is_pending()).count();
+        self.metrics.set_pending_jobs(pending);
+    }
+}
+
+fn serialize_amounts<S: Serializer>(amounts: &[U256], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(amounts.iter().map(|amount| amount.to_string()))
+}
+
+fn deserialize_amounts<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<U256>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|amount| U256::from_dec_str(amount).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store_with_job() -> (JobStore, String) {
+        let store = JobStore::new(Arc::new(Metrics::new().unwrap()));
+        let job = Job::new(
+            31337,
+            JobKind::DisperseEth,
+            None,
+            vec![Address::repeat_byte(1)],
+            vec![U256::from(100)],
+            Some("payroll".to_string()),
+            1,
+            10,
+        );
+        let id = job.id.clone();
+        store.insert(job);
+
+        (store, id)
+    }
+
+    #[test]
+    fn test_second_approval() {
+        let (store, id) = store_with_job();
+
+        assert_eq!(
+            store.decide(&id, "payroll", true, 20).unwrap_err(),
+            JobError::SelfApproval
+        );
+
+        let job = store.decide(&id, "treasury", true, 20).unwrap();
+        assert_eq!(job.status, JobStatus::Submitted);
+        assert_eq!(job.approvals.len(), 1);
+        assert_eq!(job.approvals[0].approver, "treasury");
+
+        assert_eq!(
+            store.decide(&id, "treasury", false, 30).unwrap_err(),
+            JobError::NotAwaitingApproval(JobStatus::Submitted)
+        );
+
+        store.finish(&id, true, "Transaction successful".to_string(), 40);
+        assert_eq!(store.get(&id).unwrap().status, JobStatus::Succeeded);
+    }
+
+    #[test]
+    fn test_submit_signed() {
+        let (store, id) = store_with_job();
+
+        assert_eq!(
+            store.submit_signed(&id, 20).unwrap_err(),
+            JobError::NotAwaitingSignature(JobStatus::AwaitingApproval)
+        );
+
+        let mut job = store.get(&id).unwrap();
+        job.id = "offline".to_string();
+        job.status = JobStatus::AwaitingSignature;
+        store.insert(job);
+
+        let job = store.submit_signed("offline", 20).unwrap();
+        assert_eq!(job.status, JobStatus::Submitted);
+        assert_eq!(
+            store.submit_signed("offline", 30).unwrap_err(),
+            JobError::NotAwaitingSignature(JobStatus::Submitted)
+        );
+    }
+
+    #[test]
+    fn test_cancel() {
+        let (store, id) = store_with_job();
+
+        assert_eq!(
+            store.cancel(&id, 20).unwrap_err(),
+            JobError::NotAwaitingSignature(JobStatus::AwaitingApproval)
+        );
+
+        let mut job = store.get(&id).unwrap();
+        job.status = JobStatus::AwaitingSignature;
+        store.insert(job);
+
+        assert_eq!(store.cancel(&id, 20).unwrap().status, JobStatus::Cancelled);
+        assert_eq!(
+            store.submit_signed(&id, 30).unwrap_err(),
+            JobError::NotAwaitingSignature(JobStatus::Cancelled)
+        );
+        assert_eq!(store.cancel("missing", 30).unwrap_err(), JobError::NotFound);
+        assert_eq!(
+            store
+                .fail_if_awaiting_signature(&id, "Blocked".to_string(), 40)
+                .unwrap_err(),
+            JobError::NotAwaitingSignature(JobStatus::Cancelled)
+        );
+    }
+
+    #[test]
+    fn test_jobs_survive_restart() {
+        let dir = crate::persist::temp_dir();
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let store =
+            JobStore::load(JsonFile::new(&dir, "jobs.json").unwrap(), metrics.clone()).unwrap();
+        let mut job = Job::new(
+            31337,
+            JobKind::DisperseErc20,
+            Some(Address::repeat_byte(2)),
+            vec![Address::repeat_byte(1)],
+            vec![U256::MAX],
+            Some("payroll".to_string()),
+            7,
+            10,
+        );
+        job.signer = Some(Address::repeat_byte(3));
+        let id = job.id.clone();
+        store.insert(job);
+        store.decide(&id, "treasury", true, 20).unwrap();
+
+        let store = JobStore::load(JsonFile::new(&dir, "jobs.json").unwrap(), metrics).unwrap();
+        let job = store.get(&id).unwrap();
+        assert_eq!(job.status, JobStatus::Submitted);
+        assert_eq!(job.amounts, vec![U256::MAX]);
+        assert_eq!(job.spend_id, 7);
+        assert_eq!(job.signer, Some(Address::repeat_byte(3)));
+        assert_eq!(job.approvals[0].approver, "treasury");
+    }
+
+    #[test]
+    fn test_reject() {
+        let (store, id) = store_with_job();
+
+        let job = store.decide(&id, "treasury", false, 20).unwrap();
+        assert_eq!(job.status, JobStatus::Rejected);
+        assert_eq!(
+            store.decide("missing", "treasury", true, 20).unwrap_err(),
+            JobError::NotFound
+        );
+    }
+}
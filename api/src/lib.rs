@@ -1,5 +1,20 @@
+pub mod auth;
 pub mod config;
+pub mod create2;
+pub mod deposits;
+pub mod eip712;
+pub mod error;
 pub mod handlers;
+pub mod jobs;
+pub mod metrics;
+pub mod permits;
+pub mod persist;
+pub mod planner;
+pub mod policy;
 pub mod routes;
+pub mod screening;
+pub mod signing;
 pub mod state;
+pub mod telemetry;
 pub mod types;
+pub mod vaults;
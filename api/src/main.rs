@@ -1,24 +1,65 @@
 use api::config::AppConfig;
-use api::routes::{collect_routes, disperse_routes};
+use api::handlers::deposits::spawn_deposit_watchers;
+use api::handlers::sweeps::spawn_auto_sweepers;
+use api::metrics::track_requests;
+use api::routes::{
+    allowances_routes, broadcast_routes, collect_routes, deposits_routes, disperse_routes,
+    health_routes, jobs_routes, metrics_routes, vaults_routes,
+};
 use api::state::AppState;
-use axum::Router;
+use api::telemetry::{init_tracing, make_request_span};
+use axum::{middleware, Router};
 use core::result::Result;
 use std::sync::Arc;
-use tracing::info;
+use tower::ServiceBuilder;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tracing::{info, warn, Level};
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
     dotenvy::dotenv().ok();
     let config = AppConfig::load()?;
+    init_tracing(&config)?;
     let port = config.port;
     let state: Arc<AppState> = AppState::init(config).await?;
+    if !state.api_keys().is_enabled() {
+        warn!("allow_unauthenticated is set without API keys, payout routes are unauthenticated");
+    }
+    spawn_deposit_watchers(state.clone());
+    spawn_auto_sweepers(state.clone());
 
     let collect_routes = collect_routes(state.clone());
-    let disperse_routes = disperse_routes(state);
+    let disperse_routes = disperse_routes(state.clone());
+    let allowances_routes = allowances_routes(state.clone());
+    let vaults_routes = vaults_routes(state.clone());
+    let deposits_routes = deposits_routes(state.clone());
+    let jobs_routes = jobs_routes(state.clone());
+    let broadcast_routes = broadcast_routes(state.clone());
+    let health_routes = health_routes(state.clone());
+    let metrics_routes = metrics_routes(state.clone());
 
     let app = Router::new()
         .nest("/collect", collect_routes)
-        .nest("/disperse", disperse_routes);
+        .nest("/disperse", disperse_routes)
+        .nest("/allowances", allowances_routes)
+        .nest("/vaults", vaults_routes)
+        .nest("/deposits", deposits_routes)
+        .nest("/jobs", jobs_routes)
+        .merge(broadcast_routes)
+        .merge(health_routes)
+        .merge(metrics_routes)
+        .layer(middleware::from_fn_with_state(state, track_requests))
+        .layer(
+            ServiceBuilder::new()
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(make_request_span)
+                        .on_response(DefaultOnResponse::new().level(Level::INFO)),
+                )
+                .layer(PropagateRequestIdLayer::x_request_id()),
+        );
 
     let listener = tokio::net::TcpListener::bind("127.0.0.1:".to_string() + &port.to_string())
         .await
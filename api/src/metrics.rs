@@ -0,0 +1,258 @@
+use crate::state::AppState;
+use async_trait::async_trait;
+use axum::extract::{MatchedPath, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use ethers::prelude::*;
+use ethers_providers::{HttpClientError, JsonRpcClient};
+use prometheus::{
+    CounterVec, Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts,
+    Registry, TextEncoder,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt::Debug, sync::Arc, time::Instant};
+use tracing::{debug_span, warn, Instrument};
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    payouts: IntCounterVec,
+    gas_used: CounterVec,
+    fees_spent: CounterVec,
+    rpc_requests: IntCounterVec,
+    rpc_errors: IntCounterVec,
+    operator_balance: GaugeVec,
+    pending_jobs: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new();
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status"),
+            &["method", "route", "status"],
+        )?;
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency by route",
+            ),
+            &["method", "route"],
+        )?;
+        let payouts = IntCounterVec::new(
+            Opts::new(
+                "payouts_total",
+                "Disperse and collect transactions by status",
+            ),
+            &["chain_id", "operation", "asset", "status"],
+        )?;
+        let gas_used = CounterVec::new(
+            Opts::new("gas_used_total", "Gas used by mined payout transactions"),
+            &["chain_id", "operation", "asset"],
+        )?;
+        let fees_spent = CounterVec::new(
+            Opts::new(
+                "fees_spent_wei_total",
+                "Fees paid by mined payout transactions",
+            ),
+            &["chain_id", "operation", "asset"],
+        )?;
+        let rpc_requests = IntCounterVec::new(
+            Opts::new("rpc_requests_total", "JSON-RPC calls by method"),
+            &["method"],
+        )?;
+        let rpc_errors = IntCounterVec::new(
+            Opts::new("rpc_errors_total", "Failed JSON-RPC calls by method"),
+            &["method"],
+        )?;
+        let operator_balance = GaugeVec::new(
+            Opts::new("operator_balance_wei", "Operator account balance"),
+            &["chain_id"],
+        )?;
+        let pending_jobs = IntGauge::new(
+            "pending_jobs",
+            "Jobs awaiting approval or submitted and not yet finished",
+        )?;
+
+        registry.register(Box::new(http_requests.clone()))?;
+        registry.register(Box::new(http_request_duration.clone()))?;
+        registry.register(Box::new(payouts.clone()))?;
+        registry.register(Box::new(gas_used.clone()))?;
+        registry.register(Box::new(fees_spent.clone()))?;
+        registry.register(Box::new(rpc_requests.clone()))?;
+        registry.register(Box::new(rpc_errors.clone()))?;
+        registry.register(Box::new(operator_balance.clone()))?;
+        registry.register(Box::new(pending_jobs.clone()))?;
+
+        Ok(Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            payouts,
+            gas_used,
+            fees_spent,
+            rpc_requests,
+            rpc_errors,
+            operator_balance,
+            pending_jobs,
+        })
+    }
+
+    pub fn record_payout(&self, chain_id: u64, operation: &str, asset: &str, status: &str) {
+        self.payouts
+            .with_label_values(&[&chain_id.to_string(), operation, asset, status])
+            .inc();
+    }
+
+    pub fn record_receipt(
+        &self,
+        chain_id: u64,
+        operation: &str,
+        asset: &str,
+        receipt: &TransactionReceipt,
+    ) {
+        let labels = [&chain_id.to_string(), operation, asset];
+        let gas_used = receipt.gas_used.unwrap_or_default();
+        let gas_price = receipt.effective_gas_price.unwrap_or_default();
+
+        self.gas_used
+            .with_label_values(&labels)
+            .inc_by(to_f64(gas_used));
+        self.fees_spent
+            .with_label_values(&labels)
+            .inc_by(to_f64(gas_used.saturating_mul(gas_price)));
+    }
+
+    pub fn set_operator_balance(&self, chain_id: u64, balance: U256) {
+        self.operator_balance
+            .with_label_values(&[&chain_id.to_string()])
+            .set(to_f64(balance));
+    }
+
+    pub fn set_pending_jobs(&self, pending: usize) {
+        self.pending_jobs.set(pending as i64);
+    }
+
+    fn record_rpc_call(&self, method: &str, failed: bool) {
+        self.rpc_requests.with_label_values(&[method]).inc();
+        if failed {
+            self.rpc_errors.with_label_values(&[method]).inc();
+        }
+    }
+
+    pub fn encode(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+/// Converts wei amounts for gauges and counters, saturating above `u128::MAX`
+fn to_f64(value: U256) -> f64 {
+    u128::try_from(value).unwrap_or(u128::MAX) as f64
+}
+
+/// HTTP transport that counts JSON-RPC calls and errors per method
+#[derive(Clone)]
+pub struct MeteredHttp {
+    inner: Http,
+    metrics: Arc<Metrics>,
+}
+
+impl MeteredHttp {
+    pub fn new(inner: Http, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+impl Debug for MeteredHttp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MeteredHttp")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for MeteredHttp {
+    type Error = HttpClientError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let result = JsonRpcClient::request(&self.inner, method, params)
+            .instrument(debug_span!("rpc", method))
+            .await;
+        self.metrics.record_rpc_call(method, result.is_err());
+
+        if let Err(e) = &result {
+            warn!(method, error = %e, "RPC call failed");
+        }
+
+        result
+    }
+}
+
+/// Records request count and latency per matched route
+pub async fn track_requests(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+
+    let metrics = state.metrics();
+    metrics
+        .http_request_duration
+        .with_label_values(&[&method, &route])
+        .observe(start.elapsed().as_secs_f64());
+    metrics
+        .http_requests
+        .with_label_values(&[&method, &route, response.status().as_str()])
+        .inc();
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_amounts_saturate() {
+        let metrics = Metrics::new().unwrap();
+        let receipt = TransactionReceipt {
+            gas_used: Some(U256::MAX),
+            effective_gas_price: Some(U256::MAX),
+            ..Default::default()
+        };
+        metrics.record_receipt(1, "disperse", "eth", &receipt);
+        metrics.set_operator_balance(1, U256::MAX);
+
+        let saturated = u128::MAX as f64;
+        let labels = ["1", "disperse", "eth"];
+        assert_eq!(metrics.gas_used.with_label_values(&labels).get(), saturated);
+        assert_eq!(
+            metrics.fees_spent.with_label_values(&labels).get(),
+            saturated
+        );
+        assert_eq!(
+            metrics.operator_balance.with_label_values(&["1"]).get(),
+            saturated
+        );
+        assert_eq!(to_f64(U256::from(1_000u64)), 1_000.0);
+    }
+}
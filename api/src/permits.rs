@@ -0,0 +1,336 @@
+use ethers::abi::{encode, Token};
+use ethers::types::transaction::eip712::{Eip712, TypedData};
+use ethers::types::{Address, Signature, H256, U256};
+use ethers::utils::keccak256;
+use serde_json::json;
+
+const TRANSFER_WITH_AUTHORIZATION_TYPEHASH: &str = "TransferWithAuthorization(address from,address to,uint256 value,uint256 validAfter,uint256 validBefore,bytes32 nonce)";
+const PERMIT_TYPEHASH: &str =
+    "Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)";
+
+/// EIP-2612 permit letting `spender` pull `value` from `owner`
+pub struct Eip2612Permit {
+    pub owner: Address,
+    pub spender: Address,
+    pub value: U256,
+    pub nonce: U256,
+    pub deadline: U256,
+}
+
+impl Eip2612Permit {
+    /// Digest signed by the owner, `domain_separator` is the token's `DOMAIN_SEPARATOR()`
+    pub fn digest(&self, domain_separator: [u8; 32]) -> [u8; 32] {
+        let struct_hash = keccak256(encode(&[
+            Token::FixedBytes(keccak256(PERMIT_TYPEHASH).to_vec()),
+            Token::Address(self.owner),
+            Token::Address(self.spender),
+            Token::Uint(self.value),
+            Token::Uint(self.nonce),
+            Token::Uint(self.deadline),
+        ]));
+
+        typed_data_digest(domain_separator, struct_hash)
+    }
+}
+
+/// EIP-3009 authorization moving `value` from `from` to `to` within the validity window
+pub struct TransferWithAuthorization {
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    pub valid_after: U256,
+    pub valid_before: U256,
+    pub nonce: [u8; 32],
+}
+
+impl TransferWithAuthorization {
+    /// Digest signed by `from`, `domain_separator` is the token's `DOMAIN_SEPARATOR()`
+    pub fn digest(&self, domain_separator: [u8; 32]) -> [u8; 32] {
+        let struct_hash = keccak256(encode(&[
+            Token::FixedBytes(keccak256(TRANSFER_WITH_AUTHORIZATION_TYPEHASH).to_vec()),
+            Token::Address(self.from),
+            Token::Address(self.to),
+            Token::Uint(self.value),
+            Token::Uint(self.valid_after),
+            Token::Uint(self.valid_before),
+            Token::FixedBytes(self.nonce.to_vec()),
+        ]));
+
+        typed_data_digest(domain_separator, struct_hash)
+    }
+}
+
+/// Uniswap Permit2 `PermitBatchTransferFrom` letting `spender` pull the `permitted` token amounts
+pub struct Permit2Batch<'a> {
+    pub chain_id: u64,
+    pub permit2: Address,
+    pub spender: Address,
+    /// Token and amount pairs
+    pub permitted: &'a [(Address, U256)],
+    pub nonce: U256,
+    pub deadline: U256,
+}
+
+impl Permit2Batch<'_> {
+    pub fn typed_data(&self) -> TypedData {
+        let permitted: Vec<_> = self
+            .permitted
+            .iter()
+            .map(|(token, amount)| json!({ "token": token, "amount": amount.to_string() }))
+            .collect();
+
+        serde_json::from_value(json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "PermitBatchTransferFrom": [
+                    { "name": "permitted", "type": "TokenPermissions[]" },
+                    { "name": "spender", "type": "address" },
+                    { "name": "nonce", "type": "uint256" },
+                    { "name": "deadline", "type": "uint256" }
+                ],
+                "TokenPermissions": [
+                    { "name": "token", "type": "address" },
+                    { "name": "amount", "type": "uint256" }
+                ]
+            },
+            "primaryType": "PermitBatchTransferFrom",
+            "domain": {
+                "name": "Permit2",
+                "chainId": self.chain_id,
+                "verifyingContract": self.permit2
+            },
+            "message": {
+                "permitted": permitted,
+                "spender": self.spender,
+                "nonce": self.nonce.to_string(),
+                "deadline": self.deadline.to_string()
+            }
+        }))
+        .expect("Permit2 typed data is well-formed")
+    }
+
+    pub fn digest(&self) -> Result<[u8; 32], String> {
+        self.typed_data()
+            .encode_eip712()
+            .map_err(|e| format!("Failed to encode typed data: {}", e))
+    }
+}
+
+/// Word and bit of an unordered Permit2 nonce in the owner's `nonceBitmap`
+pub fn permit2_nonce_position(nonce: U256) -> (U256, usize) {
+    (nonce >> 8, (nonce.low_u64() & 0xff) as usize)
+}
+
+/// EIP-712 digest of a struct hash under a domain separator
+pub fn typed_data_digest(domain_separator: [u8; 32], struct_hash: [u8; 32]) -> [u8; 32] {
+    keccak256([&[0x19, 0x01], &domain_separator[..], &struct_hash[..]].concat())
+}
+
+/// Checks `signature` over `digest` was made by `signer`
+pub fn verify_signer(
+    digest: [u8; 32],
+    signature: &Signature,
+    signer: Address,
+) -> Result<(), String> {
+    let recovered = signature
+        .recover(H256::from(digest))
+        .map_err(|e| format!("Failed to recover signer: {}", e))?;
+
+    if recovered != signer {
+        return Err(format!("Signed by {:?} instead of the owner", recovered));
+    }
+
+    Ok(())
+}
+
+/// `v` of `signature` as `ecrecover` expects it, 27 or 28
+///
+/// Signatures may carry a 0/1 or EIP-155 recovery id, which `recover` accepts off-chain but token
+/// contracts reject.
+pub fn ecrecover_v(signature: &Signature) -> Result<u8, String> {
+    signature
+        .recovery_id()
+        .map(|id| 27 + id.to_byte())
+        .map_err(|e| format!("Invalid recovery id: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::core::rand::thread_rng;
+    use ethers::signers::{LocalWallet, Signer};
+
+    #[test]
+    fn test_permit_digest() {
+        let owner = LocalWallet::new(&mut thread_rng());
+        let spender = Address::repeat_byte(0xc0);
+        let token = Address::repeat_byte(0x70);
+
+        let typed_data: TypedData = serde_json::from_value(json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "Permit": [
+                    { "name": "owner", "type": "address" },
+                    { "name": "spender", "type": "address" },
+                    { "name": "value", "type": "uint256" },
+                    { "name": "nonce", "type": "uint256" },
+                    { "name": "deadline", "type": "uint256" }
+                ]
+            },
+            "primaryType": "Permit",
+            "domain": {
+                "name": "TestToken",
+                "version": "1",
+                "chainId": 31337,
+                "verifyingContract": token
+            },
+            "message": {
+                "owner": owner.address(),
+                "spender": spender,
+                "value": "500",
+                "nonce": 3,
+                "deadline": 1_000
+            }
+        }))
+        .unwrap();
+
+        let permit = Eip2612Permit {
+            owner: owner.address(),
+            spender,
+            value: 500.into(),
+            nonce: 3.into(),
+            deadline: 1_000.into(),
+        };
+        let digest = permit.digest(typed_data.domain.separator());
+        assert_eq!(digest, typed_data.encode_eip712().unwrap());
+
+        let signature = owner.sign_hash(H256::from(digest)).unwrap();
+        assert_eq!(verify_signer(digest, &signature, owner.address()), Ok(()));
+        assert!(verify_signer(digest, &signature, spender).is_err());
+    }
+
+    #[test]
+    fn test_permit2_digest() {
+        let permitted = [
+            (Address::repeat_byte(0x70), U256::from(100)),
+            (Address::repeat_byte(0x71), U256::from(200)),
+        ];
+        let batch = Permit2Batch {
+            chain_id: 1,
+            permit2: "0x000000000022D473030F116dDEE9F6B43aC78BA3"
+                .parse()
+                .unwrap(),
+            spender: Address::repeat_byte(0xd1),
+            permitted: &permitted,
+            nonce: 1.into(),
+            deadline: 1_000.into(),
+        };
+
+        let typehash = keccak256(
+            "PermitBatchTransferFrom(TokenPermissions[] permitted,address spender,uint256 nonce,uint256 deadline)TokenPermissions(address token,uint256 amount)",
+        );
+        let token_typehash = keccak256("TokenPermissions(address token,uint256 amount)");
+        let permissions: Vec<u8> = permitted
+            .iter()
+            .flat_map(|(token, amount)| {
+                keccak256(encode(&[
+                    Token::FixedBytes(token_typehash.to_vec()),
+                    Token::Address(*token),
+                    Token::Uint(*amount),
+                ]))
+            })
+            .collect();
+        let struct_hash = keccak256(encode(&[
+            Token::FixedBytes(typehash.to_vec()),
+            Token::FixedBytes(keccak256(permissions).to_vec()),
+            Token::Address(batch.spender),
+            Token::Uint(batch.nonce),
+            Token::Uint(batch.deadline),
+        ]));
+
+        let domain_separator = batch.typed_data().domain.separator();
+        assert_eq!(
+            batch.digest(),
+            Ok(typed_data_digest(domain_separator, struct_hash))
+        );
+        assert_eq!(permit2_nonce_position(U256::from(258)), (U256::one(), 2));
+    }
+
+    #[test]
+    fn test_transfer_with_authorization_digest() {
+        let from = Address::repeat_byte(0xf0);
+        let to = Address::repeat_byte(0x70);
+        let nonce = keccak256("nonce");
+
+        let typed_data: TypedData = serde_json::from_value(json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "TransferWithAuthorization": [
+                    { "name": "from", "type": "address" },
+                    { "name": "to", "type": "address" },
+                    { "name": "value", "type": "uint256" },
+                    { "name": "validAfter", "type": "uint256" },
+                    { "name": "validBefore", "type": "uint256" },
+                    { "name": "nonce", "type": "bytes32" }
+                ]
+            },
+            "primaryType": "TransferWithAuthorization",
+            "domain": {
+                "name": "AuthorizationToken",
+                "version": "1",
+                "chainId": 31337,
+                "verifyingContract": Address::repeat_byte(0xa7)
+            },
+            "message": {
+                "from": from,
+                "to": to,
+                "value": "500",
+                "validAfter": 0,
+                "validBefore": 1_000,
+                "nonce": H256::from(nonce)
+            }
+        }))
+        .unwrap();
+
+        let authorization = TransferWithAuthorization {
+            from,
+            to,
+            value: 500.into(),
+            valid_after: U256::zero(),
+            valid_before: 1_000.into(),
+            nonce,
+        };
+        assert_eq!(
+            authorization.digest(typed_data.domain.separator()),
+            typed_data.encode_eip712().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_ecrecover_v() {
+        let wallet = LocalWallet::new(&mut thread_rng());
+        let digest = H256::repeat_byte(7);
+        let mut signature = wallet.sign_hash(digest).unwrap();
+        let v = signature.v as u8;
+        assert!(v == 27 || v == 28);
+
+        // the same signature written with a 0/1 recovery id
+        signature.v -= 27;
+        verify_signer(digest.0, &signature, wallet.address()).unwrap();
+        assert_eq!(ecrecover_v(&signature).unwrap(), v);
+    }
+}
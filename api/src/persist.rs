@@ -0,0 +1,47 @@
+use anyhow::Context;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// JSON file holding state that has to survive restarts
+///
+/// Writes go to a temporary file that then replaces the previous one, so a crash never leaves a
+/// partially written file behind.
+#[derive(Clone, Debug)]
+pub struct JsonFile {
+    path: PathBuf,
+}
+
+impl JsonFile {
+    /// File `name` in `dir`, creating the directory if needed
+    pub fn new(dir: &Path, name: &str) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create data directory {}", dir.display()))?;
+
+        Ok(Self {
+            path: dir.join(name),
+        })
+    }
+
+    /// Reads the stored value, the default before anything was saved
+    pub fn load<T: DeserializeOwned + Default>(&self) -> anyhow::Result<T> {
+        match fs::read(&self.path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_context(|| format!("Failed to parse {}", self.path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", self.path.display())),
+        }
+    }
+
+    pub fn save<T: Serialize>(&self, value: &T) -> io::Result<()> {
+        let temporary = self.path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_vec_pretty(value)?)?;
+        fs::rename(temporary, &self.path)
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn temp_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("api-test-{}", uuid::Uuid::new_v4()))
+}
@@ -0,0 +1,128 @@
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanStrategy {
+    /// Pull from the largest sources first
+    #[default]
+    LargestFirst,
+    /// Pull from as few sources as possible, sparing large sources when a smaller one covers the target
+    FewestTransactions,
+    /// Empty the smallest sources first
+    DrainDust,
+}
+
+/// Sender or vault with the amount that can be pulled from it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Source {
+    pub address: Address,
+    pub available: U256,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PlanError {
+    Insufficient { available: U256, target: U256 },
+}
+
+/// Picks `(source, amount)` pulls adding up to `target`, using at most `max_sources` sources
+///
+/// Sources holding no more than `min_available` (e.g. the gas cost of pulling them) are ignored.
+pub fn plan_collect(
+    mut sources: Vec<Source>,
+    target: U256,
+    strategy: PlanStrategy,
+    min_available: U256,
+    max_sources: usize,
+) -> Result<Vec<(Address, U256)>, PlanError> {
+    sources.retain(|source| source.available > min_available);
+
+    match strategy {
+        PlanStrategy::LargestFirst => {
+            sources.sort_by_key(|source| std::cmp::Reverse(source.available))
+        }
+        PlanStrategy::DrainDust => sources.sort_by_key(|source| source.available),
+        PlanStrategy::FewestTransactions => {
+            sources.sort_by_key(|source| std::cmp::Reverse(source.available));
+            let smallest_covering = sources
+                .iter()
+                .rposition(|source| source.available >= target);
+            if let Some(index) = smallest_covering {
+                let source = sources.remove(index);
+                sources.insert(0, source);
+            }
+        }
+    }
+
+    let mut pulls = Vec::new();
+    let mut remaining = target;
+    for source in sources.iter().take(max_sources) {
+        if remaining.is_zero() {
+            break;
+        }
+
+        let amount = source.available.min(remaining);
+        pulls.push((source.address, amount));
+        remaining -= amount;
+    }
+
+    if !remaining.is_zero() {
+        return Err(PlanError::Insufficient {
+            available: target - remaining,
+            target,
+        });
+    }
+
+    Ok(pulls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources() -> Vec<Source> {
+        [(1, 5), (2, 300), (3, 40), (4, 120), (5, 2)]
+            .into_iter()
+            .map(|(byte, available)| Source {
+                address: Address::repeat_byte(byte),
+                available: available.into(),
+            })
+            .collect()
+    }
+
+    fn plan(target: u64, strategy: PlanStrategy) -> Vec<(u8, u64)> {
+        plan_collect(sources(), target.into(), strategy, 2.into(), 10)
+            .unwrap()
+            .into_iter()
+            .map(|(address, amount)| (address.0[0], amount.as_u64()))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_strategies() {
+        assert_eq!(plan(100, PlanStrategy::LargestFirst), vec![(2, 100)]);
+        assert_eq!(plan(100, PlanStrategy::FewestTransactions), vec![(4, 100)]);
+        assert_eq!(
+            plan(400, PlanStrategy::FewestTransactions),
+            vec![(2, 300), (4, 100)]
+        );
+        assert_eq!(
+            plan(100, PlanStrategy::DrainDust),
+            vec![(1, 5), (3, 40), (4, 55)]
+        );
+
+        assert_eq!(
+            plan_collect(
+                sources(),
+                500.into(),
+                PlanStrategy::LargestFirst,
+                2.into(),
+                2
+            ),
+            Err(PlanError::Insufficient {
+                available: 420.into(),
+                target: 500.into()
+            })
+        );
+    }
+}
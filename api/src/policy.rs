@@ -0,0 +1,458 @@
+use crate::config::{AssetPolicy, SpendPolicyConfig};
+use crate::persist::JsonFile;
+use crate::types::{deserialize_decimal, serialize_decimal};
+use anyhow::anyhow;
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use tracing::error;
+
+const DEFAULT_CAP_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Asset {
+    Eth,
+    Token(Address),
+}
+
+/// Parses a config key, `eth` or a token address
+pub fn parse_asset(key: &str) -> Option<Asset> {
+    if key.eq_ignore_ascii_case("eth") {
+        return Some(Asset::Eth);
+    }
+
+    key.parse().ok().map(Asset::Token)
+}
+
+/// Payout checked against the spending policy, `principal` is the API key or approver
+pub struct Spend<'a> {
+    pub asset: Asset,
+    pub principal: Option<&'a str>,
+    pub amounts: &'a [U256],
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PolicyViolation {
+    TokenNotAllowed(Address),
+    TransactionLimit {
+        total: U256,
+        max: U256,
+    },
+    RecipientLimit {
+        index: usize,
+        amount: U256,
+        max: U256,
+    },
+    AssetCap {
+        spent: U256,
+        total: U256,
+        cap: U256,
+    },
+    PrincipalCap {
+        principal: String,
+        spent: U256,
+        total: U256,
+        cap: U256,
+    },
+}
+
+/// Spend recorded in the ledger, `needs_approval` is set above the second approval threshold
+#[derive(Debug, PartialEq)]
+pub struct Reservation {
+    pub id: u64,
+    pub needs_approval: bool,
+}
+
+impl Reservation {
+    /// Stands in for payouts that aren't charged to the ledger, releasing it is a no-op
+    pub fn exempt() -> Self {
+        Self {
+            id: 0,
+            needs_approval: false,
+        }
+    }
+}
+
+/// Per-chain spending guardrails with a rolling spend ledger
+///
+/// The ledger is written to `file` on every reservation and release, so a restart neither resets
+/// the caps nor forgets the reservations of held jobs.
+pub struct PolicyEngine {
+    allowed_tokens: Option<Vec<Address>>,
+    cap_window_secs: u64,
+    assets: HashMap<Asset, AssetPolicy>,
+    ledger: Mutex<Ledger>,
+    file: Option<JsonFile>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct Ledger {
+    next_id: u64,
+    entries: Vec<LedgerEntry>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct LedgerEntry {
+    id: u64,
+    at: u64,
+    asset: Asset,
+    principal: Option<String>,
+    #[serde(
+        serialize_with = "serialize_decimal",
+        deserialize_with = "deserialize_decimal"
+    )]
+    amount: U256,
+}
+
+impl fmt::Display for Asset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Asset::Eth => write!(f, "ETH"),
+            Asset::Token(token) => write!(f, "token {:?}", token),
+        }
+    }
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyViolation::TokenNotAllowed(token) => {
+                write!(f, "Token {:?} is not allowed", token)
+            }
+            PolicyViolation::TransactionLimit { total, max } => {
+                write!(
+                    f,
+                    "Total {} exceeds the per-transaction limit {}",
+                    total, max
+                )
+            }
+            PolicyViolation::RecipientLimit { index, amount, max } => write!(
+                f,
+                "Amount {} of recipient {} exceeds the per-recipient limit {}",
+                amount, index, max
+            ),
+            PolicyViolation::AssetCap { spent, total, cap } => write!(
+                f,
+                "Total {} on top of {} already spent exceeds the cap {}",
+                total, spent, cap
+            ),
+            PolicyViolation::PrincipalCap {
+                principal,
+                spent,
+                total,
+                cap,
+            } => write!(
+                f,
+                "Total {} on top of {} already spent by {} exceeds the cap {}",
+                total, spent, principal, cap
+            ),
+        }
+    }
+}
+
+impl PolicyEngine {
+    pub fn new(config: SpendPolicyConfig) -> anyhow::Result<Self> {
+        let mut assets = HashMap::new();
+        for (key, policy) in config.assets {
+            let asset =
+                parse_asset(&key).ok_or_else(|| anyhow!("Invalid spend policy asset {}", key))?;
+            assets.insert(asset, policy);
+        }
+
+        Ok(Self {
+            allowed_tokens: config.allowed_tokens,
+            cap_window_secs: config.cap_window_secs.unwrap_or(DEFAULT_CAP_WINDOW_SECS),
+            assets,
+            ledger: Mutex::new(Ledger::default()),
+            file: None,
+        })
+    }
+
+    /// Restores the spend ledger from `file` and records changes there
+    pub fn load(config: SpendPolicyConfig, file: JsonFile) -> anyhow::Result<Self> {
+        let mut engine = Self::new(config)?;
+        engine.ledger = Mutex::new(file.load()?);
+        engine.file = Some(file);
+        Ok(engine)
+    }
+
+    fn save(&self, ledger: &Ledger) {
+        if let Some(file) = &self.file {
+            if let Err(e) = file.save(ledger) {
+                error!(error = %e, "Failed to record the spend ledger");
+            }
+        }
+    }
+
+    /// Checks a payout and reserves its total in the ledger, `now` is a unix timestamp
+    ///
+    /// The reservation id is passed to [`PolicyEngine::release`] if the payout fails or is rejected.
+    /// The check and the reservation happen under one lock, so concurrent payouts can't exceed
+    /// the caps together.
+    pub fn reserve(&self, spend: &Spend, now: u64) -> Result<Reservation, PolicyViolation> {
+        let mut ledger = self.ledger.lock().unwrap();
+        self.check_ledger(&mut ledger, spend, now)?;
+        let needs_approval = self.needs_approval(spend);
+
+        ledger.next_id += 1;
+        let id = ledger.next_id;
+        ledger.entries.push(LedgerEntry {
+            id,
+            at: now,
+            asset: spend.asset,
+            principal: spend.principal.map(str::to_string),
+            amount: total(spend.amounts),
+        });
+        self.save(&ledger);
+
+        Ok(Reservation { id, needs_approval })
+    }
+
+    pub fn release(&self, id: u64) {
+        let mut ledger = self.ledger.lock().unwrap();
+        ledger.entries.retain(|entry| entry.id != id);
+        self.save(&ledger);
+    }
+
+    /// Checks a payout without reserving it
+    pub fn check(&self, spend: &Spend, now: u64) -> Result<(), PolicyViolation> {
+        self.check_ledger(&mut self.ledger.lock().unwrap(), spend, now)
+    }
+
+    fn check_ledger(
+        &self,
+        ledger: &mut Ledger,
+        spend: &Spend,
+        now: u64,
+    ) -> Result<(), PolicyViolation> {
+        self.check_asset(spend.asset)?;
+
+        let Some(policy) = self.assets.get(&spend.asset) else {
+            return Ok(());
+        };
+        let total = total(spend.amounts);
+
+        if let Some(max) = policy.max_per_transaction {
+            if total > max.0 {
+                return Err(PolicyViolation::TransactionLimit { total, max: max.0 });
+            }
+        }
+
+        if let Some(max) = policy.max_per_recipient {
+            if let Some((index, &amount)) = spend
+                .amounts
+                .iter()
+                .enumerate()
+                .find(|(_, &amount)| amount > max.0)
+            {
+                return Err(PolicyViolation::RecipientLimit {
+                    index,
+                    amount,
+                    max: max.0,
+                });
+            }
+        }
+
+        let window_start = now.saturating_sub(self.cap_window_secs);
+        ledger.entries.retain(|entry| entry.at > window_start);
+
+        if let Some(cap) = policy.cap {
+            let spent = ledger
+                .entries
+                .iter()
+                .filter(|entry| entry.asset == spend.asset)
+                .fold(U256::zero(), |sum, entry| sum.saturating_add(entry.amount));
+            if spent.saturating_add(total) > cap.0 {
+                return Err(PolicyViolation::AssetCap {
+                    spent,
+                    total,
+                    cap: cap.0,
+                });
+            }
+        }
+
+        if let (Some(cap), Some(principal)) = (policy.cap_per_api_key, spend.principal) {
+            let spent = ledger
+                .entries
+                .iter()
+                .filter(|entry| {
+                    entry.asset == spend.asset && entry.principal.as_deref() == Some(principal)
+                })
+                .fold(U256::zero(), |sum, entry| sum.saturating_add(entry.amount));
+            if spent.saturating_add(total) > cap.0 {
+                return Err(PolicyViolation::PrincipalCap {
+                    principal: principal.to_string(),
+                    spent,
+                    total,
+                    cap: cap.0,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the asset is on the allowlist
+    pub fn check_asset(&self, asset: Asset) -> Result<(), PolicyViolation> {
+        if let (Asset::Token(token), Some(allowed_tokens)) = (asset, &self.allowed_tokens) {
+            if !allowed_tokens.contains(&token) {
+                return Err(PolicyViolation::TokenNotAllowed(token));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn needs_approval(&self, spend: &Spend) -> bool {
+        self.assets
+            .get(&spend.asset)
+            .and_then(|policy| policy.second_approval_above)
+            .is_some_and(|threshold| total(spend.amounts) > threshold.0)
+    }
+}
+
+fn total(amounts: &[U256]) -> U256 {
+    amounts
+        .iter()
+        .fold(U256::zero(), |sum, amount| sum.saturating_add(*amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Amount;
+
+    fn engine() -> PolicyEngine {
+        PolicyEngine::new(config()).unwrap()
+    }
+
+    fn config() -> SpendPolicyConfig {
+        let eth_policy = AssetPolicy {
+            max_per_transaction: Some(Amount(1_000.into())),
+            max_per_recipient: Some(Amount(600.into())),
+            cap: Some(Amount(2_000.into())),
+            cap_per_api_key: Some(Amount(1_000.into())),
+            second_approval_above: Some(Amount(900.into())),
+        };
+
+        SpendPolicyConfig {
+            allowed_tokens: Some(vec![Address::repeat_byte(1)]),
+            cap_window_secs: Some(100),
+            assets: HashMap::from([("eth".to_string(), eth_policy)]),
+        }
+    }
+
+    fn spend<'a>(principal: &'a str, amounts: &'a [U256]) -> Spend<'a> {
+        Spend {
+            asset: Asset::Eth,
+            principal: Some(principal),
+            amounts,
+        }
+    }
+
+    #[test]
+    fn test_limits() {
+        let engine = engine();
+
+        let token = Spend {
+            asset: Asset::Token(Address::repeat_byte(2)),
+            principal: None,
+            amounts: &[U256::one()],
+        };
+        assert_eq!(
+            engine.check(&token, 0),
+            Err(PolicyViolation::TokenNotAllowed(Address::repeat_byte(2)))
+        );
+
+        let amounts = [U256::from(700)];
+        assert!(matches!(
+            engine.check(&spend("a", &amounts), 0),
+            Err(PolicyViolation::RecipientLimit { index: 0, .. })
+        ));
+
+        let amounts = [U256::from(600), U256::from(600)];
+        assert!(matches!(
+            engine.check(&spend("a", &amounts), 0),
+            Err(PolicyViolation::TransactionLimit { .. })
+        ));
+
+        let amounts = [U256::from(500), U256::from(450)];
+        let reservation = engine.reserve(&spend("a", &amounts), 0).unwrap();
+        assert!(reservation.needs_approval);
+    }
+
+    #[test]
+    fn test_rolling_caps() {
+        let engine = engine();
+        let amounts = [U256::from(400), U256::from(400)];
+
+        let id = engine.reserve(&spend("a", &amounts), 10).unwrap().id;
+        assert!(matches!(
+            engine.reserve(&spend("a", &amounts), 20),
+            Err(PolicyViolation::PrincipalCap { .. })
+        ));
+        engine.reserve(&spend("b", &amounts), 20).unwrap();
+        assert!(matches!(
+            engine.reserve(&spend("c", &amounts), 30),
+            Err(PolicyViolation::AssetCap { .. })
+        ));
+
+        engine.release(id);
+        engine.reserve(&spend("c", &amounts), 30).unwrap();
+
+        // the first reservations leave the window
+        engine.reserve(&spend("a", &amounts), 135).unwrap();
+    }
+
+    #[test]
+    fn test_ledger_survives_restart() {
+        let dir = crate::persist::temp_dir();
+        let file = || JsonFile::new(&dir, "ledger-31337.json").unwrap();
+        let amounts = [U256::from(450), U256::from(450)];
+
+        let engine = PolicyEngine::load(config(), file()).unwrap();
+        engine.reserve(&spend("a", &amounts), 10).unwrap();
+        let id = engine.reserve(&spend("b", &amounts), 10).unwrap().id;
+
+        let engine = PolicyEngine::load(config(), file()).unwrap();
+        assert!(matches!(
+            engine.reserve(&spend("c", &amounts), 20),
+            Err(PolicyViolation::AssetCap { .. })
+        ));
+
+        // reservations of held jobs can still be released after a restart
+        engine.release(id);
+        let engine = PolicyEngine::load(config(), file()).unwrap();
+        let reservation = engine.reserve(&spend("c", &amounts), 20).unwrap();
+        assert!(reservation.id > id);
+    }
+
+    #[test]
+    fn test_concurrent_reservations_respect_caps() {
+        let engine = engine();
+        let amounts = [U256::from(300)];
+        let principals: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+
+        let reserved = std::thread::scope(|scope| {
+            let handles: Vec<_> = principals
+                .iter()
+                .map(|principal| {
+                    let engine = &engine;
+                    let amounts = &amounts;
+                    scope.spawn(move || engine.reserve(&spend(principal, amounts), 10).is_ok())
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .filter(|&reserved| reserved)
+                .count()
+        });
+
+        // the asset cap of 2000 fits six payouts of 300
+        assert_eq!(reserved, 6);
+    }
+}
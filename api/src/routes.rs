@@ -1,18 +1,244 @@
-use crate::handlers::{collect::*, disperse::*};
+use crate::auth::{authorize, RequiredScope};
+use crate::handlers::{
+    allowances::*, collect::*, deposits::*, disperse::*, health::*, jobs::*, metrics::*, nft::*,
+    planner::*, vaults::*,
+};
 use crate::state::AppState;
-use axum::{routing::post, Router};
+use axum::{
+    middleware::from_fn_with_state,
+    routing::{delete, get, patch, post, put},
+    Router,
+};
 use std::sync::Arc;
 
 pub fn collect_routes(state: Arc<AppState>) -> Router {
+    let keys = state.api_keys();
+
     Router::new()
-        .route("/eth", post(collect_eth_handler))
-        .route("/erc20", post(collect_erc20_handler))
+        .route(
+            "/eth",
+            post(collect_eth_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "collect:eth"),
+                authorize,
+            )),
+        )
+        .route(
+            "/erc20",
+            post(collect_erc20_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "collect:erc20"),
+                authorize,
+            )),
+        )
+        .route(
+            "/erc20/permit",
+            post(collect_erc20_permit_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "collect:erc20"),
+                authorize,
+            )),
+        )
+        .route(
+            "/erc20/permit2",
+            post(collect_erc20_permit2_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "collect:erc20"),
+                authorize,
+            )),
+        )
+        .route(
+            "/plan",
+            post(plan_collect_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "collect:plan"),
+                authorize,
+            )),
+        )
         .with_state(state)
 }
 
 pub fn disperse_routes(state: Arc<AppState>) -> Router {
+    let keys = state.api_keys();
+
+    Router::new()
+        .route(
+            "/eth",
+            post(disperse_eth_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "disperse:eth").allow_signed(),
+                authorize,
+            )),
+        )
+        .route(
+            "/erc20",
+            post(disperse_erc20_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "disperse:erc20").allow_signed(),
+                authorize,
+            )),
+        )
+        .route(
+            "/multi",
+            post(disperse_multi_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "disperse:multi"),
+                authorize,
+            )),
+        )
+        .route(
+            "/erc721",
+            post(disperse_erc721_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "disperse:erc721"),
+                authorize,
+            )),
+        )
+        .route(
+            "/erc1155",
+            post(disperse_erc1155_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "disperse:erc1155"),
+                authorize,
+            )),
+        )
+        .with_state(state)
+}
+
+pub fn allowances_routes(state: Arc<AppState>) -> Router {
+    let keys = state.api_keys();
+
+    Router::new()
+        .route(
+            "/:token",
+            get(get_allowance_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "allowances:read"),
+                authorize,
+            )),
+        )
+        .route(
+            "/:token",
+            put(set_allowance_handler)
+                .delete(revoke_allowance_handler)
+                .route_layer(from_fn_with_state(
+                    RequiredScope::new(keys.clone(), "allowances:write"),
+                    authorize,
+                )),
+        )
+        .with_state(state)
+}
+
+pub fn vaults_routes(state: Arc<AppState>) -> Router {
+    let keys = state.api_keys();
+
+    Router::new()
+        .route(
+            "/",
+            get(list_vaults_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "vaults:read"),
+                authorize,
+            )),
+        )
+        .route(
+            "/",
+            post(create_vault_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "vaults:write"),
+                authorize,
+            )),
+        )
+        .route(
+            "/deposit-address",
+            post(deposit_address_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "vaults:write"),
+                authorize,
+            )),
+        )
+        .route(
+            "/:address",
+            patch(update_vault_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "vaults:write"),
+                authorize,
+            )),
+        )
+        .route(
+            "/:address/retire",
+            post(retire_vault_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "vaults:write"),
+                authorize,
+            )),
+        )
+        .with_state(state)
+}
+
+pub fn deposits_routes(state: Arc<AppState>) -> Router {
+    let keys = state.api_keys();
+
+    Router::new()
+        .route(
+            "/",
+            get(list_deposits_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "deposits:read"),
+                authorize,
+            )),
+        )
+        .with_state(state)
+}
+
+pub fn jobs_routes(state: Arc<AppState>) -> Router {
+    let keys = state.api_keys();
+
+    Router::new()
+        .route(
+            "/",
+            get(list_jobs_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "history:read"),
+                authorize,
+            )),
+        )
+        .route(
+            "/:id",
+            get(get_job_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "history:read"),
+                authorize,
+            )),
+        )
+        .route(
+            "/:id",
+            delete(cancel_job_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "jobs:broadcast"),
+                authorize,
+            )),
+        )
+        .route(
+            "/:id/approve",
+            post(approve_job_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "jobs:approve"),
+                authorize,
+            )),
+        )
+        .route(
+            "/:id/reject",
+            post(reject_job_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "jobs:approve"),
+                authorize,
+            )),
+        )
+        .with_state(state)
+}
+
+pub fn broadcast_routes(state: Arc<AppState>) -> Router {
+    let keys = state.api_keys();
+
+    Router::new()
+        .route(
+            "/broadcast",
+            post(broadcast_handler).route_layer(from_fn_with_state(
+                RequiredScope::new(keys.clone(), "jobs:broadcast"),
+                authorize,
+            )),
+        )
+        .with_state(state)
+}
+
+pub fn health_routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz_handler))
+        .route("/readyz", get(readyz_handler))
+        .with_state(state)
+}
+
+pub fn metrics_routes(state: Arc<AppState>) -> Router {
     Router::new()
-        .route("/eth", post(disperse_eth_handler))
-        .route("/erc20", post(disperse_erc20_handler))
+        .route("/metrics", get(metrics_handler))
         .with_state(state)
 }
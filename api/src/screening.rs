@@ -0,0 +1,132 @@
+use ethers::types::Address;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::SystemTime;
+use tracing::{info, warn};
+
+/// Addresses that must never receive or send funds, loaded from a file
+///
+/// The file holds one address per line, `#` starts a comment. It is reloaded when its
+/// modification time changes, so it can be updated without a restart.
+#[derive(Default)]
+pub struct Blocklist {
+    path: Option<PathBuf>,
+    loaded: RwLock<Loaded>,
+}
+
+#[derive(Default)]
+struct Loaded {
+    modified: Option<SystemTime>,
+    addresses: HashSet<Address>,
+}
+
+impl Blocklist {
+    pub fn load(path: Option<PathBuf>) -> anyhow::Result<Self> {
+        let blocklist = Self {
+            path,
+            loaded: RwLock::new(Loaded::default()),
+        };
+
+        if let Some(path) = &blocklist.path {
+            let modified = fs::metadata(path)?.modified().ok();
+            let addresses = parse(&fs::read_to_string(path)?)?;
+            info!(count = addresses.len(), path = %path.display(), "Blocklist loaded");
+            *blocklist.loaded.write().unwrap() = Loaded {
+                modified,
+                addresses,
+            };
+        }
+
+        Ok(blocklist)
+    }
+
+    /// Returns the distinct blocked addresses among `addresses`, in request order
+    pub fn blocked(&self, addresses: &[Address]) -> Vec<Address> {
+        self.reload_if_changed();
+
+        let loaded = self.loaded.read().unwrap();
+        let mut seen = HashSet::new();
+        addresses
+            .iter()
+            .filter(|address| loaded.addresses.contains(address) && seen.insert(**address))
+            .copied()
+            .collect()
+    }
+
+    fn reload_if_changed(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+        let modified = match modified {
+            Ok(modified) => modified,
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Blocklist unavailable, keeping the loaded one");
+                return;
+            }
+        };
+        if self.loaded.read().unwrap().modified == Some(modified) {
+            return;
+        }
+
+        let parsed = fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| parse(&content));
+        match parsed {
+            Ok(addresses) => {
+                info!(count = addresses.len(), path = %path.display(), "Blocklist reloaded");
+                *self.loaded.write().unwrap() = Loaded {
+                    modified: Some(modified),
+                    addresses,
+                };
+            }
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Invalid blocklist, keeping the loaded one")
+            }
+        }
+    }
+}
+
+fn parse(content: &str) -> anyhow::Result<HashSet<Address>> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            line.parse::<Address>()
+                .map_err(|_| anyhow::anyhow!("Invalid blocklist address {}", line))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_blocklist() {
+        let path = std::env::temp_dir().join(format!("blocklist-{}.txt", uuid::Uuid::new_v4()));
+        let first = Address::repeat_byte(1);
+        let second = Address::repeat_byte(2);
+
+        fs::write(&path, format!("# sanctioned\n{:?}\n\n", first)).unwrap();
+        let blocklist = Blocklist::load(Some(path.clone())).unwrap();
+        assert_eq!(blocklist.blocked(&[first, second, first]), vec![first]);
+
+        fs::write(&path, format!("{:?} # moved\n", second)).unwrap();
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + std::time::Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(blocklist.blocked(&[first, second]), vec![second]);
+
+        fs::write(&path, "not an address\n").unwrap();
+        file.set_modified(SystemTime::now() + std::time::Duration::from_secs(20))
+            .unwrap();
+        assert_eq!(blocklist.blocked(&[second]), vec![second]);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Bytes};
+use ethers::utils::rlp::Rlp;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum SignedTransactionError {
+    Invalid(String),
+    /// Fields of the signed transaction that differ from the prepared one
+    Mismatch(Vec<&'static str>),
+    WrongSigner {
+        expected: Address,
+        signer: Address,
+    },
+}
+
+impl fmt::Display for SignedTransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(e) => write!(f, "Invalid signed transaction: {}", e),
+            Self::Mismatch(fields) => write!(
+                f,
+                "Signed transaction differs from the prepared one in {}",
+                fields.join(", ")
+            ),
+            Self::WrongSigner { expected, signer } => write!(
+                f,
+                "Transaction is signed by {:?}, expected {:?}",
+                signer, expected
+            ),
+        }
+    }
+}
+
+/// Checks `raw` is `prepared` signed by its `from`, returning the decoded transaction
+pub fn verify_signed(
+    prepared: &TypedTransaction,
+    raw: &Bytes,
+) -> Result<TypedTransaction, SignedTransactionError> {
+    let (signed, signature) = TypedTransaction::decode_signed(&Rlp::new(raw))
+        .map_err(|e| SignedTransactionError::Invalid(e.to_string()))?;
+
+    let max_priority_fee_per_gas = |tx: &TypedTransaction| {
+        tx.as_eip1559_ref()
+            .and_then(|tx| tx.max_priority_fee_per_gas)
+    };
+    let fields = [
+        (
+            "type",
+            std::mem::discriminant(prepared) != std::mem::discriminant(&signed),
+        ),
+        ("to", prepared.to() != signed.to()),
+        ("data", prepared.data() != signed.data()),
+        ("value", prepared.value() != signed.value()),
+        ("nonce", prepared.nonce() != signed.nonce()),
+        ("gas", prepared.gas() != signed.gas()),
+        ("fees", prepared.gas_price() != signed.gas_price()),
+        (
+            "priority fee",
+            max_priority_fee_per_gas(prepared) != max_priority_fee_per_gas(&signed),
+        ),
+        ("chain id", prepared.chain_id() != signed.chain_id()),
+    ];
+    let mut mismatched: Vec<&'static str> = fields
+        .into_iter()
+        .filter_map(|(field, differs)| differs.then_some(field))
+        .collect();
+    // e.g. an access list the signer added
+    if mismatched.is_empty() && prepared.sighash() != signed.sighash() {
+        mismatched.push("other fields");
+    }
+    if !mismatched.is_empty() {
+        return Err(SignedTransactionError::Mismatch(mismatched));
+    }
+
+    let signer = signature
+        .recover(signed.sighash())
+        .map_err(|e| SignedTransactionError::Invalid(e.to_string()))?;
+    let expected = prepared.from().copied().unwrap_or_default();
+    if signer != expected {
+        return Err(SignedTransactionError::WrongSigner { expected, signer });
+    }
+
+    Ok(signed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::core::rand::thread_rng;
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::Eip1559TransactionRequest;
+
+    fn prepared(from: Address) -> TypedTransaction {
+        Eip1559TransactionRequest::new()
+            .from(from)
+            .to(Address::repeat_byte(1))
+            .data(vec![0xde, 0xad])
+            .value(100)
+            .nonce(7)
+            .gas(60_000)
+            .max_fee_per_gas(30_000_000_000u64)
+            .max_priority_fee_per_gas(1_000_000_000u64)
+            .chain_id(31337)
+            .into()
+    }
+
+    fn sign(wallet: &LocalWallet, tx: &TypedTransaction) -> Bytes {
+        let signature = wallet.sign_transaction_sync(tx).unwrap();
+        tx.rlp_signed(&signature)
+    }
+
+    #[test]
+    fn test_verify_signed() {
+        let wallet = LocalWallet::new(&mut thread_rng()).with_chain_id(31337u64);
+        let tx = prepared(wallet.address());
+
+        assert!(verify_signed(&tx, &sign(&wallet, &tx)).is_ok());
+
+        let mut tampered = tx.clone();
+        tampered.set_nonce(8).set_gas(21_000);
+        assert_eq!(
+            verify_signed(&tx, &sign(&wallet, &tampered)).unwrap_err(),
+            SignedTransactionError::Mismatch(vec!["nonce", "gas"])
+        );
+
+        let other = LocalWallet::new(&mut thread_rng()).with_chain_id(31337u64);
+        assert!(matches!(
+            verify_signed(&tx, &sign(&other, &tx)).unwrap_err(),
+            SignedTransactionError::WrongSigner { signer, .. } if signer == other.address()
+        ));
+
+        assert!(matches!(
+            verify_signed(&tx, &Bytes::from(vec![0x02, 0x01])).unwrap_err(),
+            SignedTransactionError::Invalid(_)
+        ));
+    }
+}
@@ -1,19 +1,279 @@
-use crate::config::AppConfig;
+use crate::auth::ApiKeys;
+use crate::config::{
+    AppConfig, AutoSweepConfig, ChainConfig, ChainLimits, DepositWatchConfig, FeePolicy,
+    ReadinessConfig,
+};
+use crate::deposits::DepositStore;
+use crate::eip712::Approvers;
+use crate::handlers::collect::Collect;
+use crate::handlers::disperse::Disperse;
+use crate::handlers::nft::CollectionApprovals;
+use crate::jobs::JobStore;
+use crate::metrics::{MeteredHttp, Metrics};
+use crate::persist::JsonFile;
+use crate::policy::{parse_asset, Asset, PolicyEngine};
+use crate::screening::Blocklist;
+use crate::vaults::VaultRegistry;
+use anyhow::{anyhow, bail};
 use derive_getters::Getters;
-use ethers_providers::{Http, Provider};
-use std::{convert::TryFrom, sync::Arc, time::Duration};
+use ethers::abi::Detokenize;
+use ethers::prelude::*;
+use std::{collections::HashMap, path::Path, str::FromStr, sync::Arc, time::Duration};
+use url::Url;
+
+pub type Client = SignerMiddleware<Provider<MeteredHttp>, LocalWallet>;
+
+const PERMIT2_ADDRESS: &str = "0x000000000022D473030F116dDEE9F6B43aC78BA3";
+/// Chain id of Anvil and Hardhat nodes
+const DEV_CHAIN_ID: u64 = 31337;
 
 #[derive(Clone, Getters)]
 pub struct AppState {
-    provider: Provider<Http>,
+    chains: HashMap<u64, ChainContext>,
+    default_chain_id: u64,
+    metrics: Arc<Metrics>,
+    api_keys: Arc<ApiKeys>,
+    approvers: Arc<Approvers>,
+    jobs: Arc<JobStore>,
+    blocklist: Arc<Blocklist>,
+    vaults: Arc<VaultRegistry>,
+    deposits: Arc<DepositStore>,
+    deposit_webhook_url: Option<Url>,
+}
+
+/// Provider, signer and contract set of a single network
+#[derive(Clone, Getters)]
+pub struct ChainContext {
+    chain_id: u64,
+    provider: Provider<MeteredHttp>,
+    wallet: LocalWallet,
+    client: Arc<Client>,
+    disperse_contract: Disperse<Client>,
+    collect_contract: Collect<Client>,
+    permit2: Address,
+    limits: ChainLimits,
+    fee_policy: FeePolicy,
+    readiness: ReadinessConfig,
+    spend_policy: Arc<PolicyEngine>,
+    just_in_time_approval: bool,
+    collection_approvals: Arc<CollectionApprovals>,
+    deposits: DepositWatchConfig,
+    auto_sweep: AutoSweepConfig,
+    /// `auto_sweep.thresholds` parsed at startup
+    sweep_thresholds: Vec<(Asset, U256)>,
+    metrics: Arc<Metrics>,
 }
 
 impl AppState {
     pub async fn init(config: AppConfig) -> anyhow::Result<Arc<Self>> {
-        let provider = Provider::<Http>::try_from(config.rpc_url)
-            .unwrap()
-            .interval(Duration::from_millis(10u64));
+        let metrics = Arc::new(Metrics::new()?);
+        let mut chains = HashMap::new();
+        let mut first_chain_id = None;
+
+        for chain_config in config.chain_configs() {
+            let chain = ChainContext::init(
+                chain_config,
+                config.private_key.as_deref(),
+                &config.data_dir,
+                metrics.clone(),
+            )
+            .await?;
+            if chains.contains_key(&chain.chain_id) {
+                bail!("Chain {} is configured more than once", chain.chain_id);
+            }
+
+            first_chain_id.get_or_insert(chain.chain_id);
+            chains.insert(chain.chain_id, chain);
+        }
+
+        let default_chain_id = config
+            .default_chain_id
+            .or(first_chain_id)
+            .ok_or_else(|| anyhow!("No chains configured"))?;
+        if !chains.contains_key(&default_chain_id) {
+            bail!("Default chain {} is not configured", default_chain_id);
+        }
 
-        Ok(Self { provider }.into())
+        if config.api_keys.is_empty() && !config.allow_unauthenticated {
+            bail!("No API keys configured, set allow_unauthenticated to serve payout routes without authentication");
+        }
+        // held payouts are approved with an API key, without keys they could never be released
+        let second_approvals = config.chain_configs().iter().any(|chain| {
+            chain
+                .spend_policy
+                .assets
+                .values()
+                .any(|policy| policy.second_approval_above.is_some())
+        });
+        let api_keys = Arc::new(ApiKeys::new(config.api_keys, config.allow_unauthenticated));
+        if second_approvals && !api_keys.is_enabled() {
+            bail!("second_approval_above requires API keys to approve held payouts");
+        }
+        let approvers = Arc::new(Approvers::load(
+            config.approvers,
+            JsonFile::new(&config.data_dir, "nonces.json")?,
+        )?);
+        let jobs = Arc::new(JobStore::load(
+            JsonFile::new(&config.data_dir, "jobs.json")?,
+            metrics.clone(),
+        )?);
+        let blocklist = Arc::new(Blocklist::load(config.blocklist_path)?);
+        let vaults = Arc::new(VaultRegistry::load(JsonFile::new(
+            &config.data_dir,
+            "vaults.json",
+        )?)?);
+        let deposits = Arc::new(DepositStore::load(JsonFile::new(
+            &config.data_dir,
+            "deposits.json",
+        )?)?);
+
+        Ok(Self {
+            chains,
+            default_chain_id,
+            metrics,
+            api_keys,
+            approvers,
+            jobs,
+            blocklist,
+            vaults,
+            deposits,
+            deposit_webhook_url: config.deposit_webhook_url,
+        }
+        .into())
+    }
+
+    /// Resolves the chain a request is routed to, `None` selects the default chain
+    pub fn chain(&self, chain_id: Option<u64>) -> Option<&ChainContext> {
+        self.chains.get(&chain_id.unwrap_or(self.default_chain_id))
     }
 }
+
+impl ChainContext {
+    async fn init(
+        config: ChainConfig,
+        default_private_key: Option<&str>,
+        data_dir: &Path,
+        metrics: Arc<Metrics>,
+    ) -> anyhow::Result<Self> {
+        let transport = MeteredHttp::new(Http::from_str(&config.rpc_url)?, metrics.clone());
+        let provider = Provider::new(transport).interval(Duration::from_millis(10u64));
+
+        let chain_id = provider.get_chainid().await?.as_u64();
+        if let Some(expected) = config.chain_id {
+            if expected != chain_id {
+                bail!(
+                    "RPC {} reports chain id {}, expected {}",
+                    config.rpc_url,
+                    chain_id,
+                    expected
+                );
+            }
+        }
+
+        let private_key = config
+            .private_key
+            .as_deref()
+            .or(default_private_key)
+            .ok_or_else(|| anyhow!("No private key configured for chain {}", chain_id))?;
+        let wallet = private_key.parse::<LocalWallet>()?.with_chain_id(chain_id);
+
+        let client = Arc::new(SignerMiddleware::new(provider.clone(), wallet.clone()));
+
+        let spend_policy = Arc::new(PolicyEngine::load(
+            config.spend_policy,
+            JsonFile::new(data_dir, &format!("ledger-{}.json", chain_id))?,
+        )?);
+        let mut sweep_thresholds = Vec::new();
+        for (key, threshold) in &config.auto_sweep.thresholds {
+            let asset =
+                parse_asset(key).ok_or_else(|| anyhow!("Invalid auto-sweep asset {}", key))?;
+            sweep_thresholds.push((asset, threshold.0));
+        }
+
+        let disperse_contract = match config.disperse_address {
+            Some(address) => Disperse::new(address, client.clone()),
+            None => Disperse::deploy(client.clone(), ())?.send().await?,
+        };
+        let collect_contract = match config.collect_address {
+            Some(address) => Collect::new(address, client.clone()),
+            None => {
+                let contract = Collect::deploy(client.clone(), ())?.send().await?;
+                contract.create_withrawal_contracts().send().await?.await?;
+                contract
+            }
+        };
+
+        Ok(Self {
+            chain_id,
+            provider,
+            wallet,
+            client,
+            disperse_contract,
+            collect_contract,
+            permit2: config
+                .permit2_address
+                .unwrap_or_else(|| PERMIT2_ADDRESS.parse().unwrap()),
+            limits: config.limits,
+            fee_policy: config.fee_policy,
+            readiness: config.readiness,
+            spend_policy,
+            just_in_time_approval: config.just_in_time_approval,
+            collection_approvals: Arc::default(),
+            deposits: config.deposits,
+            auto_sweep: config.auto_sweep,
+            sweep_thresholds,
+            metrics,
+        })
+    }
+
+    /// Local development node, where ERC20 disperses without a token deploy a test token
+    pub fn is_dev(&self) -> bool {
+        self.chain_id == DEV_CHAIN_ID
+    }
+
+    /// Applies the chain fee policy to a contract call
+    pub fn prepare<D: Detokenize>(&self, call: ContractCall<Client, D>) -> ContractCall<Client, D> {
+        let mut call = if self.fee_policy.legacy {
+            call.legacy()
+        } else {
+            call
+        };
+
+        if let Some(max_fee_per_gas) = self.fee_policy.max_fee_per_gas {
+            call = call.gas_price(max_fee_per_gas);
+        }
+        if let Some(max_priority_fee_per_gas) = self.fee_policy.max_priority_fee_per_gas {
+            if let Some(tx) = call.tx.as_eip1559_mut() {
+                tx.max_priority_fee_per_gas = Some(max_priority_fee_per_gas.into());
+            }
+        }
+
+        call
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn test_config(anvil: &ethers::utils::AnvilInstance) -> AppConfig {
+    AppConfig {
+        port: 0,
+        rpc_url: Some(anvil.endpoint()),
+        private_key: Some(hex::encode(anvil.keys()[0].to_bytes())),
+        default_chain_id: None,
+        chains: Vec::new(),
+        log_format: Default::default(),
+        log_level: None,
+        api_keys: Vec::new(),
+        allow_unauthenticated: true,
+        approvers: Vec::new(),
+        blocklist_path: None,
+        data_dir: crate::persist::temp_dir(),
+        deposit_webhook_url: None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) async fn test_state() -> (ethers::utils::AnvilInstance, Arc<AppState>) {
+    let anvil = ethers::utils::Anvil::new().spawn();
+    let state = AppState::init(test_config(&anvil)).await.unwrap();
+    (anvil, state)
+}
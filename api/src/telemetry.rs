@@ -0,0 +1,36 @@
+use crate::config::{AppConfig, LogFormat};
+use axum::extract::Request;
+use tracing::{field, info_span, Span};
+use tracing_subscriber::EnvFilter;
+
+/// Installs the global subscriber, `RUST_LOG` takes precedence over the configured level
+pub fn init_tracing(config: &AppConfig) -> anyhow::Result<()> {
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(config.log_level.as_deref().unwrap_or("info")))?;
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match config.log_format {
+        LogFormat::Pretty => builder.pretty().try_init(),
+        LogFormat::Json => builder.json().flatten_event(true).try_init(),
+    }
+    .map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Root span of a request, `api_key`, `job_id` and `tx_hash` are recorded on it later
+pub fn make_request_span(request: &Request) -> Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    info_span!(
+        "request",
+        request_id,
+        method = %request.method(),
+        uri = %request.uri(),
+        api_key = field::Empty,
+        job_id = field::Empty,
+        tx_hash = field::Empty,
+    )
+}
@@ -1,7 +1,15 @@
-use serde::{Deserialize, Serialize};
+use crate::config::Amount;
+use crate::deposits::DepositStatus;
+use crate::planner::PlanStrategy;
+use crate::vaults::Vault;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Bytes, H256, U256};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
 
-#[derive(Deserialize, Serialize)]
+#[derive(Default, Deserialize, Serialize)]
 pub enum ValuesType {
+    #[default]
     Amount,
     Percentage,
 }
@@ -11,11 +19,439 @@ pub struct DisperseRequest {
     pub values: Vec<u128>,
     pub total_amount: Option<u128>,
     pub values_type: ValuesType,
+    pub chain_id: Option<u64>,
+    /// Generated when omitted, required for signed requests
+    pub recipients: Option<Vec<Address>>,
+    /// ERC20 token to disperse, a test token is deployed when omitted on a local dev chain
+    pub token: Option<Address>,
+    pub authorization: Option<PayoutAuthorization>,
+    /// Permit2 signature of the token owner, who funds the payout instead of the operator
+    pub permit2: Option<Permit2Signature>,
+    #[serde(default)]
+    pub duplicates: DuplicateRecipients,
+    #[serde(default)]
+    pub zero_amounts: ZeroAmounts,
+    /// Returns the transaction for `from` to sign offline instead of sending it
+    #[serde(default)]
+    pub unsigned: bool,
+    /// Signer of an unsigned transaction and ERC20 sender, defaults to the operator
+    pub from: Option<Address>,
+}
+
+/// Handling of recipients listed more than once
+#[derive(Clone, Copy, Default, Deserialize)]
+pub enum DuplicateRecipients {
+    #[default]
+    Reject,
+    /// Sums the amounts into the first occurrence
+    Merge,
+}
+
+/// Handling of recipients with a zero amount
+#[derive(Clone, Copy, Default, Deserialize)]
+pub enum ZeroAmounts {
+    #[default]
+    Reject,
+    Drop,
+}
+
+/// Recipient and amount as they are paid after normalization
+#[derive(Serialize)]
+pub struct Payment {
+    pub wallet: Address,
+    #[serde(serialize_with = "serialize_decimal")]
+    pub amount: U256,
+}
+
+#[derive(Serialize)]
+pub struct DisperseResponse {
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub payments: Vec<Payment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<UnsignedTransaction>,
+}
+
+/// Payout built for offline signing, sent with `POST /broadcast` once signed
+#[derive(Serialize)]
+pub struct UnsignedTransaction {
+    pub job_id: String,
+    pub transaction: TypedTransaction,
+    /// RLP encoding of the unsigned transaction (EIP-2718 typed for EIP-1559), the signed payload
+    pub rlp: Bytes,
 }
 
 #[derive(Deserialize)]
+pub struct BroadcastRequest {
+    pub job_id: String,
+    pub raw_transaction: Bytes,
+}
+
+/// ETH and several tokens paid out in a single transaction
+#[derive(Deserialize)]
+pub struct MultiDisperseRequest {
+    pub chain_id: Option<u64>,
+    pub legs: Vec<DisperseLeg>,
+    #[serde(default)]
+    pub duplicates: DuplicateRecipients,
+    #[serde(default)]
+    pub zero_amounts: ZeroAmounts,
+}
+
+/// Payments of one asset, ETH when `token` is omitted
+#[derive(Deserialize)]
+pub struct DisperseLeg {
+    pub token: Option<Address>,
+    pub recipients: Vec<Address>,
+    pub values: Vec<u128>,
+}
+
+#[derive(Serialize)]
+pub struct MultiDisperseResponse {
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub legs: Vec<LegResult>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LegStatus {
+    Paid,
+    Failed,
+    /// Not sent because another leg failed its preflight
+    Skipped,
+}
+
+#[derive(Serialize)]
+pub struct LegResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<Address>,
+    #[serde(serialize_with = "serialize_decimal")]
+    pub total: U256,
+    pub payments: Vec<Payment>,
+    pub status: LegStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// ERC-721 tokens of one collection sent out of the operator's wallet
+#[derive(Deserialize)]
+pub struct Erc721DisperseRequest {
+    pub chain_id: Option<u64>,
+    pub token: Address,
+    pub transfers: Vec<Erc721Entry>,
+}
+
+#[derive(Deserialize)]
+pub struct Erc721Entry {
+    pub recipient: Address,
+    #[serde(rename = "tokenId")]
+    pub token_id: Amount,
+}
+
+/// ERC-1155 tokens of one contract sent out of the operator's wallet
+#[derive(Deserialize)]
+pub struct Erc1155DisperseRequest {
+    pub chain_id: Option<u64>,
+    pub token: Address,
+    pub transfers: Vec<Erc1155Entry>,
+}
+
+#[derive(Deserialize)]
+pub struct Erc1155Entry {
+    pub recipient: Address,
+    pub id: Amount,
+    pub amount: Amount,
+}
+
+/// EIP-712 signature over the canonical payout, an alternative to API keys
+#[derive(Deserialize)]
+pub struct PayoutAuthorization {
+    pub nonce: u64,
+    /// Unix timestamp after which the signature is rejected
+    pub deadline: u64,
+    pub signature: String,
+}
+
+#[derive(Deserialize, Serialize)]
 pub struct CollectRequest {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub values: Vec<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub total_amount: Option<u128>,
+    #[serde(default, skip_serializing)]
     pub values_type: ValuesType,
+    pub chain_id: Option<u64>,
+    /// ERC20 token to collect, required with `entries`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<Address>,
+    /// Defaults to the operator, or `from` for unsigned requests
+    pub receiver: Option<Address>,
+    /// Senders and amounts to collect instead of `values`, each sender approved the Collect contract
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entries: Option<Vec<CollectEntry>>,
+    /// Vaults to sweep into `receiver` instead of `values`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vaults: Option<Vec<VaultSweep>>,
+    /// EIP-3009 transfers signed over to `receiver` to collect instead of `values`, no approvals needed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorizations: Option<Vec<SignedTransferAuthorization>>,
+    /// Returns the transaction for `from` (the Collect owner) to sign offline instead of sending it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unsigned: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<Address>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct VaultSweep {
+    /// Vault address or label
+    pub vault: String,
+    pub amount: SweepAmount,
+}
+
+/// Wei amount, or `"all"` for the vault's whole balance
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SweepAmount {
+    Exact(U256),
+    All,
+}
+
+impl<'de> Deserialize<'de> for SweepAmount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Integer(u64),
+            Text(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Integer(value) => Ok(SweepAmount::Exact(value.into())),
+            Raw::Text(value) if value == "all" => Ok(SweepAmount::All),
+            Raw::Text(value) => U256::from_dec_str(&value)
+                .map(SweepAmount::Exact)
+                .map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+impl Serialize for SweepAmount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            SweepAmount::Exact(amount) => serializer.collect_str(amount),
+            SweepAmount::All => serializer.serialize_str("all"),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CollectEntry {
+    pub sender: Address,
+    pub amount: u128,
+}
+
+/// Target amount of ETH (from vaults) or of `token` (from `senders`) to gather
+#[derive(Deserialize)]
+pub struct PlanCollectRequest {
+    pub chain_id: Option<u64>,
+    pub token: Option<Address>,
+    pub target: Amount,
+    #[serde(default)]
+    pub strategy: PlanStrategy,
+    /// Wallets that approved the Collect contract, required with `token`
+    #[serde(default)]
+    pub senders: Vec<Address>,
+    pub receiver: Option<Address>,
+    /// Estimate the gas of the planned collect, which fails if it would revert
+    #[serde(default)]
+    pub simulate: bool,
+}
+
+#[derive(Serialize)]
+pub struct CollectPlan {
+    pub strategy: PlanStrategy,
+    #[serde(serialize_with = "serialize_decimal")]
+    pub target: U256,
+    #[serde(serialize_with = "serialize_decimal")]
+    pub gas_price: U256,
+    /// Sources skipped because pulling them costs more gas than they hold
+    pub skipped: Vec<Address>,
+    /// Body for `POST /collect/eth` or `/collect/erc20`
+    pub request: CollectRequest,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub simulation: Option<Simulation>,
+}
+
+#[derive(Serialize)]
+pub struct Simulation {
+    pub succeeded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Collects the permitted values from each owner, the receiver defaults to the operator
+#[derive(Deserialize)]
+pub struct PermitCollectRequest {
+    pub chain_id: Option<u64>,
+    pub token: Address,
+    pub receiver: Option<Address>,
+    pub permits: Vec<SignedPermit>,
+    /// Returns the transaction for `from` (the Collect owner) to sign offline instead of sending it
+    #[serde(default)]
+    pub unsigned: bool,
+    pub from: Option<Address>,
+}
+
+/// EIP-2612 permit signed by a sender for the Collect contract
+#[derive(Deserialize)]
+pub struct SignedPermit {
+    pub owner: Address,
+    pub value: u128,
+    pub deadline: u64,
+    pub signature: String,
+}
+
+/// EIP-3009 `TransferWithAuthorization` signed by `from` with the receiver as `to`
+#[derive(Deserialize, Serialize)]
+pub struct SignedTransferAuthorization {
+    pub from: Address,
+    pub value: u128,
+    pub valid_after: u64,
+    pub valid_before: u64,
+    pub nonce: H256,
+    pub signature: String,
+}
+
+/// Permit2 `PermitBatchTransferFrom` signature naming the Disperse or Collect contract as spender
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Permit2Signature {
+    pub owner: Address,
+    pub nonce: Amount,
+    pub deadline: u64,
+    pub signature: String,
+}
+
+#[derive(Deserialize)]
+pub struct TokenAmount {
+    pub token: Address,
+    pub amount: u128,
+}
+
+/// Collects every permitted amount of each owner, the receiver defaults to the operator
+#[derive(Deserialize)]
+pub struct Permit2CollectRequest {
+    pub chain_id: Option<u64>,
+    pub receiver: Option<Address>,
+    pub transfers: Vec<Permit2Collect>,
+    /// Returns the transaction for `from` (the Collect owner) to sign offline instead of sending it
+    #[serde(default)]
+    pub unsigned: bool,
+    pub from: Option<Address>,
+}
+
+#[derive(Deserialize)]
+pub struct Permit2Collect {
+    pub permitted: Vec<TokenAmount>,
+    pub authorization: Permit2Signature,
+}
+
+#[derive(Deserialize)]
+pub struct ChainQuery {
+    pub chain_id: Option<u64>,
+}
+
+/// Either an exact `amount` or `unlimited`
+#[derive(Deserialize)]
+pub struct SetAllowanceRequest {
+    pub chain_id: Option<u64>,
+    pub amount: Option<Amount>,
+    #[serde(default)]
+    pub unlimited: bool,
+}
+
+/// Operator allowance of a token to the Disperse contract
+#[derive(Serialize)]
+pub struct AllowanceResponse {
+    pub chain_id: u64,
+    pub token: Address,
+    pub owner: Address,
+    pub spender: Address,
+    #[serde(serialize_with = "serialize_decimal")]
+    pub allowance: U256,
+    pub unlimited: bool,
+}
+
+#[derive(Deserialize)]
+pub struct CreateVaultRequest {
+    pub chain_id: Option<u64>,
+    pub label: Option<String>,
+    pub customer: Option<String>,
+}
+
+/// Requests the deterministic deposit vault of `customer`
+#[derive(Deserialize)]
+pub struct DepositAddressRequest {
+    pub chain_id: Option<u64>,
+    pub customer: String,
+    pub label: Option<String>,
+}
+
+/// Omitted fields are left unchanged
+#[derive(Deserialize)]
+pub struct UpdateVaultRequest {
+    pub chain_id: Option<u64>,
+    pub label: Option<String>,
+    pub customer: Option<String>,
+}
+
+/// Registered vault with its live ETH balance
+#[derive(Serialize)]
+pub struct VaultResponse {
+    #[serde(flatten)]
+    pub vault: Vault,
+    #[serde(serialize_with = "serialize_decimal")]
+    pub balance: U256,
+}
+
+#[derive(Deserialize)]
+pub struct DepositQuery {
+    pub chain_id: Option<u64>,
+    pub vault: Option<Address>,
+    pub status: Option<DepositStatus>,
+}
+
+#[derive(Serialize)]
+pub struct CheckResult {
+    pub ok: bool,
+    pub detail: String,
+}
+
+#[derive(Serialize)]
+pub struct ChainReadiness {
+    pub chain_id: u64,
+    pub ready: bool,
+    pub checks: BTreeMap<&'static str, CheckResult>,
+}
+
+#[derive(Serialize)]
+pub struct ReadinessResponse {
+    pub ready: bool,
+    pub chains: Vec<ChainReadiness>,
+}
+
+pub(crate) fn serialize_decimal<S: Serializer>(
+    amount: &U256,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(amount)
+}
+
+pub(crate) fn deserialize_decimal<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<U256, D::Error> {
+    let amount = String::deserialize(deserializer)?;
+    U256::from_dec_str(&amount).map_err(serde::de::Error::custom)
 }
@@ -0,0 +1,373 @@
+use crate::error::ApiError;
+use crate::persist::JsonFile;
+use axum::http::StatusCode;
+use ethers::types::{Address, H256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultStatus {
+    /// CREATE2 address handed out, deployed on the first sweep
+    Pending,
+    Active,
+    Retired,
+}
+
+/// Withdrawal contract of the Collect contract, `index` is its position in `withdrawalContracts`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Vault {
+    pub chain_id: u64,
+    pub address: Address,
+    pub index: Option<usize>,
+    /// CREATE2 salt of deterministic vaults
+    pub salt: Option<H256>,
+    pub label: Option<String>,
+    /// Customer the vault is assigned to
+    pub customer: Option<String>,
+    pub status: VaultStatus,
+    pub created_at: u64,
+    pub retired_at: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum VaultError {
+    NotFound,
+    Retired,
+    LabelTaken(String),
+    /// The change couldn't be written to the registry file
+    Storage,
+}
+
+/// Registry of the withdrawal contracts of every chain
+///
+/// Vaults are written to `file` so that pending addresses, labels, customers and retirements
+/// survive a restart instead of coming back as active vaults from the Collect contract.
+#[derive(Default)]
+pub struct VaultRegistry {
+    vaults: Mutex<HashMap<(u64, Address), Vault>>,
+    file: Option<JsonFile>,
+}
+
+impl From<VaultError> for ApiError {
+    fn from(e: VaultError) -> Self {
+        match e {
+            VaultError::NotFound => ApiError::not_found("Vault not found"),
+            VaultError::Retired => ApiError::conflict("Vault is retired"),
+            VaultError::LabelTaken(label) => {
+                ApiError::conflict(format!("Label {} is already used", label))
+            }
+            VaultError::Storage => ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to record the vault",
+            ),
+        }
+    }
+}
+
+impl VaultRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restores the vaults registered before a restart from `file` and records changes there
+    pub fn load(file: JsonFile) -> anyhow::Result<Self> {
+        let vaults: Vec<Vault> = file.load()?;
+
+        Ok(Self {
+            vaults: Mutex::new(
+                vaults
+                    .into_iter()
+                    .map(|vault| ((vault.chain_id, vault.address), vault))
+                    .collect(),
+            ),
+            file: Some(file),
+        })
+    }
+
+    fn save(&self, vaults: &HashMap<(u64, Address), Vault>) -> Result<(), VaultError> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+
+        let stored: Vec<&Vault> = vaults.values().collect();
+        file.save(&stored).map_err(|e| {
+            error!(error = %e, "Failed to record vaults");
+            VaultError::Storage
+        })
+    }
+
+    /// Inserts `vault` and writes the registry, keeping the previous entry if that fails
+    fn store(
+        &self,
+        vaults: &mut HashMap<(u64, Address), Vault>,
+        vault: Vault,
+    ) -> Result<Vault, VaultError> {
+        let key = (vault.chain_id, vault.address);
+        let previous = vaults.insert(key, vault.clone());
+        if let Err(e) = self.save(vaults) {
+            match previous {
+                Some(previous) => vaults.insert(key, previous),
+                None => vaults.remove(&key),
+            };
+            return Err(e);
+        }
+
+        Ok(vault)
+    }
+
+    /// Registers untracked withdrawal contracts and activates deployed pending ones
+    pub fn sync(&self, chain_id: u64, addresses: &[Address], now: u64) {
+        let mut vaults = self.vaults.lock().unwrap();
+
+        for (index, &address) in addresses.iter().enumerate() {
+            let vault = vaults.entry((chain_id, address)).or_insert(Vault {
+                chain_id,
+                address,
+                index: Some(index),
+                salt: None,
+                label: None,
+                customer: None,
+                status: VaultStatus::Active,
+                created_at: now,
+                retired_at: None,
+            });
+
+            vault.index = Some(index);
+            if vault.status == VaultStatus::Pending {
+                vault.status = VaultStatus::Active;
+            }
+        }
+
+        // a failed write is logged, the next sync rebuilds the same state from the contract
+        let _ = self.save(&vaults);
+    }
+
+    pub fn register(&self, vault: Vault) -> Result<Vault, VaultError> {
+        let mut vaults = self.vaults.lock().unwrap();
+        if let Some(label) = &vault.label {
+            check_label(&vaults, vault.chain_id, vault.address, label)?;
+        }
+
+        self.store(&mut vaults, vault)
+    }
+
+    pub fn get(&self, chain_id: u64, address: Address) -> Option<Vault> {
+        self.vaults
+            .lock()
+            .unwrap()
+            .get(&(chain_id, address))
+            .cloned()
+    }
+
+    pub fn find_by_label(&self, chain_id: u64, label: &str) -> Option<Vault> {
+        self.vaults
+            .lock()
+            .unwrap()
+            .values()
+            .find(|vault| vault.chain_id == chain_id && vault.label.as_deref() == Some(label))
+            .cloned()
+    }
+
+    /// Finds a vault by address or label
+    pub fn resolve(&self, chain_id: u64, vault: &str) -> Option<Vault> {
+        match vault.parse::<Address>() {
+            Ok(address) => self.get(chain_id, address),
+            Err(_) => self.find_by_label(chain_id, vault),
+        }
+    }
+
+    /// Vaults of a chain in contract order, pending ones last
+    pub fn list(&self, chain_id: u64) -> Vec<Vault> {
+        let mut vaults: Vec<Vault> = self
+            .vaults
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|vault| vault.chain_id == chain_id)
+            .cloned()
+            .collect();
+        vaults.sort_by_key(|vault| (vault.index.is_none(), vault.index));
+        vaults
+    }
+
+    /// Pending vaults of a chain
+    pub fn pending(&self, chain_id: u64) -> Vec<Vault> {
+        self.list(chain_id)
+            .into_iter()
+            .filter(|vault| vault.status == VaultStatus::Pending)
+            .collect()
+    }
+
+    /// Sets the label and customer, `None` leaves a field unchanged
+    pub fn update(
+        &self,
+        chain_id: u64,
+        address: Address,
+        label: Option<String>,
+        customer: Option<String>,
+    ) -> Result<Vault, VaultError> {
+        let mut vaults = self.vaults.lock().unwrap();
+        if let Some(label) = &label {
+            check_label(&vaults, chain_id, address, label)?;
+        }
+
+        let mut vault = vaults
+            .get(&(chain_id, address))
+            .cloned()
+            .ok_or(VaultError::NotFound)?;
+        if vault.status == VaultStatus::Retired {
+            return Err(VaultError::Retired);
+        }
+
+        if label.is_some() {
+            vault.label = label;
+        }
+        if customer.is_some() {
+            vault.customer = customer;
+        }
+
+        self.store(&mut vaults, vault)
+    }
+
+    /// Stops using a vault for deposits and collection
+    pub fn retire(&self, chain_id: u64, address: Address, now: u64) -> Result<Vault, VaultError> {
+        let mut vaults = self.vaults.lock().unwrap();
+        let mut vault = vaults
+            .get(&(chain_id, address))
+            .cloned()
+            .ok_or(VaultError::NotFound)?;
+        if vault.status == VaultStatus::Retired {
+            return Err(VaultError::Retired);
+        }
+
+        vault.status = VaultStatus::Retired;
+        vault.retired_at = Some(now);
+
+        self.store(&mut vaults, vault)
+    }
+}
+
+fn check_label(
+    vaults: &HashMap<(u64, Address), Vault>,
+    chain_id: u64,
+    address: Address,
+    label: &str,
+) -> Result<(), VaultError> {
+    let taken = vaults.values().any(|vault| {
+        vault.chain_id == chain_id
+            && vault.address != address
+            && vault.label.as_deref() == Some(label)
+    });
+
+    if taken {
+        return Err(VaultError::LabelTaken(label.to_string()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vault_lifecycle() {
+        let registry = VaultRegistry::new();
+        let (first, second) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        registry.sync(31337, &[first, second], 10);
+        registry.sync(31337, &[first, second], 20);
+
+        let vaults = registry.list(31337);
+        assert_eq!(vaults.len(), 2);
+        assert_eq!((vaults[1].address, vaults[1].created_at), (second, 10));
+
+        let vault = registry
+            .update(31337, first, Some("alice".to_string()), None)
+            .unwrap();
+        assert_eq!(vault.label.as_deref(), Some("alice"));
+        assert_eq!(
+            registry
+                .update(31337, second, Some("alice".to_string()), None)
+                .unwrap_err(),
+            VaultError::LabelTaken("alice".to_string())
+        );
+        assert_eq!(registry.resolve(31337, "alice").unwrap().address, first);
+        assert_eq!(
+            registry
+                .resolve(31337, &format!("{:?}", second))
+                .unwrap()
+                .address,
+            second
+        );
+        assert!(registry.resolve(31337, "bob").is_none());
+
+        registry.retire(31337, first, 30).unwrap();
+        assert_eq!(
+            registry.retire(31337, first, 40).unwrap_err(),
+            VaultError::Retired
+        );
+        assert!(registry.list(1).is_empty());
+    }
+
+    #[test]
+    fn test_pending_vault_activation() {
+        let registry = VaultRegistry::new();
+        let (deployed, pending) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        registry
+            .register(Vault {
+                chain_id: 31337,
+                address: pending,
+                index: None,
+                salt: Some(H256::repeat_byte(7)),
+                label: None,
+                customer: Some("alice".to_string()),
+                status: VaultStatus::Pending,
+                created_at: 10,
+                retired_at: None,
+            })
+            .unwrap();
+        registry.sync(31337, &[deployed], 20);
+
+        assert_eq!(registry.list(31337)[1].address, pending);
+        assert_eq!(registry.pending(31337).len(), 1);
+
+        registry.sync(31337, &[deployed, pending], 30);
+        let vault = registry.get(31337, pending).unwrap();
+        assert_eq!((vault.index, vault.status), (Some(1), VaultStatus::Active));
+        assert_eq!(vault.customer.as_deref(), Some("alice"));
+    }
+
+    #[test]
+    fn test_vaults_survive_restart() {
+        let file = JsonFile::new(&crate::persist::temp_dir(), "vaults.json").unwrap();
+        let (retired, labelled) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let registry = VaultRegistry::load(file.clone()).unwrap();
+        registry.sync(31337, &[retired, labelled], 10);
+        registry.retire(31337, retired, 20).unwrap();
+        registry
+            .update(
+                31337,
+                labelled,
+                Some("alice".to_string()),
+                Some("acme".to_string()),
+            )
+            .unwrap();
+
+        let restarted = VaultRegistry::load(file).unwrap();
+        restarted.sync(31337, &[retired, labelled], 30);
+
+        let vault = restarted.get(31337, retired).unwrap();
+        assert_eq!(
+            (vault.status, vault.retired_at),
+            (VaultStatus::Retired, Some(20))
+        );
+        let vault = restarted.resolve(31337, "alice").unwrap();
+        assert_eq!(
+            (vault.address, vault.customer.as_deref()),
+            (labelled, Some("acme"))
+        );
+    }
+}
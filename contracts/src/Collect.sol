@@ -3,26 +3,103 @@ pragma solidity ^0.8.13;
 
 import "../lib/openzeppelin-contracts/contracts/access/Ownable.sol";
 import "./BaseContract.sol";
+import "./ISignatureTransfer.sol";
 
 interface IERC20 {
+    function transfer(address recipient, uint256 amount) external returns (bool);
+
     function transferFrom(
         address sender,
         address recipient,
         uint256 amount
     ) external returns (bool);
+
+    function allowance(address owner, address spender) external view returns (uint256);
+}
+
+interface IERC20Permit {
+    function permit(
+        address owner,
+        address spender,
+        uint256 value,
+        uint256 deadline,
+        uint8 v,
+        bytes32 r,
+        bytes32 s
+    ) external;
+}
+
+interface IERC3009 {
+    function transferWithAuthorization(
+        address from,
+        address to,
+        uint256 value,
+        uint256 validAfter,
+        uint256 validBefore,
+        bytes32 nonce,
+        uint8 v,
+        bytes32 r,
+        bytes32 s
+    ) external;
+
+    function authorizationState(address authorizer, bytes32 nonce) external view returns (bool);
 }
 
 contract WithdrawalContract {
+    /// @dev Deployer (the Collect contract), not a constructor argument so CREATE2 addresses only depend on the salt
+    address public immutable collector = msg.sender;
+
+    modifier onlyCollector() {
+        require(msg.sender == collector, "Not collector");
+        _;
+    }
+
     receive() external payable {}
 
-    function withdraw(address to, uint256 _amount) external {
+    function withdraw(address to, uint256 _amount) external onlyCollector {
         require(address(this).balance >= _amount, "Insufficient balance");
         (bool success, ) = to.call{value: _amount}("");
         require(success, "Transfer failed");
     }
+
+    function withdrawERC20(address token, address to, uint256 amount) external onlyCollector {
+        require(IERC20(token).transfer(to, amount), "Transfer failed");
+    }
 }
 
 contract Collect is BaseContract, Ownable {
+    struct Permit {
+        address owner;
+        uint256 value;
+        uint256 deadline;
+        uint8 v;
+        bytes32 r;
+        bytes32 s;
+    }
+
+    struct Permit2Transfer {
+        address owner;
+        ISignatureTransfer.PermitBatchTransferFrom permit;
+        bytes signature;
+    }
+
+    struct TransferAuthorization {
+        address from;
+        uint256 value;
+        uint256 validAfter;
+        uint256 validBefore;
+        bytes32 nonce;
+        uint8 v;
+        bytes32 r;
+        bytes32 s;
+    }
+
+    event WithdrawalContractCreated(address indexed withdrawalContract, uint256 index);
+    event AuthorizationSkipped(address indexed from, bytes32 indexed nonce);
+
+    error PermitFailed(address owner);
+    error AuthorizationFailed(address from);
+
     WithdrawalContract[] public withdrawalContracts;
 
     uint8 public constant WITHDRAWAL_CONTRACTS_NUMBER = 5;
@@ -31,11 +108,45 @@ contract Collect is BaseContract, Ownable {
 
     function createWithrawalContracts() external onlyOwner() {
         for (uint256 i = 0; i < WITHDRAWAL_CONTRACTS_NUMBER; i++) {
-            WithdrawalContract newContract = new WithdrawalContract();
-            withdrawalContracts.push(newContract);
+            _createWithdrawalContract();
         }
     }
 
+    /**
+     * @notice Create one more withdrawal contract (deposit vault)
+     */
+    function createWithdrawalContract() external onlyOwner returns (WithdrawalContract) {
+        return _createWithdrawalContract();
+    }
+
+    /**
+     * @notice Deploy the withdrawal contract at the CREATE2 address derived from `salt`
+     * @dev Deposits sent to the address before deployment are kept by the new contract
+     */
+    function createDeterministicWithdrawalContract(bytes32 salt) external onlyOwner returns (WithdrawalContract) {
+        return _addWithdrawalContract(new WithdrawalContract{salt: salt}());
+    }
+
+    /**
+     * @notice Address `createDeterministicWithdrawalContract(salt)` deploys to
+     */
+    function predictWithdrawalContractAddress(bytes32 salt) external view returns (address) {
+        bytes32 hash = keccak256(
+            abi.encodePacked(bytes1(0xff), address(this), salt, keccak256(type(WithdrawalContract).creationCode))
+        );
+        return address(uint160(uint256(hash)));
+    }
+
+    function _createWithdrawalContract() private returns (WithdrawalContract) {
+        return _addWithdrawalContract(new WithdrawalContract());
+    }
+
+    function _addWithdrawalContract(WithdrawalContract newContract) private returns (WithdrawalContract) {
+        withdrawalContracts.push(newContract);
+        emit WithdrawalContractCreated(address(newContract), withdrawalContracts.length - 1);
+        return newContract;
+    }
+
     function getWithdrawalContracts() public view returns (WithdrawalContract[] memory) {
         return withdrawalContracts;
     }
@@ -46,6 +157,41 @@ contract Collect is BaseContract, Ownable {
         }
     }   
 
+    /**
+     * @notice Withdraw `amounts[i]` from withdrawal contract `indexes[i]` to `receiver`
+     */
+    function collectETHTo(
+        address receiver,
+        uint256[] calldata indexes,
+        uint256[] calldata amounts
+    ) external onlyOwner {
+        require(indexes.length == amounts.length, "Length mismatch");
+
+        for (uint256 i = 0; i < indexes.length; i++) {
+            withdrawalContracts[indexes[i]].withdraw(receiver, amounts[i]);
+        }
+
+        emit TransferCompleted(msg.sender);
+    }
+
+    /**
+     * @notice Withdraw `amounts[i]` of `token` held by withdrawal contract `indexes[i]` to `receiver`
+     */
+    function collectERC20FromVaults(
+        address token,
+        address receiver,
+        uint256[] calldata indexes,
+        uint256[] calldata amounts
+    ) external onlyOwner {
+        require(indexes.length == amounts.length, "Length mismatch");
+
+        for (uint256 i = 0; i < indexes.length; i++) {
+            withdrawalContracts[indexes[i]].withdrawERC20(token, receiver, amounts[i]);
+        }
+
+        emit TransferCompleted(msg.sender);
+    }
+
     /**
      * @notice Collect ERC-20 tokens to `receiver` wallet
      * @dev Reverts with `TransferFailed` error when `erc20.transferFrom` fails
@@ -67,4 +213,92 @@ contract Collect is BaseContract, Ownable {
 
         emit TransferCompleted(msg.sender);
     }
+
+    /**
+     * @notice Collect ERC-20 tokens to `receiver` wallet using EIP-2612 permits of the senders
+     * @dev A permit that was already submitted by someone else is accepted if the allowance covers the value
+     */
+    function collectERC20WithPermit(
+        address token,
+        address receiver,
+        Permit[] calldata permits
+    ) external onlyOwner {
+        IERC20 erc20 = IERC20(token);
+
+        for (uint256 i = 0; i < permits.length; i++) {
+            Permit calldata p = permits[i];
+
+            try IERC20Permit(token).permit(p.owner, address(this), p.value, p.deadline, p.v, p.r, p.s) {
+            } catch {
+                if (erc20.allowance(p.owner, address(this)) < p.value) {
+                    revert PermitFailed(p.owner);
+                }
+            }
+
+            bool success = erc20.transferFrom(p.owner, receiver, p.value);
+            if (!success) {
+                revert TransferFailed(p.owner);
+            }
+        }
+
+        emit TransferCompleted(msg.sender);
+    }
+
+    /**
+     * @notice Collect tokens to `receiver` wallet using Permit2 batch signatures of the senders
+     * @dev Every permitted amount is transferred in full, the signatures name this contract as spender
+     */
+    function collectERC20WithPermit2(
+        ISignatureTransfer permit2,
+        address receiver,
+        Permit2Transfer[] calldata transfers
+    ) external onlyOwner {
+        for (uint256 i = 0; i < transfers.length; i++) {
+            Permit2Transfer calldata transfer = transfers[i];
+            ISignatureTransfer.SignatureTransferDetails[] memory details =
+                new ISignatureTransfer.SignatureTransferDetails[](transfer.permit.permitted.length);
+
+            for (uint256 j = 0; j < details.length; j++) {
+                details[j] = ISignatureTransfer.SignatureTransferDetails({
+                    to: receiver,
+                    requestedAmount: transfer.permit.permitted[j].amount
+                });
+            }
+
+            permit2.permitTransferFrom(transfer.permit, details, transfer.owner, transfer.signature);
+        }
+
+        emit TransferCompleted(msg.sender);
+    }
+
+    /**
+     * @notice Collect EIP-3009 tokens signed over to `receiver` with `transferWithAuthorization`
+     * @dev An authorization whose nonce is already used is skipped with `AuthorizationSkipped` instead of
+     * reverting the batch. EIP-3009 tokens only record `(from, nonce)`, so this assumes the same authorization
+     * was front-run and has paid `receiver`; a different authorization signed with the same nonce is
+     * indistinguishable here, callers check `authorizationState` beforehand and reconcile skipped transfers.
+     */
+    function collectERC20WithAuthorization(
+        address token,
+        address receiver,
+        TransferAuthorization[] calldata authorizations
+    ) external onlyOwner {
+        IERC3009 erc3009 = IERC3009(token);
+
+        for (uint256 i = 0; i < authorizations.length; i++) {
+            TransferAuthorization calldata a = authorizations[i];
+
+            try erc3009.transferWithAuthorization(
+                a.from, receiver, a.value, a.validAfter, a.validBefore, a.nonce, a.v, a.r, a.s
+            ) {
+            } catch {
+                if (!erc3009.authorizationState(a.from, a.nonce)) {
+                    revert AuthorizationFailed(a.from);
+                }
+                emit AuthorizationSkipped(a.from, a.nonce);
+            }
+        }
+
+        emit TransferCompleted(msg.sender);
+    }
 }
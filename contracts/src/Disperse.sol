@@ -1,20 +1,53 @@
 // SPDX-License-Identifier: UNLICENSED
 pragma solidity ^0.8.13;
 
+import "../lib/openzeppelin-contracts/contracts/access/Ownable.sol";
 import "./BaseContract.sol";
+import "./ISignatureTransfer.sol";
 
 interface IERC20 {
     function transfer(address recipient, uint256 amount) external returns (bool);
     function transferFrom(address from, address to, uint256 value) external returns (bool);
 }
 
-contract Disperse is BaseContract {
+interface IERC721 {
+    function safeTransferFrom(address from, address to, uint256 tokenId) external;
+}
+
+interface IERC1155 {
+    function safeTransferFrom(address from, address to, uint256 id, uint256 value, bytes calldata data) external;
+}
+
+contract Disperse is BaseContract, Ownable {
+    struct TokenLeg {
+        address token;
+        TransferData[] transfers;
+    }
+
+    struct ERC721Transfer {
+        address wallet;
+        uint256 tokenId;
+    }
+
+    struct ERC1155Transfer {
+        address wallet;
+        uint256 id;
+        uint256 amount;
+    }
+
+    /// @dev `token` is the zero address for the ETH leg
+    event LegCompleted(address indexed token, uint256 recipients, uint256 total);
+
+    error IncorrectValue(uint256 expected, uint256 received);
+
+    constructor() Ownable(msg.sender) {}
+
     /**
      * @notice Disperse ETH to multiple wallets
      * @dev Reverts with `TransferFailed` error when `call` fails
      */
     function disperseETH(TransferData[] memory transferList) external payable {
-        for (uint8 i = 0; i < transferList.length; i++) {
+        for (uint256 i = 0; i < transferList.length; i++) {
             address currentWallet = transferList[i].wallet;
             (bool sent, ) = currentWallet.call{value: transferList[i].amount}("");
             if (!sent) {
@@ -25,19 +58,121 @@ contract Disperse is BaseContract {
     }
 
     /**
-     * @notice Disperse ERC-20 tokens to multiple wallets
-     * @dev Reverts with `TransferFailed` error when `token.transfer` fails
+     * @notice Disperse ERC-20 tokens of the caller to multiple wallets
+     * @dev Tokens are pulled from the caller, so an allowance to this contract can only be spent by its owner.
+     * Reverts with `TransferFailed` error when `token.transferFrom` fails
      */
-    function disperseERC20(address tokenAddress, address sender, TransferData[] memory transferList) external {
+    function disperseERC20(address tokenAddress, TransferData[] memory transferList) external {
         IERC20 token = IERC20(tokenAddress);
 
-        for (uint8 i = 0; i < transferList.length; i++) {
+        for (uint256 i = 0; i < transferList.length; i++) {
             address currentWallet = transferList[i].wallet;
-            bool success = token.transferFrom(sender, currentWallet, transferList[i].amount);
+            bool success = token.transferFrom(msg.sender, currentWallet, transferList[i].amount);
             if (!success) {
                 revert TransferFailed(currentWallet);
             }
         }
         emit TransferCompleted(msg.sender);
     }
+
+    /**
+     * @notice Disperse tokens of `owner` to multiple wallets with a single Permit2 batch signature
+     * @dev `permit.permitted[i]` covers `transferList[i]`, the signature names this contract as spender.
+     * Permit2 signatures don't cover the recipients, so only the owner can submit them.
+     */
+    function disperseERC20WithPermit2(
+        ISignatureTransfer permit2,
+        address owner,
+        ISignatureTransfer.PermitBatchTransferFrom calldata permit,
+        TransferData[] calldata transferList,
+        bytes calldata signature
+    ) external onlyOwner {
+        ISignatureTransfer.SignatureTransferDetails[] memory details =
+            new ISignatureTransfer.SignatureTransferDetails[](transferList.length);
+
+        for (uint256 i = 0; i < transferList.length; i++) {
+            details[i] = ISignatureTransfer.SignatureTransferDetails({
+                to: transferList[i].wallet,
+                requestedAmount: transferList[i].amount
+            });
+        }
+
+        permit2.permitTransferFrom(permit, details, owner, signature);
+        emit TransferCompleted(msg.sender);
+    }
+
+    /**
+     * @notice Disperse ETH and several ERC-20 tokens in a single transaction
+     * @dev Tokens are pulled from the caller, who approves this contract for each leg's total.
+     * Reverts with `TransferFailed` error when any transfer fails, so either every leg is paid or none,
+     * and with `IncorrectValue` unless `msg.value` is the ETH leg total.
+     */
+    function disperseMulti(TransferData[] calldata ethTransfers, TokenLeg[] calldata tokenLegs) external payable {
+        _checkValue(ethTransfers);
+        if (ethTransfers.length > 0) {
+            uint256 ethTotal = 0;
+            for (uint256 i = 0; i < ethTransfers.length; i++) {
+                address currentWallet = ethTransfers[i].wallet;
+                (bool sent, ) = currentWallet.call{value: ethTransfers[i].amount}("");
+                if (!sent) {
+                    revert TransferFailed(currentWallet);
+                }
+                ethTotal += ethTransfers[i].amount;
+            }
+            emit LegCompleted(address(0), ethTransfers.length, ethTotal);
+        }
+
+        for (uint256 i = 0; i < tokenLegs.length; i++) {
+            IERC20 token = IERC20(tokenLegs[i].token);
+            TransferData[] calldata transfers = tokenLegs[i].transfers;
+            uint256 total = 0;
+            for (uint256 j = 0; j < transfers.length; j++) {
+                address currentWallet = transfers[j].wallet;
+                bool success = token.transferFrom(msg.sender, currentWallet, transfers[j].amount);
+                if (!success) {
+                    revert TransferFailed(currentWallet);
+                }
+                total += transfers[j].amount;
+            }
+            emit LegCompleted(tokenLegs[i].token, transfers.length, total);
+        }
+        emit TransferCompleted(msg.sender);
+    }
+
+    /**
+     * @notice Disperse ERC-721 tokens of the caller to multiple wallets
+     * @dev The caller approves this contract with `setApprovalForAll`. Uses `safeTransferFrom`,
+     * so contract recipients must implement `onERC721Received`.
+     */
+    function disperseERC721(address tokenAddress, ERC721Transfer[] calldata transferList) external {
+        IERC721 token = IERC721(tokenAddress);
+
+        for (uint256 i = 0; i < transferList.length; i++) {
+            token.safeTransferFrom(msg.sender, transferList[i].wallet, transferList[i].tokenId);
+        }
+        emit TransferCompleted(msg.sender);
+    }
+
+    /**
+     * @notice Disperse ERC-1155 tokens of the caller to multiple wallets
+     * @dev The caller approves this contract with `setApprovalForAll`
+     */
+    function disperseERC1155(address tokenAddress, ERC1155Transfer[] calldata transferList) external {
+        IERC1155 token = IERC1155(tokenAddress);
+
+        for (uint256 i = 0; i < transferList.length; i++) {
+            token.safeTransferFrom(msg.sender, transferList[i].wallet, transferList[i].id, transferList[i].amount, "");
+        }
+        emit TransferCompleted(msg.sender);
+    }
+
+    function _checkValue(TransferData[] calldata transferList) private view {
+        uint256 total = 0;
+        for (uint256 i = 0; i < transferList.length; i++) {
+            total += transferList[i].amount;
+        }
+        if (msg.value != total) {
+            revert IncorrectValue(total, msg.value);
+        }
+    }
 }
@@ -0,0 +1,11 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import "../lib/openzeppelin-contracts/contracts/token/ERC1155/ERC1155.sol";
+
+contract TestMultiToken is ERC1155 {
+    constructor(address account) ERC1155("") {
+        _mint(account, 1, 100, "");
+        _mint(account, 2, 10, "");
+    }
+}
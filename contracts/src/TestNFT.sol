@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import "../lib/openzeppelin-contracts/contracts/token/ERC721/ERC721.sol";
+
+contract TestNFT is ERC721 {
+    constructor(address account, uint256 count) ERC721("TestNFT", "TNFT") {
+        for (uint256 tokenId = 1; tokenId <= count; tokenId++) {
+            _mint(account, tokenId);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import "../lib/openzeppelin-contracts/contracts/token/ERC20/IERC20.sol";
+import "../lib/openzeppelin-contracts/contracts/token/ERC20/utils/SafeERC20.sol";
+import "../lib/openzeppelin-contracts/contracts/utils/cryptography/ECDSA.sol";
+import "./ISignatureTransfer.sol";
+
+/// @notice Mock of the Uniswap Permit2 batch signature transfer, with the same typed data, domain and unordered nonces
+contract TestPermit2 is ISignatureTransfer {
+    using SafeERC20 for IERC20;
+
+    bytes32 public constant TOKEN_PERMISSIONS_TYPEHASH = keccak256("TokenPermissions(address token,uint256 amount)");
+    bytes32 public constant PERMIT_BATCH_TRANSFER_FROM_TYPEHASH = keccak256(
+        "PermitBatchTransferFrom(TokenPermissions[] permitted,address spender,uint256 nonce,uint256 deadline)TokenPermissions(address token,uint256 amount)"
+    );
+    bytes32 private constant DOMAIN_TYPEHASH =
+        keccak256("EIP712Domain(string name,uint256 chainId,address verifyingContract)");
+
+    mapping(address => mapping(uint256 => uint256)) public nonceBitmap;
+
+    error SignatureExpired(uint256 deadline);
+    error InvalidNonce();
+    error InvalidSigner();
+    error LengthMismatch();
+    error InvalidAmount(uint256 maxAmount);
+
+    function DOMAIN_SEPARATOR() public view returns (bytes32) {
+        return keccak256(abi.encode(DOMAIN_TYPEHASH, keccak256("Permit2"), block.chainid, address(this)));
+    }
+
+    /// @dev The caller is the spender the signature has to name
+    function permitTransferFrom(
+        PermitBatchTransferFrom memory permit,
+        SignatureTransferDetails[] calldata transferDetails,
+        address owner,
+        bytes calldata signature
+    ) external {
+        if (block.timestamp > permit.deadline) {
+            revert SignatureExpired(permit.deadline);
+        }
+        if (permit.permitted.length != transferDetails.length) {
+            revert LengthMismatch();
+        }
+
+        _useUnorderedNonce(owner, permit.nonce);
+        if (ECDSA.recover(_digest(permit, msg.sender), signature) != owner) {
+            revert InvalidSigner();
+        }
+
+        for (uint256 i = 0; i < transferDetails.length; i++) {
+            uint256 requestedAmount = transferDetails[i].requestedAmount;
+            if (requestedAmount > permit.permitted[i].amount) {
+                revert InvalidAmount(permit.permitted[i].amount);
+            }
+            if (requestedAmount != 0) {
+                IERC20(permit.permitted[i].token).safeTransferFrom(owner, transferDetails[i].to, requestedAmount);
+            }
+        }
+    }
+
+    function _digest(PermitBatchTransferFrom memory permit, address spender) private view returns (bytes32) {
+        bytes32[] memory permissionHashes = new bytes32[](permit.permitted.length);
+        for (uint256 i = 0; i < permit.permitted.length; i++) {
+            permissionHashes[i] = keccak256(abi.encode(TOKEN_PERMISSIONS_TYPEHASH, permit.permitted[i]));
+        }
+        bytes32 structHash = keccak256(
+            abi.encode(
+                PERMIT_BATCH_TRANSFER_FROM_TYPEHASH,
+                keccak256(abi.encodePacked(permissionHashes)),
+                spender,
+                permit.nonce,
+                permit.deadline
+            )
+        );
+
+        return keccak256(abi.encodePacked("\x19\x01", DOMAIN_SEPARATOR(), structHash));
+    }
+
+    function _useUnorderedNonce(address owner, uint256 nonce) private {
+        uint256 bit = 1 << uint8(nonce);
+        uint256 flipped = nonceBitmap[owner][nonce >> 8] ^= bit;
+        if (flipped & bit == 0) {
+            revert InvalidNonce();
+        }
+    }
+}
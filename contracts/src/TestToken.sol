@@ -2,9 +2,10 @@
 pragma solidity ^0.8.13;
 
 import "../lib/openzeppelin-contracts/contracts/token/ERC20/ERC20.sol";
+import "../lib/openzeppelin-contracts/contracts/token/ERC20/extensions/ERC20Permit.sol";
 
-contract TestToken is ERC20 {
-    constructor(address account) ERC20("TestToken", "TT") {
+contract TestToken is ERC20, ERC20Permit {
+    constructor(address account) ERC20("TestToken", "TT") ERC20Permit("TestToken") {
         _mint(account, 100 * 10**18); 
     }
-}
\ No newline at end of file
+}
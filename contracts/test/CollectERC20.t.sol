@@ -63,4 +63,33 @@ contract CollectERCTest is Test {
         );
         collect.collectERC20(address(testToken), receiver, senders, getBigAmountsArray());
     }
+
+    function testCollectERC20FromVaults() public {
+        collect.createWithrawalContracts();
+        WithdrawalContract[] memory vaults = collect.getWithdrawalContracts();
+        vm.prank(mainAccount);
+        testToken.transfer(address(vaults[2]), 7);
+
+        uint256[] memory indexes = new uint256[](1);
+        indexes[0] = 2;
+        uint256[] memory amounts = new uint256[](1);
+        amounts[0] = 7;
+        collect.collectERC20FromVaults(address(testToken), receiver, indexes, amounts);
+
+        assertEq(testToken.balanceOf(receiver), 7);
+        assertEq(testToken.balanceOf(address(vaults[2])), 0);
+    }
+
+    function testVaultOnlyPaysCollector() public {
+        collect.createWithrawalContracts();
+        WithdrawalContract vault = collect.getWithdrawalContracts()[0];
+        vm.prank(mainAccount);
+        testToken.transfer(address(vault), 7);
+
+        vm.expectRevert("Not collector");
+        vault.withdrawERC20(address(testToken), address(this), 7);
+
+        vm.expectRevert("Not collector");
+        vault.withdraw(address(this), 0);
+    }
 }
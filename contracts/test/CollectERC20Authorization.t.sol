@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {Test} from "forge-std/Test.sol";
+import {Collect} from "../src/Collect.sol";
+import {AuthorizationToken} from "../src/AuthorizationToken.sol";
+
+contract CollectERCAuthorizationTest is Test {
+    Collect collect;
+    AuthorizationToken token;
+    address mainAccount = address(0x1);
+    uint256[] senderKeys = [0xA11CE, 0xB0B];
+    address receiver = address(0x5);
+
+    function setUp() public {
+        collect = new Collect();
+        token = new AuthorizationToken(mainAccount);
+
+        for (uint256 i = 0; i < senderKeys.length; i++) {
+            vm.prank(mainAccount);
+            token.transfer(vm.addr(senderKeys[i]), 20);
+        }
+    }
+
+    function signAuthorization(uint256 key, address to, uint256 value, bytes32 nonce)
+        private
+        view
+        returns (Collect.TransferAuthorization memory)
+    {
+        address from = vm.addr(key);
+        uint256 validBefore = block.timestamp + 1 hours;
+        bytes32 structHash = keccak256(
+            abi.encode(token.TRANSFER_WITH_AUTHORIZATION_TYPEHASH(), from, to, value, 0, validBefore, nonce)
+        );
+        bytes32 digest = keccak256(abi.encodePacked("\x19\x01", token.DOMAIN_SEPARATOR(), structHash));
+        (uint8 v, bytes32 r, bytes32 s) = vm.sign(key, digest);
+
+        return Collect.TransferAuthorization(from, value, 0, validBefore, nonce, v, r, s);
+    }
+
+    function testCollectERCAuthorizationSuccess() public {
+        Collect.TransferAuthorization[] memory authorizations = new Collect.TransferAuthorization[](2);
+        authorizations[0] = signAuthorization(senderKeys[0], receiver, 5, keccak256("a"));
+        authorizations[1] = signAuthorization(senderKeys[1], receiver, 7, keccak256("b"));
+
+        collect.collectERC20WithAuthorization(address(token), receiver, authorizations);
+
+        assertEq(token.balanceOf(receiver), 12);
+        assertTrue(token.authorizationState(vm.addr(senderKeys[0]), keccak256("a")));
+    }
+
+    function testFrontRunAuthorization() public {
+        Collect.TransferAuthorization[] memory authorizations = new Collect.TransferAuthorization[](1);
+        authorizations[0] = signAuthorization(senderKeys[0], receiver, 5, keccak256("a"));
+
+        Collect.TransferAuthorization memory a = authorizations[0];
+        token.transferWithAuthorization(a.from, receiver, a.value, a.validAfter, a.validBefore, a.nonce, a.v, a.r, a.s);
+
+        vm.expectEmit(true, true, false, false);
+        emit Collect.AuthorizationSkipped(a.from, a.nonce);
+        collect.collectERC20WithAuthorization(address(token), receiver, authorizations);
+
+        assertEq(token.balanceOf(receiver), 5);
+    }
+
+    function testNonceUsedByOtherAuthorization() public {
+        Collect.TransferAuthorization[] memory authorizations = new Collect.TransferAuthorization[](1);
+        authorizations[0] = signAuthorization(senderKeys[0], receiver, 5, keccak256("a"));
+
+        // the sender spends the nonce on a transfer to someone else, which the token can't tell apart
+        Collect.TransferAuthorization memory other = signAuthorization(senderKeys[0], address(0x6), 5, keccak256("a"));
+        token.transferWithAuthorization(
+            other.from, address(0x6), other.value, other.validAfter, other.validBefore, other.nonce, other.v, other.r, other.s
+        );
+
+        vm.expectEmit(true, true, false, false);
+        emit Collect.AuthorizationSkipped(other.from, other.nonce);
+        collect.collectERC20WithAuthorization(address(token), receiver, authorizations);
+
+        assertEq(token.balanceOf(receiver), 0, "The skipped authorization paid nothing");
+    }
+
+    function testAuthorizationForOtherReceiver() public {
+        Collect.TransferAuthorization[] memory authorizations = new Collect.TransferAuthorization[](1);
+        authorizations[0] = signAuthorization(senderKeys[0], address(0x6), 5, keccak256("a"));
+
+        vm.expectRevert(abi.encodeWithSelector(Collect.AuthorizationFailed.selector, vm.addr(senderKeys[0])));
+        collect.collectERC20WithAuthorization(address(token), receiver, authorizations);
+    }
+}
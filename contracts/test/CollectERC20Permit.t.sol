@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {Test} from "forge-std/Test.sol";
+import {Collect} from "../src/Collect.sol";
+import {TestToken} from "../src/TestToken.sol";
+
+contract CollectERCPermitTest is Test {
+    bytes32 constant PERMIT_TYPEHASH =
+        keccak256("Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)");
+
+    Collect collect;
+    TestToken testToken;
+    address mainAccount = address(0x1);
+    uint256[] senderKeys = [0xA11CE, 0xB0B];
+    address receiver = address(0x5);
+
+    function setUp() public {
+        collect = new Collect();
+        testToken = new TestToken(mainAccount);
+
+        for (uint256 i = 0; i < senderKeys.length; i++) {
+            vm.prank(mainAccount);
+            testToken.transfer(vm.addr(senderKeys[i]), 20);
+        }
+    }
+
+    function signPermit(uint256 key, uint256 value) private view returns (Collect.Permit memory) {
+        address owner = vm.addr(key);
+        uint256 deadline = block.timestamp + 1 hours;
+        bytes32 structHash = keccak256(
+            abi.encode(PERMIT_TYPEHASH, owner, address(collect), value, testToken.nonces(owner), deadline)
+        );
+        bytes32 digest = keccak256(abi.encodePacked("\x19\x01", testToken.DOMAIN_SEPARATOR(), structHash));
+        (uint8 v, bytes32 r, bytes32 s) = vm.sign(key, digest);
+
+        return Collect.Permit(owner, value, deadline, v, r, s);
+    }
+
+    function testCollectERCPermitSuccess() public {
+        Collect.Permit[] memory permits = new Collect.Permit[](2);
+        permits[0] = signPermit(senderKeys[0], 5);
+        permits[1] = signPermit(senderKeys[1], 7);
+
+        collect.collectERC20WithPermit(address(testToken), receiver, permits);
+
+        assertEq(testToken.balanceOf(receiver), 12);
+        assertEq(testToken.allowance(vm.addr(senderKeys[0]), address(collect)), 0);
+    }
+
+    function testFrontRunPermit() public {
+        Collect.Permit[] memory permits = new Collect.Permit[](1);
+        permits[0] = signPermit(senderKeys[0], 5);
+
+        Collect.Permit memory p = permits[0];
+        testToken.permit(p.owner, address(collect), p.value, p.deadline, p.v, p.r, p.s);
+
+        collect.collectERC20WithPermit(address(testToken), receiver, permits);
+
+        assertEq(testToken.balanceOf(receiver), 5);
+    }
+
+    function testInvalidPermit() public {
+        Collect.Permit[] memory permits = new Collect.Permit[](1);
+        permits[0] = signPermit(senderKeys[0], 5);
+        permits[0].value = 6;
+
+        vm.expectRevert(abi.encodeWithSelector(Collect.PermitFailed.selector, vm.addr(senderKeys[0])));
+        collect.collectERC20WithPermit(address(testToken), receiver, permits);
+    }
+}
@@ -5,6 +5,7 @@ import {Test} from "forge-std/Test.sol";
 import {Collect} from "../src/Collect.sol";
 import {WithdrawalContract} from "../src/Collect.sol";
 import {BaseContract} from "../src/BaseContract.sol";
+import {Ownable} from "../lib/openzeppelin-contracts/contracts/access/Ownable.sol";
 
 contract CollectETHTest is Test {
     Collect collect;
@@ -29,6 +30,30 @@ contract CollectETHTest is Test {
         assertEq(collect.getWithdrawalContracts().length, 5);
     }
 
+    function testCreateWithdrawalContract() public {
+        vm.prank(address(0x1));
+        collect.createWithrawalContracts();
+
+        vm.prank(address(0x1));
+        WithdrawalContract created = collect.createWithdrawalContract();
+
+        assertEq(collect.getWithdrawalContracts().length, 6);
+        assertEq(address(collect.withdrawalContracts(5)), address(created));
+    }
+
+    function testCreateDeterministicWithdrawalContract() public {
+        bytes32 salt = keccak256("customer-1");
+        address predicted = collect.predictWithdrawalContractAddress(salt);
+        payable(predicted).transfer(1 ether);
+
+        vm.prank(address(0x1));
+        WithdrawalContract created = collect.createDeterministicWithdrawalContract(salt);
+
+        assertEq(address(created), predicted);
+        assertEq(address(created).balance, 1 ether);
+        assertEq(address(collect.withdrawalContracts(0)), predicted);
+    }
+
     function testCollectFundsContracts() public {
         vm.prank(address(0x1));
         collect.createWithrawalContracts();
@@ -69,6 +94,51 @@ contract CollectETHTest is Test {
         assertEq(address(0x1).balance, 7 * DECIMALS);
     }
 
+    function testCollectETHToReceiver() public {
+        vm.prank(address(0x1));
+        collect.createWithrawalContracts();
+
+        WithdrawalContract[] memory generatedContracts = collect
+            .getWithdrawalContracts();
+        payable(address(generatedContracts[1])).transfer(3 ether);
+        payable(address(generatedContracts[3])).transfer(2 ether);
+
+        uint256[] memory indexes = new uint256[](2);
+        indexes[0] = 3;
+        indexes[1] = 1;
+        uint256[] memory amounts = new uint256[](2);
+        amounts[0] = 2 ether;
+        amounts[1] = 1 ether;
+
+        vm.prank(address(0x1));
+        collect.collectETHTo(address(0x5), indexes, amounts);
+
+        assertEq(address(0x5).balance, 3 ether);
+        assertEq(address(generatedContracts[1]).balance, 2 ether);
+        assertEq(address(generatedContracts[3]).balance, 0);
+    }
+
+    function testVaultETHOnlyPaysCollector() public {
+        vm.prank(address(0x1));
+        collect.createWithrawalContracts();
+        WithdrawalContract vault = collect.getWithdrawalContracts()[0];
+        payable(address(vault)).transfer(3 ether);
+
+        assertEq(vault.collector(), address(collect));
+
+        // not even the Collect owner can bypass collectETHTo
+        vm.prank(address(0x1));
+        vm.expectRevert("Not collector");
+        vault.withdraw(address(0x1), 3 ether);
+
+        assertEq(address(vault).balance, 3 ether);
+    }
+
+    function testCollectETHToOnlyOwner() public {
+        vm.expectRevert(abi.encodeWithSelector(Ownable.OwnableUnauthorizedAccount.selector, address(this)));
+        collect.collectETHTo(address(0x5), new uint256[](0), new uint256[](0));
+    }
+
     function getAmountArray() private pure returns (uint256[] memory) {
         uint256[] memory amountArray = new uint256[](5);
         amountArray[0] = 1 * DECIMALS;
@@ -40,6 +40,17 @@ contract DisperseETHTest is Test {
         assertEq(address(recipient2).balance, 2 ether);
     }
 
+    function testDisperseMoreThan255Recipients() public {
+        Disperse.TransferData[] memory transferList = new Disperse.TransferData[](300);
+        for (uint256 i = 0; i < transferList.length; i++) {
+            transferList[i] = BaseContract.TransferData({ wallet: address(uint160(0x1000 + i)), amount: 1 });
+        }
+
+        disperse.disperseETH{value: 300}(transferList);
+
+        assertEq(address(uint160(0x1000 + 299)).balance, 1);
+    }
+
     function testRevertingContract() public {
         Disperse.TransferData[] memory transferList = new Disperse.TransferData[](1);
         transferList[0] = BaseContract.TransferData({ wallet: address(revertingContract), amount: 1 ether });
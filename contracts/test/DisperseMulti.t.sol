@@ -0,0 +1,104 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {Test, console} from "forge-std/Test.sol";
+import {Disperse} from "../src/Disperse.sol";
+import {BaseContract} from "../src/BaseContract.sol";
+import {TestToken} from "../src/TestToken.sol";
+
+contract DisperseMultiTest is Test {
+    Disperse disperse;
+    TestToken usd;
+    TestToken eur;
+    address mainAccount = address(0x1);
+    address[] recipients = [address(0x2), address(0x3)];
+
+    uint256 public constant DECIMALS = 10 ** 18;
+
+    function setUp() public {
+        disperse = new Disperse();
+        usd = new TestToken(address(mainAccount));
+        eur = new TestToken(address(mainAccount));
+        vm.deal(mainAccount, 10 ether);
+        vm.startPrank(mainAccount);
+        usd.approve(address(disperse), 100 * DECIMALS);
+        eur.approve(address(disperse), 100 * DECIMALS);
+        vm.stopPrank();
+    }
+
+    function transfers(uint256 first, uint256 second) internal view returns (BaseContract.TransferData[] memory) {
+        BaseContract.TransferData[] memory transferList = new BaseContract.TransferData[](2);
+        transferList[0] = BaseContract.TransferData({wallet: recipients[0], amount: first});
+        transferList[1] = BaseContract.TransferData({wallet: recipients[1], amount: second});
+        return transferList;
+    }
+
+    function testDisperseMultiSuccess() public {
+        Disperse.TokenLeg[] memory legs = new Disperse.TokenLeg[](2);
+        legs[0] = Disperse.TokenLeg({token: address(usd), transfers: transfers(10 * DECIMALS, 20 * DECIMALS)});
+        legs[1] = Disperse.TokenLeg({token: address(eur), transfers: transfers(5 * DECIMALS, 15 * DECIMALS)});
+
+        vm.expectEmit(true, false, false, true);
+        emit Disperse.LegCompleted(address(0), 2, 3 ether);
+        vm.expectEmit(true, false, false, true);
+        emit Disperse.LegCompleted(address(usd), 2, 30 * DECIMALS);
+        vm.expectEmit(true, false, false, true);
+        emit Disperse.LegCompleted(address(eur), 2, 20 * DECIMALS);
+
+        vm.prank(mainAccount);
+        disperse.disperseMulti{value: 3 ether}(transfers(1 ether, 2 ether), legs);
+
+        assertEq(recipients[0].balance, 1 ether);
+        assertEq(recipients[1].balance, 2 ether);
+        assertEq(usd.balanceOf(recipients[0]), 10 * DECIMALS);
+        assertEq(usd.balanceOf(recipients[1]), 20 * DECIMALS);
+        assertEq(eur.balanceOf(recipients[0]), 5 * DECIMALS);
+        assertEq(eur.balanceOf(recipients[1]), 15 * DECIMALS);
+    }
+
+    function testFailedLegRevertsEveryLeg() public {
+        Disperse.TokenLeg[] memory legs = new Disperse.TokenLeg[](2);
+        legs[0] = Disperse.TokenLeg({token: address(usd), transfers: transfers(10 * DECIMALS, 20 * DECIMALS)});
+        legs[1] = Disperse.TokenLeg({token: address(eur), transfers: transfers(50 * DECIMALS, 60 * DECIMALS)});
+
+        vm.prank(mainAccount);
+        vm.expectRevert();
+        disperse.disperseMulti{value: 3 ether}(transfers(1 ether, 2 ether), legs);
+
+        assertEq(recipients[0].balance, 0);
+        assertEq(usd.balanceOf(recipients[0]), 0);
+    }
+
+    function testOverpaidValueReverts() public {
+        vm.prank(mainAccount);
+        vm.expectRevert(abi.encodeWithSelector(Disperse.IncorrectValue.selector, 3 ether, 4 ether));
+        disperse.disperseMulti{value: 4 ether}(transfers(1 ether, 2 ether), new Disperse.TokenLeg[](0));
+    }
+
+    function testUnderpaidValueReverts() public {
+        vm.deal(address(disperse), 5 ether);
+
+        vm.prank(mainAccount);
+        vm.expectRevert(abi.encodeWithSelector(Disperse.IncorrectValue.selector, 3 ether, 1 ether));
+        disperse.disperseMulti{value: 1 ether}(transfers(1 ether, 2 ether), new Disperse.TokenLeg[](0));
+
+        assertEq(recipients[0].balance, 0);
+    }
+
+    function testValueWithoutEthLegReverts() public {
+        Disperse.TokenLeg[] memory legs = new Disperse.TokenLeg[](1);
+        legs[0] = Disperse.TokenLeg({token: address(usd), transfers: transfers(10 * DECIMALS, 20 * DECIMALS)});
+
+        vm.prank(mainAccount);
+        vm.expectRevert(abi.encodeWithSelector(Disperse.IncorrectValue.selector, 0, 1 ether));
+        disperse.disperseMulti{value: 1 ether}(new BaseContract.TransferData[](0), legs);
+    }
+
+    function testTokensArePulledFromCaller() public {
+        Disperse.TokenLeg[] memory legs = new Disperse.TokenLeg[](1);
+        legs[0] = Disperse.TokenLeg({token: address(usd), transfers: transfers(10 * DECIMALS, 20 * DECIMALS)});
+
+        vm.expectRevert();
+        disperse.disperseMulti(new BaseContract.TransferData[](0), legs);
+    }
+}
@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {Test, console} from "forge-std/Test.sol";
+import {Disperse} from "../src/Disperse.sol";
+import {TestNFT} from "../src/TestNFT.sol";
+import {TestMultiToken} from "../src/TestMultiToken.sol";
+
+contract DisperseNFTTest is Test {
+    Disperse disperse;
+    TestNFT nft;
+    TestMultiToken multiToken;
+    address mainAccount = address(0x1);
+    address[] recipients = [address(0x2), address(0x3)];
+
+    function setUp() public {
+        disperse = new Disperse();
+        nft = new TestNFT(mainAccount, 3);
+        multiToken = new TestMultiToken(mainAccount);
+    }
+
+    function testDisperseERC721Success() public {
+        vm.prank(mainAccount);
+        nft.setApprovalForAll(address(disperse), true);
+
+        Disperse.ERC721Transfer[] memory transferList = new Disperse.ERC721Transfer[](2);
+        transferList[0] = Disperse.ERC721Transfer({wallet: recipients[0], tokenId: 1});
+        transferList[1] = Disperse.ERC721Transfer({wallet: recipients[1], tokenId: 3});
+
+        vm.prank(mainAccount);
+        disperse.disperseERC721(address(nft), transferList);
+
+        assertEq(nft.ownerOf(1), recipients[0]);
+        assertEq(nft.ownerOf(2), mainAccount);
+        assertEq(nft.ownerOf(3), recipients[1]);
+    }
+
+    function testDisperseERC721WithoutApproval() public {
+        Disperse.ERC721Transfer[] memory transferList = new Disperse.ERC721Transfer[](1);
+        transferList[0] = Disperse.ERC721Transfer({wallet: recipients[0], tokenId: 1});
+
+        vm.prank(mainAccount);
+        vm.expectRevert();
+        disperse.disperseERC721(address(nft), transferList);
+    }
+
+    function testDisperseERC721OfAnotherOwner() public {
+        vm.prank(mainAccount);
+        nft.setApprovalForAll(address(disperse), true);
+
+        Disperse.ERC721Transfer[] memory transferList = new Disperse.ERC721Transfer[](1);
+        transferList[0] = Disperse.ERC721Transfer({wallet: recipients[0], tokenId: 1});
+
+        vm.prank(recipients[1]);
+        vm.expectRevert();
+        disperse.disperseERC721(address(nft), transferList);
+    }
+
+    function testDisperseERC1155Success() public {
+        vm.prank(mainAccount);
+        multiToken.setApprovalForAll(address(disperse), true);
+
+        Disperse.ERC1155Transfer[] memory transferList = new Disperse.ERC1155Transfer[](3);
+        transferList[0] = Disperse.ERC1155Transfer({wallet: recipients[0], id: 1, amount: 40});
+        transferList[1] = Disperse.ERC1155Transfer({wallet: recipients[1], id: 1, amount: 10});
+        transferList[2] = Disperse.ERC1155Transfer({wallet: recipients[1], id: 2, amount: 5});
+
+        vm.prank(mainAccount);
+        disperse.disperseERC1155(address(multiToken), transferList);
+
+        assertEq(multiToken.balanceOf(recipients[0], 1), 40);
+        assertEq(multiToken.balanceOf(recipients[1], 1), 10);
+        assertEq(multiToken.balanceOf(recipients[1], 2), 5);
+        assertEq(multiToken.balanceOf(mainAccount, 1), 50);
+    }
+
+    function testDisperseERC1155InsufficientBalance() public {
+        vm.prank(mainAccount);
+        multiToken.setApprovalForAll(address(disperse), true);
+
+        Disperse.ERC1155Transfer[] memory transferList = new Disperse.ERC1155Transfer[](1);
+        transferList[0] = Disperse.ERC1155Transfer({wallet: recipients[0], id: 2, amount: 11});
+
+        vm.prank(mainAccount);
+        vm.expectRevert();
+        disperse.disperseERC1155(address(multiToken), transferList);
+    }
+}
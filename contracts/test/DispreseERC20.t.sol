@@ -10,6 +10,7 @@ contract DisperseERC20Test is Test {
     Disperse disperse;
     TestToken testToken;
     address mainAccount = address(0x1);
+    address attacker = address(0x5);
     address[] recipients = [address(0x2), address(0x3), address(0x4)];
 
     uint256 public constant DECIMALS = 10 ** 18;
@@ -28,7 +29,8 @@ contract DisperseERC20Test is Test {
         transferList[0] = BaseContract.TransferData({wallet: recipients[0], amount: 10 * DECIMALS});
         transferList[1] = BaseContract.TransferData({wallet: recipients[1], amount: 20 * DECIMALS});
 
-        disperse.disperseERC20(address(testToken), mainAccount, transferList);
+        vm.prank(mainAccount);
+        disperse.disperseERC20(address(testToken), transferList);
 
         assertEq(testToken.balanceOf(recipients[0]), 10 * DECIMALS, "Recipient 1 should have 10 tokens");
         assertEq(testToken.balanceOf(recipients[1]), 20 * DECIMALS, "Recipient 2 should have 20 tokens");
@@ -40,7 +42,21 @@ contract DisperseERC20Test is Test {
         transferList[1] = BaseContract.TransferData({wallet: recipients[1], amount: 40 * DECIMALS});
         transferList[2] = BaseContract.TransferData({wallet: recipients[2], amount: 20 * DECIMALS});
 
+        vm.prank(mainAccount);
         vm.expectRevert();
-        disperse.disperseERC20(mainAccount, address(testToken), transferList);
+        disperse.disperseERC20(address(testToken), transferList);
+    }
+
+    function testAllowanceCannotBeSpentByOthers() public {
+        Disperse.TransferData[] memory transferList = new Disperse.TransferData[](1);
+        transferList[0] = BaseContract.TransferData({wallet: attacker, amount: 10 * DECIMALS});
+
+        // the standing allowance belongs to mainAccount, the attacker has neither balance nor allowance
+        vm.prank(attacker);
+        vm.expectRevert();
+        disperse.disperseERC20(address(testToken), transferList);
+
+        assertEq(testToken.balanceOf(attacker), 0, "Attacker should not receive tokens");
+        assertEq(testToken.balanceOf(mainAccount), 100 * DECIMALS, "MainAccount should keep its tokens");
     }
 }
\ No newline at end of file
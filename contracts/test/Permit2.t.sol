@@ -0,0 +1,163 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {Test} from "forge-std/Test.sol";
+import {Disperse} from "../src/Disperse.sol";
+import {Collect} from "../src/Collect.sol";
+import {BaseContract} from "../src/BaseContract.sol";
+import {ISignatureTransfer} from "../src/ISignatureTransfer.sol";
+import {TestPermit2} from "../src/TestPermit2.sol";
+import {TestToken} from "../src/TestToken.sol";
+
+contract Permit2Test is Test {
+    TestPermit2 permit2;
+    Disperse disperse;
+    Collect collect;
+    TestToken testToken;
+    address mainAccount = address(0x1);
+    uint256[] ownerKeys = [0xA11CE, 0xB0B];
+    address[] recipients = [address(0x2), address(0x3)];
+    address receiver = address(0x5);
+
+    function setUp() public {
+        permit2 = new TestPermit2();
+        disperse = new Disperse();
+        collect = new Collect();
+        testToken = new TestToken(mainAccount);
+
+        for (uint256 i = 0; i < ownerKeys.length; i++) {
+            address owner = vm.addr(ownerKeys[i]);
+            vm.prank(mainAccount);
+            testToken.transfer(owner, 20);
+            vm.prank(owner);
+            testToken.approve(address(permit2), type(uint256).max);
+        }
+    }
+
+    function permitBatch(uint256[] memory amounts, uint256 nonce)
+        private
+        view
+        returns (ISignatureTransfer.PermitBatchTransferFrom memory permit)
+    {
+        permit.permitted = new ISignatureTransfer.TokenPermissions[](amounts.length);
+        for (uint256 i = 0; i < amounts.length; i++) {
+            permit.permitted[i] = ISignatureTransfer.TokenPermissions({token: address(testToken), amount: amounts[i]});
+        }
+        permit.nonce = nonce;
+        permit.deadline = block.timestamp + 1 hours;
+    }
+
+    function sign(uint256 key, ISignatureTransfer.PermitBatchTransferFrom memory permit, address spender)
+        private
+        view
+        returns (bytes memory)
+    {
+        bytes32[] memory permissionHashes = new bytes32[](permit.permitted.length);
+        for (uint256 i = 0; i < permit.permitted.length; i++) {
+            permissionHashes[i] = keccak256(abi.encode(permit2.TOKEN_PERMISSIONS_TYPEHASH(), permit.permitted[i]));
+        }
+        bytes32 structHash = keccak256(
+            abi.encode(
+                permit2.PERMIT_BATCH_TRANSFER_FROM_TYPEHASH(),
+                keccak256(abi.encodePacked(permissionHashes)),
+                spender,
+                permit.nonce,
+                permit.deadline
+            )
+        );
+        bytes32 digest = keccak256(abi.encodePacked("\x19\x01", permit2.DOMAIN_SEPARATOR(), structHash));
+        (uint8 v, bytes32 r, bytes32 s) = vm.sign(key, digest);
+
+        return abi.encodePacked(r, s, v);
+    }
+
+    function transferList(uint256[] memory amounts) private view returns (Disperse.TransferData[] memory list) {
+        list = new Disperse.TransferData[](amounts.length);
+        for (uint256 i = 0; i < amounts.length; i++) {
+            list[i] = BaseContract.TransferData({wallet: recipients[i], amount: amounts[i]});
+        }
+    }
+
+    function testDisperseWithPermit2() public {
+        uint256[] memory amounts = new uint256[](2);
+        amounts[0] = 5;
+        amounts[1] = 7;
+        ISignatureTransfer.PermitBatchTransferFrom memory permit = permitBatch(amounts, 1);
+        bytes memory signature = sign(ownerKeys[0], permit, address(disperse));
+
+        disperse.disperseERC20WithPermit2(permit2, vm.addr(ownerKeys[0]), permit, transferList(amounts), signature);
+
+        assertEq(testToken.balanceOf(recipients[0]), 5, "Recipient 1 should have 5 tokens");
+        assertEq(testToken.balanceOf(recipients[1]), 7, "Recipient 2 should have 7 tokens");
+        assertEq(testToken.balanceOf(vm.addr(ownerKeys[0])), 8, "Owner should have 8 tokens left");
+    }
+
+    function testDisperseWithPermit2OnlyOwner() public {
+        uint256[] memory amounts = new uint256[](1);
+        amounts[0] = 5;
+        ISignatureTransfer.PermitBatchTransferFrom memory permit = permitBatch(amounts, 1);
+        bytes memory signature = sign(ownerKeys[0], permit, address(disperse));
+
+        // the signature doesn't cover the recipients, so anyone else could redirect the payout
+        vm.prank(receiver);
+        vm.expectRevert();
+        disperse.disperseERC20WithPermit2(permit2, vm.addr(ownerKeys[0]), permit, transferList(amounts), signature);
+    }
+
+    function testDisperseWithPermit2ReusedNonce() public {
+        uint256[] memory amounts = new uint256[](1);
+        amounts[0] = 5;
+        ISignatureTransfer.PermitBatchTransferFrom memory permit = permitBatch(amounts, 1);
+        bytes memory signature = sign(ownerKeys[0], permit, address(disperse));
+
+        disperse.disperseERC20WithPermit2(permit2, vm.addr(ownerKeys[0]), permit, transferList(amounts), signature);
+
+        vm.expectRevert(TestPermit2.InvalidNonce.selector);
+        disperse.disperseERC20WithPermit2(permit2, vm.addr(ownerKeys[0]), permit, transferList(amounts), signature);
+    }
+
+    function testDisperseWithPermit2WrongSpender() public {
+        uint256[] memory amounts = new uint256[](1);
+        amounts[0] = 5;
+        ISignatureTransfer.PermitBatchTransferFrom memory permit = permitBatch(amounts, 1);
+        bytes memory signature = sign(ownerKeys[0], permit, address(collect));
+
+        vm.expectRevert(TestPermit2.InvalidSigner.selector);
+        disperse.disperseERC20WithPermit2(permit2, vm.addr(ownerKeys[0]), permit, transferList(amounts), signature);
+    }
+
+    function testCollectWithPermit2() public {
+        Collect.Permit2Transfer[] memory transfers = new Collect.Permit2Transfer[](2);
+        for (uint256 i = 0; i < ownerKeys.length; i++) {
+            uint256[] memory amounts = new uint256[](1);
+            amounts[0] = 10 + i;
+            ISignatureTransfer.PermitBatchTransferFrom memory permit = permitBatch(amounts, 1);
+            transfers[i] = Collect.Permit2Transfer({
+                owner: vm.addr(ownerKeys[i]),
+                permit: permit,
+                signature: sign(ownerKeys[i], permit, address(collect))
+            });
+        }
+
+        collect.collectERC20WithPermit2(permit2, receiver, transfers);
+
+        assertEq(testToken.balanceOf(receiver), 21, "Receiver should have 21 tokens");
+        assertEq(permit2.nonceBitmap(vm.addr(ownerKeys[0]), 0), 2, "Nonce 1 should be used");
+    }
+
+    function testCollectWithPermit2Expired() public {
+        uint256[] memory amounts = new uint256[](1);
+        amounts[0] = 10;
+        ISignatureTransfer.PermitBatchTransferFrom memory permit = permitBatch(amounts, 1);
+        Collect.Permit2Transfer[] memory transfers = new Collect.Permit2Transfer[](1);
+        transfers[0] = Collect.Permit2Transfer({
+            owner: vm.addr(ownerKeys[0]),
+            permit: permit,
+            signature: sign(ownerKeys[0], permit, address(collect))
+        });
+
+        vm.warp(permit.deadline + 1);
+        vm.expectRevert(abi.encodeWithSelector(TestPermit2.SignatureExpired.selector, permit.deadline));
+        collect.collectERC20WithPermit2(permit2, receiver, transfers);
+    }
+}